@@ -2,17 +2,16 @@ extern crate ds;
 
 use ds::*;
 
-use std::thread;
-use std::time::Duration;
-
 #[tokio::main]
-async fn main() {
-    let ds = DriverStation::new_team(4533, Alliance::new_red(1)).await;
+async fn main() -> Result<()> {
+    let ds = DriverStation::new_team(4533, Alliance::new_red(1)).await?;
 
-    thread::sleep(Duration::from_millis(1500));
+    let mut trace = ds.watch_trace();
     loop {
-        println!("Code: {}", ds.trace().await.is_code_started());
+        println!("Code: {}", trace.borrow_and_update().is_code_started());
 
-        thread::sleep(Duration::from_millis(20));
+        if trace.changed().await.is_err() {
+            return Ok(());
+        }
     }
 }
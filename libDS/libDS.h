@@ -64,8 +64,9 @@ typedef struct Alliance Alliance;
 /**
  * Represents a connection to the roboRIO acting as a driver station
  *
- * This struct will contain relevant functions to update the state of the robot,
- * and also manages the threads that manage network connections and joysticks
+ * Each driver station runs its network tasks on a runtime of its own, which is stopped when it is destroyed.
+ * Functions taking a driver station block the calling thread until they have completed, and so must not be called
+ * from within the callbacks registered with it.
  */
 typedef struct DriverStation DriverStation;
 
@@ -73,13 +74,56 @@ typedef struct {
   const char *message;
 } StdoutMessage;
 
+/**
+ * A snapshot of the state of a driver station and the robot it is connected to
+ */
+typedef struct {
+  /**
+   * When the snapshot was taken, in milliseconds since the Unix epoch
+   */
+  uint64_t timestamp;
+  bool connected;
+  bool tcp_connected;
+  bool enabled;
+  bool estopped;
+  Mode mode;
+  /**
+   * The bits of the last Trace received from the robot
+   */
+  uint8_t trace;
+  float battery_voltage;
+  /**
+   * Control packets sent per second
+   */
+  double sent_rate;
+  /**
+   * Status packets received per second
+   */
+  double recv_rate;
+  double jitter_ms;
+  uint64_t received;
+  uint64_t lost;
+  /**
+   * The time taken for the robot to respond to a control packet, or -1 if it isn't known yet
+   */
+  double trip_time_ms;
+  /**
+   * How many control packets have been sent more than a full period after they were due
+   */
+  uint64_t late_ticks;
+} Telemetry;
+
 /**
  * Constructs a new Alliance representing a Blue alliance robot of the given position
+ *
+ * This function will return NULL if position is not between 1 and 3
  */
 Alliance *DS_Alliance_new_blue(uint8_t position);
 
 /**
  * Constructs a new Alliance representing a Red alliance robot of the given position
+ *
+ * This function will return NULL if position is not between 1 and 3
  */
 Alliance *DS_Alliance_new_red(uint8_t position);
 
@@ -92,7 +136,7 @@ Alliance *DS_Alliance_new_red(uint8_t position);
 float DS_DriverStation_battery_voltage(const DriverStation *ds);
 
 /**
- * Safely frees a given DriverStation.
+ * Disables the robot and frees a given DriverStation, once its final disabled packet has been sent
  *
  * This function should only be passed pointers that were allocated via DS_DriverStation_new_team or DS_DriverStation_new_ip
  */
@@ -108,9 +152,11 @@ void DS_DriverStation_disable(DriverStation *ds);
 /**
  * Enables the robot connected to the given ds
  *
- * This function does nothing if ds is NULL
+ * This function will return -1 if ds is NULL
+ * It will return 1 if the robot couldn't be enabled, because an interlock is configured and hasn't been armed
+ * It will return 0 on a success.
  */
-void DS_DriverStation_enable(DriverStation *ds);
+int8_t DS_DriverStation_enable(DriverStation *ds);
 
 /**
  * Checks whether the given DS is enabling its connected robot
@@ -160,7 +206,7 @@ uint32_t DS_DriverStation_get_team_number(const DriverStation *ds);
 /**
  * Constructs a new DriverStation that will connect to the specified IP, and that will be assigned the given alliance and team number
  *
- * This function will return NULL if alliance or ip is NULL
+ * This function will return NULL if alliance or ip is NULL, if the team number is too large, or if the driver station's sockets couldn't be bound
  * After calling this function, alliance will no longer be a valid pointer. Attempting to use it may result in UB.
  * The pointer returned by this function **must** be freed using DS_DriverStation_destroy(). Using any other means is undefined.
  */
@@ -171,7 +217,7 @@ DriverStation *DS_DriverStation_new_ip(const char *ip,
 /**
  * Constructs a new DriverStation that will connect to 10.TE.AM.2 with the given team, and that will be assigned the given alliance.
  *
- * This function will return NULL if alliance is NULL
+ * This function will return NULL if alliance is NULL, if the team number is too large, or if the driver station's sockets couldn't be bound
  * After calling this function, alliance will no longer be a valid pointer. Attempting to use it may result in UB.
  * The pointer returned by this function **must** be freed using DS_DriverStation_destroy(). Using any other means is undefined.
  */
@@ -220,6 +266,24 @@ int8_t DS_DriverStation_set_game_specific_message(DriverStation *ds,
  */
 void DS_DriverStation_set_mode(DriverStation *ds, Mode mode);
 
+/**
+ * Sets the current state of the joystick in `port`, which is sent to the roboRIO until it is updated again
+ *
+ * Axes range from -1.0 to 1.0, and POV angles are in degrees, or -1 if not pressed.
+ * Any of the arrays may be NULL if its length is 0. Joysticks initialized with DS_Joystick_init take precedence over values set this way.
+ *
+ * This function will return -1 if ds is NULL, or if an array is NULL with a length other than 0
+ * It will return 0 on a success.
+ */
+int8_t DS_DriverStation_set_joystick_values(DriverStation *ds,
+                                            uintptr_t port,
+                                            const float *axes,
+                                            uintptr_t axes_len,
+                                            const bool *buttons,
+                                            uintptr_t buttons_len,
+                                            const int16_t *povs,
+                                            uintptr_t povs_len);
+
 /**
  * Register a callback to be notified when the driver station returns TCP packets containing riolog data
  *
@@ -237,9 +301,24 @@ void DS_DriverStation_set_tcp_consumer(DriverStation *ds,
  * Updates the team number of the given driver station. This will automatically reconnect the
  * network threads to target 10.TE.AM.2
  *
- * This function does nothing if ds is NULL
+ * This function will return -1 if ds is NULL
+ * It will return 1 if the team number is too large to be mapped to an address, in which case nothing is changed
+ * It will return 0 on a success.
+ */
+int8_t DS_DriverStation_set_team_number(DriverStation *ds, uint32_t team_number);
+
+/**
+ * Register a callback to be called with a telemetry snapshot every `period_ms` milliseconds
+ *
+ * `user_data` is passed to the callback as is. The callback is called from a thread owned by the driver station,
+ * and replaces any registered before. Passing a NULL callback stops the callbacks.
+ *
+ * This function does nothing if the given ds pointer is NULL, or if period_ms is 0
  */
-void DS_DriverStation_set_team_number(DriverStation *ds, uint32_t team_number);
+void DS_DriverStation_set_telemetry_callback(DriverStation *ds,
+                                             void (*callback)(Telemetry, void*),
+                                             void *user_data,
+                                             uint32_t period_ms);
 
 /**
  * Specifies whether the driver station should attempt to connect to 172.22.11.2 over USB rather than any other specified target
@@ -249,12 +328,19 @@ void DS_DriverStation_set_team_number(DriverStation *ds, uint32_t team_number);
 void DS_DriverStation_set_use_usb(DriverStation *ds,
                                   bool use_usb);
 
+/**
+ * Fills in `telemetry` with a snapshot of the state of the given driver station and its robot
+ *
+ * This function returns 1 if either pointer is NULL, and 0 on a success
+ */
+uint8_t DS_DriverStation_telemetry(const DriverStation *ds, Telemetry *telemetry);
+
 /**
  * Returns the latest Trace returned by the roboRIO connected to the given driver station
  *
  * Trace is a bitflags value, the individual bitmasks are #define'd at the top of the header.
  *
- * This function does nothing if the given pointer is NULL
+ * This function returns 0 if the given pointer is NULL
  */
 uint8_t DS_DriverStation_trace(const DriverStation *ds);
 
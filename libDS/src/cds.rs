@@ -1,45 +1,108 @@
-use ds::{Alliance, DriverStation, TcpPacket};
-use std::ptr;
-use libc::c_char;
+use ds::{Alliance, TcpPacket};
+use libc::{c_char, c_void};
+use std::convert::TryFrom;
 use std::ffi::{CStr, CString};
-use crate::{Mode, DsMode, StdoutMessage};
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::sync::{Mutex, MutexGuard};
+use tokio::task::JoinHandle;
+use crate::{DsMode, Mode, StdoutMessage, Telemetry};
+
+/// Represents a connection to the roboRIO acting as a driver station
+///
+/// Each driver station runs its network tasks on a runtime of its own, which is stopped when it is destroyed.
+/// Functions taking a driver station block the calling thread until they have completed, and so must not be called
+/// from within the callbacks registered with it.
+pub struct DriverStation {
+    pub(crate) runtime: Runtime,
+    inner: Arc<Mutex<ds::DriverStation>>,
+    /// The task calling the telemetry callback, if one has been registered
+    telemetry: Option<JoinHandle<()>>,
+}
+
+impl DriverStation {
+    fn new<F>(connect: F) -> *mut DriverStation
+    where
+        F: std::future::Future<Output = ds::Result<ds::DriverStation>>,
+    {
+        let runtime = match tokio::runtime::Builder::new_multi_thread().enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(_) => return ptr::null_mut(),
+        };
+        match runtime.block_on(connect) {
+            Ok(inner) => Box::into_raw(Box::new(DriverStation {
+                runtime,
+                inner: Arc::new(Mutex::new(inner)),
+                telemetry: None,
+            })),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, ds::DriverStation> {
+        self.inner.blocking_lock()
+    }
+
+    /// Stops the telemetry callback, if one has been registered
+    fn stop_telemetry(&mut self) {
+        if let Some(task) = self.telemetry.take() {
+            task.abort();
+            // Waiting for the task to end releases its reference to the driver station
+            let _ = self.runtime.block_on(task);
+        }
+    }
+}
+
+/// Converts a position from C, which would panic if it were out of range
+fn alliance(position: u8, new: fn(u8) -> Alliance) -> *mut Alliance {
+    if !(1..=3).contains(&position) {
+        return ptr::null_mut();
+    }
+
+    Box::into_raw(Box::new(new(position)))
+}
 
 /// Constructs a new Alliance representing a Red alliance robot of the given position
+///
+/// This function will return NULL if position is not between 1 and 3
 #[no_mangle]
 pub extern "C" fn DS_Alliance_new_red(position: u8) -> *mut Alliance {
-    let ptr = Box::new(Alliance::new_red(position));
-
-    Box::into_raw(ptr)
+    alliance(position, Alliance::new_red)
 }
 
 /// Constructs a new Alliance representing a Blue alliance robot of the given position
+///
+/// This function will return NULL if position is not between 1 and 3
 #[no_mangle]
 pub extern "C" fn DS_Alliance_new_blue(position: u8) -> *mut Alliance {
-    let ptr = Box::new(Alliance::new_blue(position));
-
-    Box::into_raw(ptr)
+    alliance(position, Alliance::new_blue)
 }
 
 /// Constructs a new DriverStation that will connect to 10.TE.AM.2 with the given team, and that will be assigned the given alliance.
 ///
-/// This function will return NULL if alliance is NULL
+/// This function will return NULL if alliance is NULL, if the team number is too large, or if the driver station's sockets couldn't be bound
 /// After calling this function, alliance will no longer be a valid pointer. Attempting to use it may result in UB.
 /// The pointer returned by this function **must** be freed using DS_DriverStation_destroy(). Using any other means is undefined.
 #[no_mangle]
-pub extern "C" fn DS_DriverStation_new_team(team_number: u32, alliance: *mut Alliance) -> *mut DriverStation {
+pub unsafe extern "C" fn DS_DriverStation_new_team(team_number: u32, alliance: *mut Alliance) -> *mut DriverStation {
     if alliance.is_null() {
         return ptr::null_mut();
     }
 
-    let alliance = unsafe { Box::from_raw(alliance) };
-    let ds = Box::new(DriverStation::new_team(team_number, *alliance));
-
-    Box::into_raw(ds)
+    let alliance = *Box::from_raw(alliance);
+    let team_number = match u16::try_from(team_number) {
+        Ok(team_number) => team_number,
+        Err(_) => return ptr::null_mut(),
+    };
+    DriverStation::new(ds::DriverStation::new_team(team_number, alliance))
 }
 
 /// Constructs a new DriverStation that will connect to the specified IP, and that will be assigned the given alliance and team number
 ///
-/// This function will return NULL if alliance or ip is NULL
+/// This function will return NULL if alliance or ip is NULL, if the team number is too large, or if the driver station's sockets couldn't be bound
 /// After calling this function, alliance will no longer be a valid pointer. Attempting to use it may result in UB.
 /// The pointer returned by this function **must** be freed using DS_DriverStation_destroy(). Using any other means is undefined.
 #[no_mangle]
@@ -48,24 +111,28 @@ pub unsafe extern "C" fn DS_DriverStation_new_ip(ip: *const c_char, alliance: *m
         return ptr::null_mut();
     }
 
-    let ip = CStr::from_ptr(ip);
-
-    let alliance = Box::from_raw(alliance);
-    let ds = Box::new(DriverStation::new(ip.to_str().unwrap(), *alliance, team_number));
-
-    Box::into_raw(ds)
+    let alliance = *Box::from_raw(alliance);
+    let (ip, team_number) = match (CStr::from_ptr(ip).to_str(), u16::try_from(team_number)) {
+        (Ok(ip), Ok(team_number)) => (ip, team_number),
+        _ => return ptr::null_mut(),
+    };
+    DriverStation::new(ds::DriverStation::new(ip, alliance, team_number))
 }
 
-/// Safely frees a given DriverStation.
+/// Disables the robot and frees a given DriverStation, once its final disabled packet has been sent
 ///
 /// This function should only be passed pointers that were allocated via DS_DriverStation_new_team or DS_DriverStation_new_ip
 #[no_mangle]
-pub extern "C" fn DS_DriverStation_destroy(ds: *mut DriverStation) {
+pub unsafe extern "C" fn DS_DriverStation_destroy(ds: *mut DriverStation) {
     if ds.is_null() {
         return;
     }
 
-    unsafe { Box::from_raw(ds); }
+    let mut ds = Box::from_raw(ds);
+    ds.stop_telemetry();
+    if let Ok(inner) = Arc::try_unwrap(ds.inner) {
+        ds.runtime.block_on(inner.into_inner().shutdown());
+    }
 }
 
 /// Assigns the given alliance station to the given driver station
@@ -78,20 +145,32 @@ pub unsafe extern "C" fn DS_DriverStation_set_alliance(ds: *mut DriverStation, a
         return;
     }
 
-    (*ds).set_alliance(*Box::from_raw(alliance));
+    let alliance = *Box::from_raw(alliance);
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().set_alliance(alliance));
 }
 
 /// Updates the team number of the given driver station. This will automatically reconnect the
 /// network threads to target 10.TE.AM.2
 ///
-/// This function does nothing if ds is NULL
+/// This function will return -1 if ds is NULL
+/// It will return 1 if the team number is too large to be mapped to an address, in which case nothing is changed
+/// It will return 0 on a success.
 #[no_mangle]
-pub unsafe extern "C" fn DS_DriverStation_set_team_number(ds: *mut DriverStation, team_number: u32) {
+pub unsafe extern "C" fn DS_DriverStation_set_team_number(ds: *mut DriverStation, team_number: u32) -> i8 {
     if ds.is_null() {
-        return;
+        return -1;
     }
 
-    (*ds).set_team_number(team_number);
+    let team_number = match u16::try_from(team_number) {
+        Ok(team_number) => team_number,
+        Err(_) => return 1,
+    };
+    let ds = &*ds;
+    match ds.runtime.block_on(ds.lock().set_team_number(team_number)) {
+        Ok(()) => 0,
+        Err(_) => 1,
+    }
 }
 
 /// Specifies whether the driver station should attempt to connect to 172.22.11.2 over USB rather than any other specified target
@@ -103,7 +182,7 @@ pub unsafe extern "C" fn DS_DriverStation_set_use_usb(ds: *mut DriverStation, us
         return;
     }
 
-    (*ds).set_use_usb(use_usb);
+    (*ds).lock().set_use_usb(use_usb);
 }
 
 /// Gets the team number currently assigned to the given DriverStation
@@ -115,7 +194,7 @@ pub unsafe extern "C" fn DS_DriverStation_get_team_number(ds: *const DriverStati
         return 0;
     }
 
-    (*ds).team_number()
+    (*ds).lock().team_number().into()
 }
 
 /// Updates the Game Specific Message (GSM) associated with the given DriverStation.
@@ -132,9 +211,12 @@ pub unsafe extern "C" fn DS_DriverStation_set_game_specific_message(ds: *mut Dri
         return -1;
     }
 
-    let msg = CStr::from_ptr(message).to_str().unwrap();
-
-    match (*ds).set_game_specific_message(msg) {
+    let msg = match CStr::from_ptr(message).to_str() {
+        Ok(msg) => msg,
+        Err(_) => return 1,
+    };
+    let ds = &*ds;
+    match ds.runtime.block_on(ds.lock().set_game_specific_message(msg)) {
         Ok(()) => 0,
         Err(_) => 1
     }
@@ -150,8 +232,9 @@ pub unsafe extern "C" fn DS_DriverStation_get_mode(ds: *const DriverStation, mod
         return 1;
     }
 
-    *mode = Mode::from_ds((*ds).mode());
-    return 0;
+    let ds = &*ds;
+    *mode = Mode::from_ds(ds.runtime.block_on(ds.lock().mode()));
+    0
 }
 
 /// Changes the robot mode of the specified ds
@@ -163,7 +246,8 @@ pub unsafe extern "C" fn DS_DriverStation_set_mode(ds: *mut DriverStation, mode:
         return;
     }
 
-    (*ds).set_mode(mode.to_ds())
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().set_mode(mode.to_ds()));
 }
 
 /// Gets the DsMode of the specified ds, DsMode can specify whether the DS is currently connected to a simulator
@@ -176,20 +260,27 @@ pub unsafe extern "C" fn DS_DriverStation_get_ds_mode(ds: *const DriverStation,
         return 1;
     }
 
-    *mode = DsMode::from_ds((*ds).ds_mode());
-    return 0;
+    let ds = &*ds;
+    *mode = DsMode::from_ds(ds.runtime.block_on(ds.lock().ds_mode()));
+    0
 }
 
 /// Enables the robot connected to the given ds
 ///
-/// This function does nothing if ds is NULL
+/// This function will return -1 if ds is NULL
+/// It will return 1 if the robot couldn't be enabled, because an interlock is configured and hasn't been armed
+/// It will return 0 on a success.
 #[no_mangle]
-pub unsafe extern "C" fn DS_DriverStation_enable(ds: *mut DriverStation) {
+pub unsafe extern "C" fn DS_DriverStation_enable(ds: *mut DriverStation) -> i8 {
     if ds.is_null() {
-        return;
+        return -1;
     }
 
-    (*ds).enable();
+    let ds = &*ds;
+    match ds.runtime.block_on(ds.lock().enable()) {
+        Ok(()) => 0,
+        Err(_) => 1,
+    }
 }
 
 /// Disables the robot connected to the given ds
@@ -201,7 +292,8 @@ pub unsafe extern "C" fn DS_DriverStation_disable(ds: *mut DriverStation) {
         return;
     }
 
-    (*ds).disable();
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().disable());
 }
 
 /// Checks whether the given DS is enabling its connected robot
@@ -210,10 +302,11 @@ pub unsafe extern "C" fn DS_DriverStation_disable(ds: *mut DriverStation) {
 #[no_mangle]
 pub unsafe extern "C" fn DS_DriverStation_enabled(ds: *const DriverStation) -> bool {
     if ds.is_null() {
-        false
-    } else {
-        (*ds).enabled()
+        return false;
     }
+
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().enabled())
 }
 
 /// Emergency stops the robot connected to the given ds
@@ -225,7 +318,8 @@ pub unsafe extern "C" fn DS_DriverStation_estop(ds: *mut DriverStation) {
         return;
     }
 
-    (*ds).estop()
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().estop());
 }
 
 /// Checks whether the given ds is estopping its connected robot
@@ -234,10 +328,11 @@ pub unsafe extern "C" fn DS_DriverStation_estop(ds: *mut DriverStation) {
 #[no_mangle]
 pub unsafe extern "C" fn DS_DriverStation_estopped(ds: *const DriverStation) -> bool {
     if ds.is_null() {
-        false
-    } else {
-        (*ds).estopped()
+        return false;
     }
+
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().estopped())
 }
 
 /// Instructs the roboRIO connected to the given driver station to restart user code
@@ -249,7 +344,8 @@ pub unsafe extern "C" fn DS_DriverStation_restart_code(ds: *mut DriverStation) {
         return;
     }
 
-    (*ds).restart_code();
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().restart_code());
 }
 
 /// Instructs the roboRIO connected to the given driver station to reboot itself
@@ -261,21 +357,23 @@ pub unsafe extern "C" fn DS_DriverStation_restart_roborio(ds: *mut DriverStation
         return;
     }
 
-    (*ds).restart_roborio();
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().restart_roborio());
 }
 
 /// Returns the latest Trace returned by the roboRIO connected to the given driver station
 ///
 /// Trace is a bitflags value, the individual bitmasks are #define'd at the top of the header.
 ///
-/// This function does nothing if the given pointer is NULL
+/// This function returns 0 if the given pointer is NULL
 #[no_mangle]
 pub unsafe extern "C" fn DS_DriverStation_trace(ds: *const DriverStation) -> u8 {
     if ds.is_null() {
         return 0;
     }
 
-    return (*ds).trace().bits()
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().trace()).bits()
 }
 
 /// Returns the reported battery voltage of the connected robot
@@ -288,7 +386,103 @@ pub unsafe extern "C" fn DS_DriverStation_battery_voltage(ds: *const DriverStati
         return 0f32;
     }
 
-    return (*ds).battery_voltage()
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().battery_voltage())
+}
+
+/// Sets the current state of the joystick in `port`, which is sent to the roboRIO until it is updated again
+///
+/// Axes range from -1.0 to 1.0, and POV angles are in degrees, or -1 if not pressed.
+/// Any of the arrays may be NULL if its length is 0. Joysticks initialized with DS_Joystick_init take precedence over values set this way.
+///
+/// This function will return -1 if ds is NULL, or if an array is NULL with a length other than 0
+/// It will return 0 on a success.
+#[no_mangle]
+pub unsafe extern "C" fn DS_DriverStation_set_joystick_values(
+    ds: *mut DriverStation,
+    port: usize,
+    axes: *const f32,
+    axes_len: usize,
+    buttons: *const bool,
+    buttons_len: usize,
+    povs: *const i16,
+    povs_len: usize,
+) -> i8 {
+    unsafe fn array<'a, T>(ptr: *const T, len: usize) -> Option<&'a [T]> {
+        match (ptr.is_null(), len) {
+            (_, 0) => Some(&[]),
+            (true, _) => None,
+            (false, len) => Some(slice::from_raw_parts(ptr, len)),
+        }
+    }
+
+    if ds.is_null() {
+        return -1;
+    }
+
+    let (axes, buttons, povs) = match (array(axes, axes_len), array(buttons, buttons_len), array(povs, povs_len)) {
+        (Some(axes), Some(buttons), Some(povs)) => (axes, buttons, povs),
+        _ => return -1,
+    };
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().update_joystick(port, axes, buttons, povs));
+    0
+}
+
+/// Fills in `telemetry` with a snapshot of the state of the given driver station and its robot
+///
+/// This function returns 1 if either pointer is NULL, and 0 on a success
+#[no_mangle]
+pub unsafe extern "C" fn DS_DriverStation_telemetry(ds: *const DriverStation, telemetry: *mut Telemetry) -> u8 {
+    if ds.is_null() || telemetry.is_null() {
+        return 1;
+    }
+
+    let ds = &*ds;
+    *telemetry = Telemetry::from_ds(ds.runtime.block_on(ds.lock().telemetry()));
+    0
+}
+
+/// The user data passed to a telemetry callback, which is only ever handed back to C
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+/// Register a callback to be called with a telemetry snapshot every `period_ms` milliseconds
+///
+/// `user_data` is passed to the callback as is. The callback is called from a thread owned by the driver station,
+/// and replaces any registered before. Passing a NULL callback stops the callbacks.
+///
+/// This function does nothing if the given ds pointer is NULL, or if period_ms is 0
+#[no_mangle]
+pub unsafe extern "C" fn DS_DriverStation_set_telemetry_callback(
+    ds: *mut DriverStation,
+    callback: Option<extern "C" fn(Telemetry, *mut c_void)>,
+    user_data: *mut c_void,
+    period_ms: u32,
+) {
+    if ds.is_null() || period_ms == 0 {
+        return;
+    }
+
+    let ds = &mut *ds;
+    ds.stop_telemetry();
+    let callback = match callback {
+        Some(callback) => callback,
+        None => return,
+    };
+
+    let inner = ds.inner.clone();
+    let user_data = UserData(user_data);
+    ds.telemetry = Some(ds.runtime.spawn(async move {
+        let user_data = user_data;
+        let mut interval = tokio::time::interval(Duration::from_millis(period_ms.into()));
+        loop {
+            interval.tick().await;
+            let telemetry = inner.lock().await.telemetry().await;
+            callback(Telemetry::from_ds(telemetry), user_data.0);
+        }
+    }));
 }
 
 /// Register a callback to be notified when the driver station returns TCP packets containing riolog data
@@ -305,14 +499,14 @@ pub unsafe extern "C" fn DS_DriverStation_set_tcp_consumer(ds: *mut DriverStatio
         return;
     }
 
-    (*ds).set_tcp_consumer(move |packet| {
-        match packet {
-            TcpPacket::Stdout(stdout) => {
-                let cstr = CString::new(stdout.message).unwrap();
-                let ffi = StdoutMessage::new(&cstr);
-                callback(ffi);
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().set_tcp_consumer(move |packet| {
+        if let TcpPacket::Stdout(stdout) = packet {
+            // Messages containing a NUL can't be passed to C whole, so they are cut short
+            let message = stdout.message.split('\0').next().unwrap_or_default();
+            if let Ok(cstr) = CString::new(message) {
+                callback(StdoutMessage::new(&cstr));
             }
-            _ => {}
         }
-    })
+    }))
 }
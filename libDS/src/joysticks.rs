@@ -1,4 +1,5 @@
-use ds::{JoystickValue, DriverStation};
+use ds::JoystickValue;
+use crate::cds::DriverStation;
 use lazy_static::lazy_static;
 use std::sync::Mutex;
 
@@ -34,9 +35,10 @@ pub unsafe extern "C" fn DS_Joystick_init(ds: *mut DriverStation) -> i8 {
         return -1;
     }
 
-    (*ds).set_joystick_supplier(|| {
-        JOYSTICKS.lock().unwrap().clone()
-    });
+    let ds = &*ds;
+    ds.runtime.block_on(ds.lock().set_joystick_supplier(|| {
+        JOYSTICKS.lock().map(|joy| joy.clone()).unwrap_or_default()
+    }));
     0
 }
 
@@ -54,7 +56,7 @@ pub unsafe extern "C" fn DS_Joystick_attach(port: usize) -> u8 {
     }
 
     let mut joy = safe_unwrap_mux!();
-    for _ in 0..port - joy.len() {
+    while joy.len() <= port {
         joy.push(vec![])
     }
     0
@@ -76,12 +78,10 @@ pub unsafe extern "C" fn DS_Joystick_detach(port: usize) -> u8 {
     }
 
     let mut joy = safe_unwrap_mux!();
-    if port == joy.len() - 1 {
+    if port + 1 == joy.len() {
         joy.remove(port);
-    } else {
-        if let Some(js) = joy.get_mut(port) {
-            *js = vec![];
-        }
+    } else if let Some(js) = joy.get_mut(port) {
+        *js = vec![];
     }
     0
 }
@@ -97,11 +97,10 @@ pub unsafe extern "C" fn DS_Joystick_detach(port: usize) -> u8 {
 pub unsafe extern "C" fn DS_Joystick_set_button(port: usize, button: u8, pressed: bool) -> u8 {
     let mut joy = safe_unwrap_mux!();
 
-    if port > joy.len() - 1 {
-        return EOUTOFBOUND;
-    }
-
-    let mut inner = &mut joy[port];
+    let inner = match joy.get_mut(port) {
+        Some(inner) => inner,
+        None => return EOUTOFBOUND,
+    };
     match inner.iter_mut().find(|value| value.is_button() && value.id() == button) {
         Some(btn) => *btn = JoystickValue::Button { id: button, pressed },
         None => inner.push(JoystickValue::Button { id: button, pressed }),
@@ -119,11 +118,10 @@ pub unsafe extern "C" fn DS_Joystick_set_button(port: usize, button: u8, pressed
 pub unsafe extern "C" fn DS_Joystick_set_axis(port: usize, axis: u8, value: f32) -> u8 {
     let mut joy = safe_unwrap_mux!();
 
-    if port > joy.len() - 1 {
-        return EOUTOFBOUND;
-    }
-
-    let mut inner = &mut joy[port];
+    let inner = match joy.get_mut(port) {
+        Some(inner) => inner,
+        None => return EOUTOFBOUND,
+    };
     match inner.iter_mut().find(|value| value.is_axis() && value.id() == axis) {
         Some(ax) => *ax = JoystickValue::Axis { id: axis, value },
         None => inner.push(JoystickValue::Axis { id: axis, value }),
@@ -141,11 +139,10 @@ pub unsafe extern "C" fn DS_Joystick_set_axis(port: usize, axis: u8, value: f32)
 pub unsafe extern "C" fn DS_Joystick_set_pov(port: usize, pov: u8, value: i16) -> u8 {
     let mut joy = safe_unwrap_mux!();
 
-    if port > joy.len() - 1 {
-        return EOUTOFBOUND;
-    }
-
-    let mut inner = &mut joy[port];
+    let inner = match joy.get_mut(port) {
+        Some(inner) => inner,
+        None => return EOUTOFBOUND,
+    };
     match inner.iter_mut().find(|value| value.is_pov() && value.id() == pov) {
         Some(p) => *p = JoystickValue::POV { id: pov, angle: value },
         None => inner.push(JoystickValue::POV { id: pov, angle: value })
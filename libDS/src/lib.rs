@@ -12,7 +12,7 @@ pub struct StdoutMessage<'a> {
 }
 
 impl<'a> StdoutMessage<'a> {
-    pub(crate) fn new(message: &'a CString) -> StdoutMessage {
+    pub(crate) fn new(message: &'a CString) -> StdoutMessage<'a> {
         StdoutMessage {
             message: message.as_ptr(),
             _lifetime: PhantomData,
@@ -28,6 +28,56 @@ pub enum Mode {
     Test
 }
 
+/// A snapshot of the state of a driver station and the robot it is connected to
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct Telemetry {
+    /// When the snapshot was taken, in milliseconds since the Unix epoch
+    timestamp: u64,
+    connected: bool,
+    tcp_connected: bool,
+    enabled: bool,
+    estopped: bool,
+    mode: Mode,
+    /// The bits of the last Trace received from the robot
+    trace: u8,
+    battery_voltage: f32,
+    /// Control packets sent per second
+    sent_rate: f64,
+    /// Status packets received per second
+    recv_rate: f64,
+    jitter_ms: f64,
+    received: u64,
+    lost: u64,
+    /// The time taken for the robot to respond to a control packet, or -1 if it isn't known yet
+    trip_time_ms: f64,
+    /// How many control packets have been sent more than a full period after they were due
+    late_ticks: u64,
+}
+
+impl Telemetry {
+    pub(crate) fn from_ds(telemetry: ds::Telemetry) -> Telemetry {
+        let millis = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+        Telemetry {
+            timestamp: telemetry.timestamp,
+            connected: telemetry.connected,
+            tcp_connected: telemetry.tcp_connected,
+            enabled: telemetry.enabled,
+            estopped: telemetry.estopped,
+            mode: Mode::from_ds(telemetry.mode),
+            trace: telemetry.trace.bits(),
+            battery_voltage: telemetry.battery_voltage,
+            sent_rate: telemetry.net.sent_rate,
+            recv_rate: telemetry.net.recv_rate,
+            jitter_ms: millis(telemetry.net.jitter),
+            received: telemetry.net.received,
+            lost: telemetry.net.lost,
+            trip_time_ms: telemetry.net.trip_time.map_or(-1.0, millis),
+            late_ticks: telemetry.ticks.late,
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub enum DsMode {
@@ -0,0 +1,299 @@
+//! A terminal dashboard for driving a robot with `ds`
+//!
+//! Usage: `ds-tui <team number> [roboRIO address]`
+//!
+//! The robot is enabled with `e`, disabled with `d` or Enter, and E-stopped with Space. `a`, `t` and `s` switch
+//! between autonomous, teleop and test, `r` restarts the robot code, and `q` quits.
+
+use std::collections::VecDeque;
+use std::env;
+use std::io;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use ds::{Alliance, DriverStation, Mode, TcpPacket, Telemetry, Trace};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tokio::time::MissedTickBehavior;
+
+/// How often the dashboard is redrawn
+const FRAME_PERIOD: Duration = Duration::from_millis(50);
+/// How many voltage samples are graphed, one per frame
+const VOLTAGE_HISTORY: usize = 200;
+/// How many lines of robot output are kept
+const CONSOLE_LINES: usize = 500;
+
+const TRACE_FLAGS: [(Trace, &str); 6] = [
+    (Trace::ROBOT_CODE, "Robot code"),
+    (Trace::IS_ROBORIO, "roboRIO"),
+    (Trace::DISABLED, "Disabled"),
+    (Trace::AUTONOMOUS, "Autonomous"),
+    (Trace::TELEOP, "Teleop"),
+    (Trace::TEST_MODE, "Test"),
+];
+
+struct App {
+    ds: DriverStation,
+    team: u16,
+    packets: broadcast::Receiver<TcpPacket>,
+    telemetry: Telemetry,
+    voltages: VecDeque<f32>,
+    console: VecDeque<String>,
+    /// The last error returned by a command, shown until the next key is pressed
+    error: Option<String>,
+}
+
+impl App {
+    async fn new(ds: DriverStation, team: u16) -> App {
+        App {
+            packets: ds.tcp_packets(),
+            telemetry: ds.telemetry().await,
+            ds,
+            team,
+            voltages: VecDeque::with_capacity(VOLTAGE_HISTORY),
+            console: VecDeque::with_capacity(CONSOLE_LINES),
+            error: None,
+        }
+    }
+
+    async fn update(&mut self) {
+        self.telemetry = self.ds.telemetry().await;
+        if self.voltages.len() == VOLTAGE_HISTORY {
+            self.voltages.pop_front();
+        }
+        self.voltages.push_back(self.telemetry.battery_voltage);
+
+        loop {
+            match self.packets.try_recv() {
+                Ok(TcpPacket::Stdout(stdout)) => {
+                    for line in stdout.message.lines() {
+                        if self.console.len() == CONSOLE_LINES {
+                            self.console.pop_front();
+                        }
+                        self.console.push_back(line.to_string());
+                    }
+                }
+                Ok(_) | Err(TryRecvError::Lagged(_)) => {}
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// Applies the command bound to `key`, returning false if the dashboard should exit
+    async fn handle_key(&mut self, key: KeyCode) -> bool {
+        self.error = None;
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('e') => {
+                if let Err(e) = self.ds.enable().await {
+                    self.error = Some(e.to_string());
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Enter => self.ds.disable().await,
+            KeyCode::Char(' ') => self.ds.estop_now(),
+            KeyCode::Char('a') => self.ds.set_mode(Mode::Autonomous).await,
+            KeyCode::Char('t') => self.ds.set_mode(Mode::Teleoperated).await,
+            KeyCode::Char('s') => self.ds.set_mode(Mode::Test).await,
+            KeyCode::Char('r') => self.ds.restart_code().await,
+            _ => {}
+        }
+        true
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let Some(team) = args.next().and_then(|team| team.parse::<u16>().ok()) else {
+        eprintln!("Usage: ds-tui <team number> [roboRIO address]");
+        return ExitCode::from(2);
+    };
+
+    let ds = match args.next() {
+        Some(ip) => DriverStation::new(&ip, Alliance::new_red(1), team).await,
+        None => DriverStation::new_team(team, Alliance::new_red(1)).await,
+    };
+    let ds = match ds {
+        Ok(ds) => ds,
+        Err(e) => {
+            eprintln!("Failed to start the driver station: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut app = App::new(ds, team).await;
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app).await;
+    ratatui::restore();
+    app.ds.shutdown().await;
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Terminal error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    let mut frames = tokio::time::interval(FRAME_PERIOD);
+    frames.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        frames.tick().await;
+
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !app.handle_key(key.code).await
+            {
+                return Ok(());
+            }
+        }
+
+        app.update().await;
+        terminal.draw(|frame| draw(frame, app))?;
+    }
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let [status, middle, console, help] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(10),
+        Constraint::Percentage(40),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [graph, flags] =
+        Layout::horizontal([Constraint::Min(30), Constraint::Length(30)]).areas(middle);
+
+    draw_status(frame, app, status);
+    draw_voltage(frame, app, graph);
+    draw_flags(frame, app, flags);
+    draw_console(frame, app, console);
+
+    let help_line = match app.error {
+        Some(ref error) => Line::from(error.as_str()).red(),
+        None => Line::from(
+            "e enable  d/Enter disable  Space e-stop  a/t/s auto/teleop/test  r restart code  q quit",
+        )
+        .dark_gray(),
+    };
+    frame.render_widget(help_line, help);
+}
+
+fn draw_status(frame: &mut Frame, app: &App, area: Rect) {
+    let telemetry = &app.telemetry;
+    let flag = |on: bool, label: &'static str| {
+        if on {
+            Span::from(label).green()
+        } else {
+            Span::from(label).red()
+        }
+    };
+    let state = if telemetry.estopped {
+        Span::from("E-STOPPED").white().on_red()
+    } else if telemetry.enabled {
+        Span::from("Enabled").green().bold()
+    } else {
+        Span::from("Disabled").red()
+    };
+    let mode = match telemetry.mode {
+        Mode::Autonomous => "Autonomous",
+        Mode::Teleoperated => "Teleoperated",
+        Mode::Test => "Test",
+    };
+
+    let line = Line::from(vec![
+        Span::from(format!("Team {}", app.team)).bold(),
+        Span::from("  "),
+        flag(telemetry.connected, "Communications"),
+        Span::from("  "),
+        flag(telemetry.tcp_connected, "TCP"),
+        Span::from("  "),
+        flag(telemetry.trace.contains(Trace::ROBOT_CODE), "Robot code"),
+        Span::from("  "),
+        Span::from(mode),
+        Span::from("  "),
+        state,
+        Span::from("  "),
+        Span::from(format!("{:.2} V", telemetry.battery_voltage)),
+    ]);
+    frame.render_widget(Paragraph::new(line).block(Block::bordered()), area);
+}
+
+fn draw_voltage(frame: &mut Frame, app: &App, area: Rect) {
+    let points = app
+        .voltages
+        .iter()
+        .enumerate()
+        .map(|(i, &voltage)| (i as f64, f64::from(voltage)))
+        .collect::<Vec<_>>();
+    let dataset = Dataset::default()
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::new().fg(Color::Yellow))
+        .data(&points);
+    let chart = Chart::new(vec![dataset])
+        .block(Block::bordered().title("Battery voltage"))
+        .x_axis(Axis::default().bounds([0.0, VOLTAGE_HISTORY as f64]))
+        .y_axis(Axis::default().bounds([0.0, 14.0]).labels(["0", "7", "14"]));
+    frame.render_widget(chart, area);
+}
+
+fn draw_flags(frame: &mut Frame, app: &App, area: Rect) {
+    let telemetry = &app.telemetry;
+    let mut lines = TRACE_FLAGS
+        .iter()
+        .map(|&(flag, label)| {
+            if telemetry.trace.contains(flag) {
+                Line::from(format!("● {}", label)).green()
+            } else {
+                Line::from(format!("○ {}", label)).dark_gray()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let net = &telemetry.net;
+    lines.push(Line::default());
+    lines.push(Line::from(format!(
+        "Packets {:.0}/s sent, {:.0}/s received",
+        net.sent_rate, net.recv_rate
+    )));
+    lines.push(Line::from(format!(
+        "Lost {} ({:.1}%)",
+        net.lost,
+        net.loss() * 100.0
+    )));
+    lines.push(Line::from(match net.trip_time {
+        Some(trip) => format!("Trip time {:.1} ms", trip.as_secs_f64() * 1000.0),
+        None => "Trip time unknown".to_string(),
+    }));
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Robot")),
+        area,
+    );
+}
+
+fn draw_console(frame: &mut Frame, app: &App, area: Rect) {
+    // Only the most recent lines that fit inside the border are shown
+    let visible = usize::from(area.height.saturating_sub(2));
+    let lines = app
+        .console
+        .iter()
+        .skip(app.console.len().saturating_sub(visible))
+        .map(|line| Line::from(line.as_str()))
+        .collect::<Vec<_>>();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Console")),
+        area,
+    );
+}
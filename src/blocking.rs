@@ -0,0 +1,256 @@
+//! A synchronous interface to the driver station, for applications that aren't async
+//!
+//! [`DriverStation`](struct.DriverStation.html) owns a tokio runtime of its own that the connection tasks run on, and
+//! blocks the calling thread until each operation has completed. Its methods must not be called from within an async
+//! context, where the async [`DriverStation`](../struct.DriverStation.html) should be used instead.
+
+use std::fmt;
+use std::time::Duration;
+
+use tokio::runtime::{Handle, Runtime};
+use tokio::sync::broadcast;
+
+use crate::{
+    Alliance, DriverStationBuilder, DsEvent, JoystickValue, Mode, Result, RobotState,
+    TcpConsumerHandle, TcpPacket, Telemetry, Trace,
+};
+
+/// A driver station with blocking methods, running its connection tasks on a runtime it owns
+///
+/// Each method blocks until the equivalent method of the async [`DriverStation`](../struct.DriverStation.html)
+/// completes, and is documented there.
+///
+/// ```no_run
+/// use ds::Alliance;
+/// use ds::blocking::DriverStation;
+///
+/// # fn main() -> ds::Result<()> {
+/// let mut ds = DriverStation::new_team(4533, Alliance::new_red(1))?;
+/// ds.enable()?;
+/// std::thread::sleep(std::time::Duration::from_secs(5));
+/// ds.shutdown();
+/// # Ok(())
+/// # }
+/// ```
+pub struct DriverStation {
+    // Dropped before the runtime, so that its tasks are stopped while the runtime is still around
+    inner: crate::DriverStation,
+    runtime: Runtime,
+}
+
+impl DriverStation {
+    /// Creates a new driver station for the given team number and alliance, connecting to 10.TE.AM.2
+    ///
+    /// Returns an error if the runtime couldn't be started, or the driver station's sockets couldn't be bound.
+    pub fn new_team(team_number: u16, alliance: Alliance) -> Result<DriverStation> {
+        DriverStation::from_builder(crate::DriverStation::builder(team_number, alliance))
+    }
+
+    /// Creates a new driver station for the given alliance station and team number, connecting to the roboRIO at `ip`
+    pub fn new(ip: &str, alliance: Alliance, team_number: u16) -> Result<DriverStation> {
+        DriverStation::from_builder(crate::DriverStation::builder(team_number, alliance).target(ip))
+    }
+
+    /// Builds a driver station configured by `builder`
+    pub fn from_builder(builder: DriverStationBuilder) -> Result<DriverStation> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("ds")
+            .enable_all()
+            .build()?;
+        let inner = runtime.block_on(builder.runtime(runtime.handle().clone()).build())?;
+        Ok(DriverStation { inner, runtime })
+    }
+
+    /// Returns the async driver station wrapped by this one
+    pub fn get_ref(&self) -> &crate::DriverStation {
+        &self.inner
+    }
+
+    /// Returns the async driver station wrapped by this one, to use methods without a blocking equivalent
+    pub fn get_mut(&mut self) -> &mut crate::DriverStation {
+        &mut self.inner
+    }
+
+    /// Returns a handle to the runtime that the connection tasks run on
+    pub fn handle(&self) -> &Handle {
+        self.runtime.handle()
+    }
+
+    pub fn set_joystick_supplier(
+        &mut self,
+        supplier: impl Fn() -> Vec<Vec<JoystickValue>> + Send + Sync + 'static,
+    ) {
+        self.runtime
+            .block_on(self.inner.set_joystick_supplier(supplier));
+    }
+
+    pub fn set_joystick_slots(&mut self, slots: usize) {
+        self.runtime.block_on(self.inner.set_joystick_slots(slots));
+    }
+
+    pub fn update_joystick(&mut self, port: usize, axes: &[f32], buttons: &[bool], povs: &[i16]) {
+        self.runtime
+            .block_on(self.inner.update_joystick(port, axes, buttons, povs));
+    }
+
+    pub fn set_tcp_consumer(&mut self, consumer: impl FnMut(TcpPacket) + Send + Sync + 'static) {
+        self.runtime.block_on(self.inner.set_tcp_consumer(consumer));
+    }
+
+    pub fn add_tcp_consumer(
+        &mut self,
+        consumer: impl FnMut(TcpPacket) + Send + Sync + 'static,
+    ) -> TcpConsumerHandle {
+        self.runtime.block_on(self.inner.add_tcp_consumer(consumer))
+    }
+
+    pub fn set_alliance(&mut self, alliance: Alliance) {
+        self.runtime.block_on(self.inner.set_alliance(alliance));
+    }
+
+    pub fn set_team_number(&mut self, team_number: u16) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.set_team_number(team_number))
+    }
+
+    pub fn team_number(&self) -> u16 {
+        self.inner.team_number()
+    }
+
+    pub fn set_game_specific_message(&mut self, message: &str) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.set_game_specific_message(message))
+    }
+
+    pub fn set_remaining_time(&mut self, remaining: Duration) {
+        self.runtime
+            .block_on(self.inner.set_remaining_time(remaining));
+    }
+
+    pub fn clear_remaining_time(&mut self) {
+        self.runtime.block_on(self.inner.clear_remaining_time());
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.runtime.block_on(self.inner.set_mode(mode));
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.runtime.block_on(self.inner.mode())
+    }
+
+    pub fn reported_mode(&self) -> Option<Mode> {
+        self.runtime.block_on(self.inner.reported_mode())
+    }
+
+    pub fn enable(&mut self) -> Result<()> {
+        self.runtime.block_on(self.inner.enable())
+    }
+
+    pub fn disable(&mut self) {
+        self.runtime.block_on(self.inner.disable());
+    }
+
+    pub fn estop(&mut self) {
+        self.runtime.block_on(self.inner.estop());
+    }
+
+    pub fn clear_estop_after_code_restart(&mut self) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.clear_estop_after_code_restart())
+    }
+
+    pub fn estop_now(&self) {
+        self.inner.estop_now();
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.runtime.block_on(self.inner.enabled())
+    }
+
+    pub fn estopped(&self) -> bool {
+        self.runtime.block_on(self.inner.estopped())
+    }
+
+    pub fn restart_code(&mut self) {
+        self.runtime.block_on(self.inner.restart_code());
+    }
+
+    pub fn restart_roborio(&mut self) {
+        self.runtime.block_on(self.inner.restart_roborio());
+    }
+
+    pub fn robot_state(&self) -> RobotState {
+        self.runtime.block_on(self.inner.robot_state())
+    }
+
+    pub fn trace(&self) -> Trace {
+        self.runtime.block_on(self.inner.trace())
+    }
+
+    pub fn connected(&self) -> bool {
+        self.runtime.block_on(self.inner.connected())
+    }
+
+    pub fn tcp_connected(&self) -> bool {
+        self.inner.tcp_connected()
+    }
+
+    pub fn battery_voltage(&self) -> f32 {
+        self.runtime.block_on(self.inner.battery_voltage())
+    }
+
+    pub fn telemetry(&self) -> Telemetry {
+        self.runtime.block_on(self.inner.telemetry())
+    }
+
+    /// Subscribes to events emitted by the driver station, which can be waited for with
+    /// [`blocking_recv`](https://docs.rs/tokio/1/tokio/sync/broadcast/struct.Receiver.html#method.blocking_recv)
+    pub fn events(&self) -> broadcast::Receiver<DsEvent> {
+        self.inner.events()
+    }
+
+    /// Subscribes to TCP packets received from the roboRIO, which can be waited for with
+    /// [`blocking_recv`](https://docs.rs/tokio/1/tokio/sync/broadcast/struct.Receiver.html#method.blocking_recv)
+    pub fn tcp_packets(&self) -> broadcast::Receiver<TcpPacket> {
+        self.inner.tcp_packets()
+    }
+
+    /// Disables the robot and stops the connection tasks, returning once the final control packet has been sent
+    pub fn shutdown(self) {
+        let DriverStation { inner, runtime } = self;
+        runtime.block_on(inner.shutdown());
+    }
+}
+
+impl fmt::Debug for DriverStation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DriverStation")
+            .field("team_number", &self.inner.team_number())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn methods_block_on_the_owned_runtime() {
+        let builder = crate::DriverStation::builder(4533, Alliance::new_red(1))
+            .bind_address(Ipv4Addr::LOCALHOST.into())
+            .receive_port(0)
+            .without_simulator();
+        let mut ds = DriverStation::from_builder(builder).unwrap();
+
+        ds.set_mode(Mode::Autonomous);
+        ds.enable().unwrap();
+        assert_eq!(ds.mode(), Mode::Autonomous);
+        assert!(ds.enabled());
+        assert!(!ds.connected());
+
+        ds.shutdown();
+    }
+}
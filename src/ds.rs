@@ -1,71 +1,185 @@
-use anyhow::bail;
-
+#[cfg(feature = "runtime")]
+mod battery;
+#[cfg(feature = "runtime")]
+pub(crate) mod builder;
+#[cfg(feature = "runtime")]
 mod conn;
+#[cfg(feature = "runtime")]
+mod controller;
+#[cfg(feature = "runtime")]
+mod csv;
+#[cfg(feature = "runtime")]
+mod event;
+mod mode;
+#[cfg(feature = "runtime")]
+mod monitor;
+#[cfg(feature = "runtime")]
+mod pool;
+#[cfg(feature = "runtime")]
+mod practice;
+#[cfg(feature = "runtime")]
+mod queue;
+#[cfg(feature = "runtime")]
+mod received;
+#[cfg(any(feature = "json", feature = "grpc"))]
+mod remote;
+mod robot;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "runtime")]
 pub(crate) mod state;
-
-use self::conn::*;
+mod stats;
+mod telemetry;
+#[cfg(feature = "signals")]
+mod termination;
+#[cfg(feature = "runtime")]
+mod wpilog;
+
+#[cfg(feature = "runtime")]
+pub(crate) use self::conn::{TaskGuard, connect_tcp};
+#[cfg(feature = "runtime")]
 use self::state::*;
 
+#[cfg(feature = "runtime")]
+pub use self::battery::{BatteryLevel, BatteryThresholds};
+#[cfg(feature = "runtime")]
+pub use self::builder::DriverStationBuilder;
+#[cfg(feature = "runtime")]
+pub use self::conn::{BackoffPolicy, ChaosProfile, DisconnectPolicy};
+#[cfg(feature = "runtime")]
+pub use self::controller::DsController;
+#[cfg(feature = "runtime")]
+pub use self::csv::{CsvLog, CsvLogHandle};
+#[cfg(feature = "runtime")]
+pub use self::event::{DsEvent, EstopSource, JoystickWarning};
+pub use self::mode::{DsMode, Mode};
+#[cfg(feature = "runtime")]
+pub use self::monitor::DsMonitor;
+#[cfg(feature = "runtime")]
+pub use self::pool::DsPool;
+#[cfg(feature = "runtime")]
+pub use self::practice::{MatchPhase, MatchTiming};
+#[cfg(feature = "runtime")]
+pub use self::queue::OverflowPolicy;
+#[cfg(feature = "runtime")]
+pub use self::received::PacketTime;
+#[cfg(feature = "json")]
+pub use self::remote::Command;
+#[cfg(feature = "grpc")]
+pub use self::remote::grpc::GrpcServer;
+#[cfg(feature = "http")]
+pub use self::remote::http::HttpServer;
+#[cfg(feature = "websocket")]
+pub use self::remote::websocket::WebSocketServer;
+pub use self::robot::{RobotState, RobotStateChange};
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::{SqliteLog, SqliteLogHandle};
+pub use self::stats::{NetStats, TickStats};
+pub use self::telemetry::Telemetry;
+#[cfg(feature = "json")]
+pub use self::telemetry::json::TelemetryExport;
+#[cfg(feature = "signals")]
+pub use self::termination::TerminationAction;
+#[cfg(feature = "runtime")]
+pub use self::wpilog::{WpiLog, WpiLogHandle};
+
+#[cfg(feature = "runtime")]
+use std::fmt;
+#[cfg(feature = "runtime")]
+use std::net::IpAddr;
+#[cfg(feature = "runtime")]
+use std::ops::Deref;
+#[cfg(feature = "runtime")]
 use std::sync::Arc;
-
-use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
-
-use crate::proto::tcp::outbound::{GameData, TcpTag};
-use crate::proto::udp::inbound::types::Trace;
+#[cfg(feature = "runtime")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+#[cfg(feature = "runtime")]
+use std::time::Instant;
+
+#[cfg(feature = "runtime")]
+use tokio::sync::broadcast;
+#[cfg(feature = "runtime")]
+use tokio::sync::mpsc::UnboundedSender;
+
+#[cfg(feature = "runtime")]
+use crate::proto::tcp::outbound::TcpTag;
+#[cfg(feature = "runtime")]
 use crate::proto::udp::outbound::types::*;
-use crate::util::ip_from_team_number;
+#[cfg(feature = "runtime")]
+use crate::util::{TargetSpec, USB_TARGET};
+#[cfg(feature = "runtime")]
 use crate::{Result, TcpPacket};
 
+/// How often control packets are sent to the roboRIO
+pub(crate) const PACKET_PERIOD: Duration = Duration::from_millis(20);
+
+/// Returns the current time on the runtime's clock
+///
+/// Unlike `Instant::now`, this stands still while tokio's time is paused, so that tests can skip ahead through
+/// timeouts rather than waiting for them.
+#[cfg(feature = "runtime")]
+pub(crate) fn now() -> Instant {
+    tokio::time::Instant::now().into_std()
+}
+
 /// Represents a connection to the roboRIO acting as a driver station
 ///
 /// This struct will contain relevant functions to update the state of the robot,
-/// and also manages the threads that manage network connections and joysticks
+/// and also manages the threads that manage network connections and joysticks.
+/// Its getters and event streams are those of [`DsMonitor`](struct.DsMonitor.html), which it dereferences to.
+#[cfg(feature = "runtime")]
 pub struct DriverStation {
     thread_tx: UnboundedSender<Signal>,
     team_number: u16,
+    /// How the addresses the roboRIO is looked for at are chosen, derived again when the team number changes
+    targets: TargetSpec,
+    /// Addresses probed in addition to the team's addresses
+    extra_targets: Vec<String>,
     state: Arc<DsState>,
+    monitor: DsMonitor,
+    /// Handles to the root tasks spawned for this driver station
+    tasks: Vec<TaskGuard<Result<()>>>,
 }
 
+#[cfg(feature = "runtime")]
 impl DriverStation {
     /// Creates a new driver station with the given team number and alliance
     ///
     /// This driver station will attempt to connect to a roboRIO at 10.TE.AM.2,
     /// if the roboRIO is at a different ip, use [new] and specify the ip directly.
-    pub async fn new_team(team_number: u16, alliance: Alliance) -> DriverStation {
-        Self::new(
-            &ip_from_team_number(team_number),
-            alliance,
-            team_number,
-        )
-        .await
+    ///
+    /// Returns an error if the sockets used to communicate with the roboRIO could not be bound.
+    pub async fn new_team(
+        team_number: u16,
+        alliance: impl Into<Alliance>,
+    ) -> Result<DriverStation> {
+        Self::builder(team_number, alliance).build().await
     }
 
     /// Creates a new driver station for the given alliance station and team number
     /// Connects to the roborio at `ip`. To infer the ip from team_number, use `new_team` instead.
-    pub async fn new(ip: &str, alliance: Alliance, team_number: u16) -> DriverStation {
-        // Channels to communicate to the threads that make up the application, used to break out of infinite loops when the struct is dropped
-        let (tx, rx) = unbounded_channel::<Signal>();
-
-        // Global state of the driver station
-        let state = Arc::new(DsState::new(alliance));
-
-        // Thread containing UDP sockets communicating with the roboRIO
-        let udp_state = state.clone();
-        let udp_ip = ip.to_owned();
-
-        let sim_tx = tx.clone();
-        tokio::spawn(async {
-            sim_conn(sim_tx).await.unwrap();
-        });
-        udp_conn(udp_state, udp_ip, rx)
+    ///
+    /// The connection to the roboRIO is made in the background, and this function returns as soon as the
+    /// sockets have been bound. Use [`connected`](#method.connected) or [`events`](#method.events) to observe its progress.
+    ///
+    /// Returns an error if the sockets used to communicate with the roboRIO could not be bound,
+    /// for example [`DsError::PortInUse`](enum.DsError.html#variant.PortInUse) if another driver station is already listening on port 1150.
+    pub async fn new(
+        ip: &str,
+        alliance: impl Into<Alliance>,
+        team_number: u16,
+    ) -> Result<DriverStation> {
+        Self::builder(team_number, alliance)
+            .target(ip)
+            .build()
             .await
-            .expect("Error with udp connection");
+    }
 
-        DriverStation {
-            thread_tx: tx,
-            state,
-            team_number,
-        }
+    /// Creates a builder for a driver station with the given team number and alliance,
+    /// allowing further network configuration before connecting
+    pub fn builder(team_number: u16, alliance: impl Into<Alliance>) -> DriverStationBuilder {
+        DriverStationBuilder::new(team_number, alliance.into())
     }
 
     /// Provides a closure that will be called when constructing outbound packets to append joystick values
@@ -80,6 +194,28 @@ impl DriverStation {
             .set_joystick_supplier(supplier);
     }
 
+    /// Always sends at least `slots` joysticks to the roboRIO, filling in empty slots for any that are missing
+    ///
+    /// This keeps the ports seen by robot code stable when the supplier returns fewer joysticks, such as while a
+    /// controller is unplugged. At most 6 slots, the number of ports WPILib reads, are padded to.
+    pub async fn set_joystick_slots(&mut self, slots: usize) {
+        self.state.send().write().await.set_joystick_slots(slots);
+    }
+
+    /// Sets the current state of the joystick in `port`, which is sent to the roboRIO until it is updated again
+    ///
+    /// This suits event driven input libraries better than [`set_joystick_supplier`](#method.set_joystick_supplier),
+    /// which takes precedence if both are used. Axes range from `-1.0..=1.0`, and POV angles are in degrees, or -1 if not pressed.
+    pub async fn update_joystick(
+        &mut self,
+        port: usize,
+        axes: &[f32],
+        buttons: &[bool],
+        povs: &[i16],
+    ) {
+        self.state.update_joystick(port, axes, buttons, povs).await;
+    }
+
     /// Provides a closure that will be called when TCP packets are received from the roboRIO
     ///
     /// Example usage: Logging all stdout messages from robot code.
@@ -90,9 +226,54 @@ impl DriverStation {
         self.state.tcp().write().await.set_tcp_consumer(consumer);
     }
 
-    /// Changes the alliance for the given `DriverStation`
-    pub async fn set_alliance(&mut self, alliance: Alliance) {
-        self.state.send().write().await.set_alliance(alliance);
+    /// Adds a closure that will be called when TCP packets are received from the roboRIO, alongside any others
+    ///
+    /// Unlike [`set_tcp_consumer`](#method.set_tcp_consumer), consumers added this way don't replace each other.
+    /// The closure is called until the returned handle is dropped.
+    pub async fn add_tcp_consumer(
+        &mut self,
+        consumer: impl FnMut(TcpPacket) + Send + Sync + 'static,
+    ) -> TcpConsumerHandle {
+        let active = Arc::new(AtomicBool::new(true));
+        self.state
+            .tcp()
+            .write()
+            .await
+            .add_tcp_consumer(active.clone(), consumer);
+        TcpConsumerHandle(ConsumerHandle::Sync(active))
+    }
+
+    /// Adds an async closure that will be awaited for each TCP packet received from the roboRIO
+    ///
+    /// The closure runs on its own task, so it can forward packets over channels or sockets without holding up the
+    /// connection. Packets are handled one at a time in the order they arrive, and if the closure falls too far
+    /// behind, the oldest packets are skipped as with [`tcp_packets`](#method.tcp_packets).
+    /// The closure is called until the returned handle is dropped.
+    pub async fn add_async_tcp_consumer<F, Fut>(&mut self, mut consumer: F) -> TcpConsumerHandle
+    where
+        F: FnMut(TcpPacket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut packets = self.state.tcp_packets().subscribe();
+        let task = self.state.spawn(async move {
+            loop {
+                match packets.recv().await {
+                    Ok(packet) => consumer(packet).await,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        TcpConsumerHandle(ConsumerHandle::Async { _task: task })
+    }
+
+    /// Changes the alliance for the given `DriverStation`, which can also be given as an [`AllianceStation`](enum.AllianceStation.html)
+    pub async fn set_alliance(&mut self, alliance: impl Into<Alliance>) {
+        self.state
+            .send()
+            .write()
+            .await
+            .set_alliance(alliance.into());
     }
 
     /// Changes the given `mode` the robot will be in
@@ -100,33 +281,52 @@ impl DriverStation {
         self.state.send().write().await.set_mode(mode);
     }
 
-    pub async fn ds_mode(&self) -> DsMode {
-        self.state.send().read().await.ds_mode()
-    }
-
     /// Changes the team number of this driver station, as well as the ip the driver station will attempt to connect to.
-    /// The ip of the new roboRIO target is 10.TE.AM.2
-    pub fn set_team_number(&mut self, team_number: u16) {
+    ///
+    /// The new targets are chosen the same way as the builder chose them, such as 10.TE.AM.2 or the team's mDNS
+    /// hostname. Targets given explicitly with [`target`](struct.DriverStationBuilder.html#method.target) or
+    /// [`targets`](struct.DriverStationBuilder.html#method.targets) are kept.
+    ///
+    /// Returns an error if the team number is too large to be mapped to an address, in which case nothing is changed.
+    pub async fn set_team_number(&mut self, team_number: u16) -> Result<()> {
+        let targets = self.targets_for(team_number)?;
         self.team_number = team_number;
-        self.thread_tx
-            .send(Signal::NewTarget(
-                ip_from_team_number(team_number),
-            ))
-            .unwrap();
+        self.state.send().write().await.set_team_number(team_number);
+        self.thread_tx.send(Signal::NewTarget(targets))?;
+        Ok(())
     }
 
+    /// Connects to the roboRIO over USB only, at 172.22.11.2, or goes back to the targets the driver station was
+    /// configured with for the current team number
     pub fn set_use_usb(&mut self, use_usb: bool) {
-        if use_usb {
-            self.thread_tx
-                .send(Signal::NewTarget("172.22.11.2".to_string()))
-                .unwrap();
+        let targets = if use_usb {
+            vec![USB_TARGET.to_string()]
         } else {
-            self.thread_tx
-                .send(Signal::NewTarget(
-                    ip_from_team_number(self.team_number),
-                ))
-                .unwrap();
-        }
+            // The team number was checked when it was set, so it always maps to an address
+            let Ok(targets) = self.targets_for(self.team_number) else {
+                return;
+            };
+            targets
+        };
+        let _ = self.thread_tx.send(Signal::NewTarget(targets));
+    }
+
+    /// Replaces the addresses the roboRIO is looked for at with `targets`, in order of preference
+    ///
+    /// See [`DriverStationBuilder::targets`](struct.DriverStationBuilder.html#method.targets) for how the list is used.
+    pub fn set_targets(&mut self, targets: impl IntoIterator<Item = impl Into<String>>) {
+        let targets: Vec<String> = targets.into_iter().map(Into::into).collect();
+        // Kept when the team number changes, like targets given to the builder
+        self.targets = TargetSpec::Custom(targets.clone());
+        self.extra_targets.clear();
+        let _ = self.thread_tx.send(Signal::NewTarget(targets));
+    }
+
+    /// Returns the addresses the roboRIO should be looked for at for the given team number
+    fn targets_for(&self, team_number: u16) -> Result<Vec<String>> {
+        let mut targets = self.targets.targets(team_number)?;
+        targets.extend(self.extra_targets.iter().cloned());
+        Ok(targets)
     }
 
     #[inline(always)]
@@ -136,29 +336,54 @@ impl DriverStation {
 
     /// Sets the game specific message sent to the robot, and used during the autonomous period
     pub async fn set_game_specific_message(&mut self, message: &str) -> Result<()> {
-        if message.len() != 3 {
-            bail!("Message should be 3 characters long");
-        }
+        self.state.set_game_specific_message(message).await
+    }
 
-        let _ = self
-            .state
-            .tcp()
-            .write()
-            .await
-            .queue_tcp(TcpTag::GameData(GameData {
-                gsm: message.to_string(),
-            }));
-        Ok(())
+    /// Sets whether the robot is told it is connected to a field management system
+    ///
+    /// Robot code can behave differently on the field, such as WPILib skipping some timeouts, so this lets it be
+    /// tested off the field. This crate doesn't connect to an FMS itself, so the bit is only set by this method.
+    pub async fn set_fms_connected(&mut self, connected: bool) {
+        self.state.send().write().await.set_fms_connected(connected);
     }
 
-    /// Returns the current mode of the robot
-    pub async fn mode(&self) -> Mode {
-        self.state.send().read().await.mode()
+    /// Sets the roboRIO's clock to the configured clock's time, along with its timezone
+    ///
+    /// This is done automatically whenever the roboRIO asks for the date, unless
+    /// [`DriverStationBuilder::without_time_sync`](struct.DriverStationBuilder.html#method.without_time_sync) was used.
+    pub async fn sync_time(&mut self) {
+        self.state.queue_time().await;
     }
 
     /// Enables outputs on the robot
-    pub async fn enable(&mut self) {
-        self.state.send().write().await.enable();
+    ///
+    /// Returns an error if an interlock is configured, and the driver station wasn't [armed](#method.arm) beforehand.
+    pub async fn enable(&mut self) -> Result<()> {
+        self.state.enable().await
+    }
+
+    /// Enables outputs on the robot for `duration`, after which it is disabled again
+    ///
+    /// The robot is disabled by the driver station's own tasks, so this holds even if the calling task stops or panics.
+    /// Calling [`enable`](#method.enable) or [`disable`](#method.disable) in the meantime cancels the timer.
+    ///
+    /// Returns an error if an interlock is configured, and the driver station wasn't [armed](#method.arm) beforehand.
+    pub async fn enable_for(&mut self, duration: Duration) -> Result<()> {
+        self.state.enable_for(duration).await
+    }
+
+    /// Arms the interlock, allowing the robot to be enabled once within the window it was configured with
+    ///
+    /// See [`DriverStationBuilder::interlock`](struct.DriverStationBuilder.html#method.interlock).
+    pub async fn arm(&mut self) {
+        self.state.send().write().await.arm(now());
+    }
+
+    /// Tells the driver station that the application is still running, when a watchdog has been configured
+    ///
+    /// See [`DriverStationBuilder::watchdog`](struct.DriverStationBuilder.html#method.watchdog).
+    pub async fn feed_watchdog(&mut self) {
+        self.state.send().write().await.feed_watchdog(now());
     }
 
     /// Instructs the roboRIO to restart robot code
@@ -179,19 +404,128 @@ impl DriverStation {
             .request(Request::REBOOT_ROBORIO);
     }
 
-    /// Returns whether the robot is currently enabled
-    pub async fn enabled(&self) -> bool {
-        self.state.send().read().await.enabled()
+    /// Restarts the seqnums of control packets from 0
+    ///
+    /// This is done whenever the target changes or the simulator is found. The jump back isn't counted as lost packets.
+    pub async fn reset_seqnum(&mut self) {
+        self.state.reset_seqnum().await;
     }
 
-    /// Returns the last received Trace from the robot
-    pub async fn trace(&self) -> Trace {
-        self.state.recv().read().await.trace()
+    /// Writes a [telemetry](#method.telemetry) snapshot to `writer` every `period`, as newline-delimited JSON
+    ///
+    /// Snapshots are written until the returned handle is dropped, or writing fails.
+    #[cfg(feature = "json")]
+    pub fn export_json<W>(&self, writer: W, period: Duration) -> TelemetryExport
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        telemetry::json::to_writer(self.state.clone(), writer, period)
     }
 
-    /// Returns the last received battery voltage from the robot
-    pub async fn battery_voltage(&self) -> f32 {
-        self.state.recv().read().await.battery_voltage()
+    /// Sends a [telemetry](#method.telemetry) snapshot serialized as a line of JSON to `tx` every `period`
+    ///
+    /// Each line ends with a newline. Snapshots are sent until the returned handle is dropped, or the receiver is closed.
+    #[cfg(feature = "json")]
+    pub fn export_json_channel(
+        &self,
+        tx: tokio::sync::mpsc::Sender<String>,
+        period: Duration,
+    ) -> TelemetryExport {
+        telemetry::json::to_channel(self.state.clone(), tx, period)
+    }
+
+    /// Logs a [telemetry](#method.telemetry) snapshot to CSV files as described by `log`, for analysis in a spreadsheet
+    ///
+    /// Rows are written until the returned handle is dropped, or writing fails.
+    /// Returns an error if the log directory or the first file couldn't be created.
+    pub async fn log_csv(&self, log: CsvLog) -> Result<CsvLogHandle> {
+        self.state.run(csv::start(self.state.clone(), log)).await
+    }
+
+    /// Records the session to a new SQLite database in the directory described by `log`, to be queried afterwards
+    ///
+    /// Telemetry, changes to the control state, console messages and [events](enum.DsEvent.html) are recorded until
+    /// the returned handle is dropped, or writing fails. See [`SqliteLog`](struct.SqliteLog.html) for the tables.
+    /// Returns an error if the log directory or the database couldn't be created.
+    #[cfg(feature = "sqlite")]
+    pub async fn record_sqlite(&self, log: SqliteLog) -> Result<SqliteLogHandle> {
+        self.state.run(sqlite::start(self.state.clone(), log)).await
+    }
+
+    /// Logs [telemetry](#method.telemetry) to a new `.wpilog` file in the directory described by `log`, to be opened in
+    /// AdvantageScope alongside the robot's own logs
+    ///
+    /// Records are written until the returned handle is dropped, or writing fails.
+    /// Returns an error if the log directory or the file couldn't be created.
+    pub async fn log_wpilog(&self, log: WpiLog) -> Result<WpiLogHandle> {
+        self.state.run(wpilog::start(self.state.clone(), log)).await
+    }
+
+    /// Serves a WebSocket endpoint on `addr` for browser based driver station UIs
+    ///
+    /// Each client is sent a [telemetry](#method.telemetry) snapshot every `period`, and can send
+    /// [commands](enum.Command.html) to control the robot. Messages are JSON objects tagged by their `type`, either
+    /// `telemetry` or `reply`, with a reply sent for each command in the order they were received.
+    ///
+    /// Anyone who can reach the endpoint can enable the robot, so it should only be served on a trusted network.
+    /// Returns an error if `addr` couldn't be bound.
+    #[cfg(feature = "websocket")]
+    pub async fn serve_websocket(
+        &self,
+        addr: std::net::SocketAddr,
+        period: Duration,
+    ) -> Result<WebSocketServer> {
+        self.state
+            .run(remote::websocket::serve(self.state.clone(), addr, period))
+            .await
+    }
+
+    /// Serves an HTTP API on `addr` for scripts and other automation to control the driver station
+    ///
+    /// The endpoints are:
+    ///
+    /// * `GET /telemetry`, returning a [telemetry](#method.telemetry) snapshot as JSON
+    /// * `POST /enable`, `POST /disable` and `POST /estop`
+    /// * `POST /mode`, with a body such as `{"mode": "Autonomous"}`
+    /// * `POST /game_data`, with a body such as `{"message": "LRL"}`
+    ///
+    /// Commands are answered with `{"type": "reply", "ok": true}`, or a 400 response with an `error` when they fail.
+    /// Anyone who can reach the server can enable the robot, so it should only be served on a trusted network.
+    /// Returns an error if `addr` couldn't be bound.
+    #[cfg(feature = "http")]
+    pub async fn serve_http(&self, addr: std::net::SocketAddr) -> Result<HttpServer> {
+        self.state
+            .run(remote::http::serve(self.state.clone(), addr))
+            .await
+    }
+
+    /// Serves the `ds.DriverStation` gRPC service on `addr`, for test infrastructure written in other languages
+    ///
+    /// The service offers the same commands as [`serve_http`](#method.serve_http), along with a stream of
+    /// [telemetry](#method.telemetry) snapshots. It is described by `src/ds/remote/grpc.proto` in the repository,
+    /// which clients can be generated from.
+    ///
+    /// Anyone who can reach the server can enable the robot, so it should only be served on a trusted network.
+    /// Returns an error if `addr` couldn't be bound.
+    #[cfg(feature = "grpc")]
+    pub async fn serve_grpc(&self, addr: std::net::SocketAddr) -> Result<GrpcServer> {
+        self.state
+            .run(remote::grpc::serve(self.state.clone(), addr))
+            .await
+    }
+
+    /// Returns a cloneable handle that can enable, disable and observe this driver station from elsewhere
+    ///
+    /// See [`DsController`](struct.DsController.html).
+    pub fn controller(&self) -> DsController {
+        DsController::new(self.state.clone())
+    }
+
+    /// Returns a cloneable, read-only handle that can observe this driver station but not control the robot
+    ///
+    /// See [`DsMonitor`](struct.DsMonitor.html).
+    pub fn monitor(&self) -> DsMonitor {
+        self.monitor.clone()
     }
 
     /// Queues a UDP tag to be transmitted with the next outbound packet to the roboRIO
@@ -199,34 +533,113 @@ impl DriverStation {
         self.state.send().write().await.queue_udp(udp_tag);
     }
 
-    /// Returns a Vec of the current contents of the UDP queue
-    pub async fn udp_queue(&self) -> Vec<UdpTag> {
-        self.state.send().read().await.pending_udp().clone()
-    }
-
     /// Queues a TCP tag to be transmitted to the roboRIO
     pub async fn queue_tcp(&mut self, tcp_tag: TcpTag) {
         let _ = self.state.tcp().write().await.queue_tcp(tcp_tag);
     }
 
     /// Disables outputs on the robot and disallows enabling it until the code is restarted.
+    ///
+    /// The E-stop is then cleared with [`clear_estop_after_code_restart`](#method.clear_estop_after_code_restart).
     pub async fn estop(&mut self) {
-        self.state.send().write().await.estop();
+        self.state.estop().await;
     }
 
-    /// Returns whether the robot is currently E-stopped
-    pub async fn estopped(&self) -> bool {
-        self.state.send().read().await.estopped()
+    /// E-stops the robot from any context without blocking or waiting for a lock, taking effect with the next control packet
+    ///
+    /// This suits GUI callbacks and other synchronous code, where [`estop`](#method.estop) can't be awaited.
+    /// The robot is E-stopped within 20ms, even if the driver station's state is locked by another task in the meantime.
+    pub fn estop_now(&self) {
+        self.state.estop_now();
+    }
+
+    /// Clears the E-stop, allowing the robot to be enabled again, once robot code has restarted since it was E-stopped
+    ///
+    /// Like the official driver station, the E-stop is latched until the robot's code has gone away and come back, either
+    /// by calling [`restart_code`](#method.restart_code), restarting it by hand, or rebooting the roboRIO. The robot is
+    /// left disabled.
+    ///
+    /// Returns an error if robot code hasn't restarted yet, in which case the robot stays E-stopped. Does nothing if
+    /// the robot isn't E-stopped.
+    pub async fn clear_estop_after_code_restart(&mut self) -> Result<()> {
+        self.state.send().write().await.clear_estop()
     }
 
     /// Disables outputs on the robot
+    ///
+    /// This also ends any practice match in progress.
     pub async fn disable(&mut self) {
-        self.state.send().write().await.disable();
+        self.state.disable().await;
+    }
+
+    /// Reports `remaining` as the time left in the current period to robot code, counting down with each control packet
+    ///
+    /// A Countdown tag is sent with every packet until [`clear_remaining_time`](#method.clear_remaining_time) is called,
+    /// and holds at 0 once the time has run out. A practice match in progress sends its own countdown instead.
+    pub async fn set_remaining_time(&mut self, remaining: Duration) {
+        self.state
+            .send()
+            .write()
+            .await
+            .set_countdown(Some(now() + remaining));
+    }
+
+    /// Stops reporting the time remaining set with [`set_remaining_time`](#method.set_remaining_time)
+    pub async fn clear_remaining_time(&mut self) {
+        self.state.send().write().await.set_countdown(None);
+    }
+
+    /// Starts a practice match, enabling the robot in autonomous then teleop according to `timing`
+    ///
+    /// A Countdown tag with the time left in the current period is sent with every control packet,
+    /// and the robot is disabled once the match ends. Disabling or E-stopping the robot ends the match early.
+    ///
+    /// Returns an error if an interlock is configured, and the driver station wasn't [armed](#method.arm) beforehand.
+    pub async fn start_practice_match(&mut self, timing: MatchTiming) -> Result<()> {
+        self.state.check_reenable().await?;
+        let mut send = self.state.send().write().await;
+        send.check_armed(self.state.config().interlock, now())?;
+        send.start_practice(timing);
+        Ok(())
+    }
+
+    /// Disables the robot and stops all connection tasks, resolving once they have exited
+    ///
+    /// A final disabled control packet is sent to the roboRIO before the sockets are closed.
+    pub async fn shutdown(mut self) {
+        self.state.disable().await;
+        let _ = self.thread_tx.send(Signal::Disconnect);
+
+        for task in self.tasks.drain(..) {
+            let _ = task.await;
+        }
+    }
+
+    /// Enables chaos mode, disturbing traffic to and from the roboRIO according to `profile`
+    ///
+    /// Passing `None` returns to normal operation. This is intended for testing how applications
+    /// cope with a poor network link, and should not be used while actually driving a robot.
+    pub fn set_chaos_profile(&mut self, profile: Option<ChaosProfile>) {
+        self.state.chaos().send_replace(profile);
+    }
+
+    pub(crate) fn state(&self) -> &Arc<DsState> {
+        &self.state
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl Deref for DriverStation {
+    type Target = DsMonitor;
+
+    fn deref(&self) -> &DsMonitor {
+        &self.monitor
     }
 }
 
 /// Enum representing a value from a Joystick to be transmitted to the roboRIO
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum JoystickValue {
     /// Represents an axis value to be sent to the roboRIO
     ///
@@ -264,16 +677,55 @@ impl JoystickValue {
     }
 }
 
+/// Keeps a consumer added with [`DriverStation::add_tcp_consumer`](struct.DriverStation.html#method.add_tcp_consumer)
+/// subscribed to TCP packets, unsubscribing it when dropped
+#[cfg(feature = "runtime")]
+#[must_use = "the consumer is unsubscribed as soon as its handle is dropped"]
+pub struct TcpConsumerHandle(ConsumerHandle);
+
+#[cfg(feature = "runtime")]
+enum ConsumerHandle {
+    /// A closure called by the TCP task, which skips it once the flag is cleared
+    Sync(Arc<AtomicBool>),
+    /// A task awaiting an async closure, which is aborted when the handle is dropped
+    Async { _task: TaskGuard<()> },
+}
+
+#[cfg(feature = "runtime")]
+impl fmt::Debug for TcpConsumerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpConsumerHandle").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl Drop for TcpConsumerHandle {
+    fn drop(&mut self) {
+        if let ConsumerHandle::Sync(ref active) = self.0 {
+            active.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(feature = "runtime")]
 impl Drop for DriverStation {
     fn drop(&mut self) {
         // When this struct is dropped the threads that we spawned should be stopped otherwise we're leaking
+        // Dropping the task handles aborts them, which in turn aborts the tasks they spawned
         let _ = self.thread_tx.send(Signal::Disconnect);
+        self.tasks.clear();
     }
 }
 
+#[cfg(feature = "runtime")]
 #[derive(Debug)]
 pub(crate) enum Signal {
     Disconnect,
-    NewTarget(String),
+    /// Replaces the candidate addresses that the roboRIO is looked for at
+    NewTarget(Vec<String>),
     NewMode(DsMode),
+    /// Sent to the send task once the roboRIO has answered from the given address
+    TargetFound(IpAddr),
+    /// Sent to the send task once the roboRIO has stopped answering
+    TargetLost,
 }
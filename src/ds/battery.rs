@@ -0,0 +1,167 @@
+/// Battery voltages at which [`DsEvent::BatteryLevelChanged`](enum.DsEvent.html#variant.BatteryLevelChanged) is emitted
+///
+/// A level is entered once the voltage falls below its threshold, and left once it recovers past the threshold by
+/// `hysteresis`, so that a voltage hovering around a threshold doesn't raise an alert with every packet.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BatteryThresholds {
+    /// The voltage below which the battery is running low
+    pub warning: f32,
+    /// The voltage below which the robot is at risk of browning out
+    pub critical: f32,
+    /// How far the voltage must recover past a threshold before its level is left
+    pub hysteresis: f32,
+}
+
+impl Default for BatteryThresholds {
+    /// Warns below 8V and alerts below 7V, recovering 0.3V above each
+    fn default() -> Self {
+        BatteryThresholds {
+            warning: 8.0,
+            critical: 7.0,
+            hysteresis: 0.3,
+        }
+    }
+}
+
+/// How charged the battery is, according to the configured [`BatteryThresholds`](struct.BatteryThresholds.html)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BatteryLevel {
+    Normal,
+    Warning,
+    Critical,
+}
+
+/// Tracks the battery level of the robot across status packets
+pub(crate) struct BatteryAlert {
+    thresholds: BatteryThresholds,
+    level: BatteryLevel,
+}
+
+impl BatteryAlert {
+    pub fn new(thresholds: BatteryThresholds) -> BatteryAlert {
+        BatteryAlert {
+            thresholds,
+            level: BatteryLevel::Normal,
+        }
+    }
+
+    /// Updates the level from a newly received `voltage`, returning it if it has changed
+    pub fn update(&mut self, voltage: f32) -> Option<BatteryLevel> {
+        // Robots without a voltage reading report 0, which shouldn't raise an alert
+        if voltage <= 0.0 {
+            return None;
+        }
+
+        let BatteryThresholds {
+            warning,
+            critical,
+            hysteresis,
+        } = self.thresholds;
+        let level = if voltage < critical
+            || (self.level == BatteryLevel::Critical && voltage < critical + hysteresis)
+        {
+            BatteryLevel::Critical
+        } else if voltage < warning
+            || (self.level >= BatteryLevel::Warning && voltage < warning + hysteresis)
+        {
+            BatteryLevel::Warning
+        } else {
+            BatteryLevel::Normal
+        };
+
+        (level != self.level).then(|| {
+            self.level = level;
+            level
+        })
+    }
+
+    /// Forgets the level, such as once the roboRIO disconnects and a new battery may be in the robot when it returns
+    pub fn reset(&mut self) {
+        self.level = BatteryLevel::Normal;
+    }
+}
+
+/// Corrects and smooths the battery voltages decoded from status packets
+///
+/// See [`DriverStationBuilder::battery_offset`](struct.DriverStationBuilder.html#method.battery_offset) and
+/// [`DriverStationBuilder::battery_smoothing`](struct.DriverStationBuilder.html#method.battery_smoothing).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct BatteryFilter {
+    /// Added to every reading
+    pub offset: f32,
+    /// The weight given to each new reading, or `None` to use readings as they are
+    pub smoothing: Option<f32>,
+    /// The smoothed voltage so far, if a reading has been taken since the filter was reset
+    smoothed: Option<f32>,
+}
+
+impl BatteryFilter {
+    pub const fn new() -> BatteryFilter {
+        BatteryFilter {
+            offset: 0.0,
+            smoothing: None,
+            smoothed: None,
+        }
+    }
+
+    /// Applies the offset and smoothing to a newly decoded `voltage`, returning the voltage to report
+    pub fn apply(&mut self, voltage: f32) -> f32 {
+        // 0 means the robot has no reading, and would drag the average down if it were included
+        if voltage <= 0.0 {
+            return voltage;
+        }
+
+        let voltage = voltage + self.offset;
+        let smoothed = match (self.smoothing, self.smoothed) {
+            (Some(weight), Some(previous)) => previous + weight * (voltage - previous),
+            _ => voltage,
+        };
+        self.smoothed = Some(smoothed);
+        smoothed
+    }
+
+    /// Starts smoothing afresh, such as once the roboRIO disconnects
+    pub fn reset(&mut self) {
+        self.smoothed = None;
+    }
+}
+
+impl Default for BatteryFilter {
+    fn default() -> Self {
+        BatteryFilter::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn levels_change_with_hysteresis() {
+        let mut alert = BatteryAlert::new(BatteryThresholds::default());
+
+        assert_eq!(alert.update(12.5), None);
+        assert_eq!(alert.update(7.9), Some(BatteryLevel::Warning));
+        assert_eq!(alert.update(8.1), None);
+        assert_eq!(alert.update(6.5), Some(BatteryLevel::Critical));
+        assert_eq!(alert.update(7.2), None);
+        assert_eq!(alert.update(0.0), None);
+        assert_eq!(alert.update(7.4), Some(BatteryLevel::Warning));
+        assert_eq!(alert.update(8.4), Some(BatteryLevel::Normal));
+    }
+
+    #[test]
+    fn filter_offsets_and_smooths_readings() {
+        let mut filter = BatteryFilter {
+            offset: 0.2,
+            smoothing: Some(0.5),
+            ..BatteryFilter::new()
+        };
+
+        assert_eq!(filter.apply(12.0), 12.2);
+        assert_eq!(filter.apply(11.0), 11.7);
+        assert_eq!(filter.apply(0.0), 0.0);
+        filter.reset();
+        assert_eq!(filter.apply(10.0), 10.2);
+    }
+}
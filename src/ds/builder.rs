@@ -0,0 +1,815 @@
+use super::battery::{BatteryFilter, BatteryThresholds};
+use super::conn::*;
+use super::queue::OverflowPolicy;
+use super::state::DsState;
+#[cfg(feature = "signals")]
+use super::termination::{TerminationAction, stop_on_termination};
+use super::{DriverStation, DsMonitor, Signal};
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::unbounded_channel;
+use tokio::time::MissedTickBehavior;
+
+#[cfg(feature = "halsim")]
+use crate::halsim::halsim_conn;
+use crate::netconsole::netconsole_conn;
+use crate::proto::udp::outbound::types::Alliance;
+use crate::riolog::{RIOLOG_PORT, riolog_conn};
+use crate::util::TargetSpec;
+use crate::{DsError, Result};
+
+/// Network configuration shared by the connection tasks
+#[derive(Debug, Clone)]
+pub(crate) struct DsConfig {
+    /// The local address that sockets are bound to
+    pub bind_addr: IpAddr,
+    /// The network interface that sockets are bound to, if any
+    pub interface: Option<String>,
+    /// How long the roboRIO may go without sending a status packet before it is considered disconnected
+    pub rio_timeout: Duration,
+    /// How sending control packets is retried after failures
+    pub backoff: BackoffPolicy,
+    /// What happens to the robot's enable state when the roboRIO stops responding
+    pub disconnect_policy: DisconnectPolicy,
+    /// How long the application may go without feeding the watchdog while the robot is enabled, if the watchdog is used
+    pub watchdog: Option<Duration>,
+    /// How long arming the driver station allows the robot to be enabled for, if the interlock is used
+    pub interlock: Option<Duration>,
+    /// The voltages at which battery level events are emitted, if battery alerts are used
+    pub battery_alerts: Option<BatteryThresholds>,
+    /// The correction and smoothing applied to battery voltages as they are received
+    pub battery_filter: BatteryFilter,
+    /// The time that the roboRIO's clock is set to when it asks for the date
+    pub clock: Clock,
+    /// The timezone the roboRIO is told it is in, rather than the host's
+    pub timezone: Option<String>,
+    /// Whether the roboRIO's clock is set automatically whenever it asks for the date
+    pub time_sync: bool,
+    /// Whether the time sent to the roboRIO is advanced by the estimated latency of the link
+    pub compensate_clock_latency: bool,
+    /// How many UDP or TCP tags may wait to be sent at once
+    pub queue_capacity: usize,
+    /// What happens to tags queued beyond `queue_capacity`
+    pub overflow: OverflowPolicy,
+    /// How the control packet loop catches up after it is stalled
+    pub missed_ticks: MissedTickBehavior,
+    /// Whether control packets are sent from a dedicated thread, rather than the application's runtime
+    pub sender_thread: bool,
+    /// Whether the control packet loop is timed by a precise sleep, rather than the runtime's timer
+    pub high_resolution_timer: bool,
+    /// Whether status packets from addresses other than the roboRIO's are ignored once it has been found
+    pub filter_source: bool,
+    /// The DSCP value control packets are marked with, if any
+    pub dscp: Option<u8>,
+    /// The port that status packets from the roboRIO are received on
+    pub rx_port: u16,
+    /// The port that the TCP connection to the roboRIO is opened on
+    pub tcp_port: u16,
+    /// Whether the receiving sockets may share their ports with other sockets
+    pub reuse_address: bool,
+    /// Whether the robot's console output is also received over NetConsole
+    pub netconsole: bool,
+    /// Whether the console is read from riolog, rather than the driver station's TCP connection
+    pub riolog: bool,
+    /// Whether a simulator connecting to `sim_detect_addr` switches the driver station to simulation mode
+    pub detect_simulator: bool,
+    /// The address listened on for a simulator to start
+    pub sim_detect_addr: SocketAddr,
+    /// The address control packets are sent to in simulation mode
+    pub sim_target: SocketAddr,
+    /// The runtime that the connection tasks are spawned on, rather than the one the driver station is built from
+    pub runtime: Option<Handle>,
+    /// The address of a simulation's HALSim WebSocket server to drive, if any
+    #[cfg(feature = "halsim")]
+    pub halsim: Option<SocketAddr>,
+    /// What is done to the robot before the process exits on SIGINT or SIGTERM, if the signals are handled
+    #[cfg(feature = "signals")]
+    pub termination: Option<TerminationAction>,
+}
+
+impl Default for DsConfig {
+    fn default() -> Self {
+        DsConfig {
+            bind_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            interface: None,
+            rio_timeout: Duration::from_secs(2),
+            backoff: BackoffPolicy::default(),
+            disconnect_policy: DisconnectPolicy::default(),
+            watchdog: None,
+            interlock: None,
+            battery_alerts: None,
+            battery_filter: BatteryFilter::new(),
+            clock: Clock::default(),
+            timezone: None,
+            time_sync: true,
+            compensate_clock_latency: false,
+            queue_capacity: 16,
+            overflow: OverflowPolicy::default(),
+            missed_ticks: MissedTickBehavior::Burst,
+            sender_thread: false,
+            high_resolution_timer: false,
+            filter_source: true,
+            dscp: None,
+            rx_port: 1150,
+            tcp_port: 1740,
+            reuse_address: false,
+            netconsole: false,
+            riolog: false,
+            detect_simulator: true,
+            sim_detect_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 1135)),
+            sim_target: SocketAddr::from((Ipv4Addr::LOCALHOST, 1110)),
+            runtime: None,
+            #[cfg(feature = "halsim")]
+            halsim: None,
+            #[cfg(feature = "signals")]
+            termination: None,
+        }
+    }
+}
+
+/// Builds a [`DriverStation`](struct.DriverStation.html) with a non-default configuration
+///
+/// Obtained from [`DriverStation::builder`](struct.DriverStation.html#method.builder).
+pub struct DriverStationBuilder {
+    /// How the addresses the roboRIO is looked for at are chosen
+    targets: TargetSpec,
+    /// Additional addresses probed alongside the main target
+    extra_targets: Vec<String>,
+    pub(crate) team_number: u16,
+    alliance: Alliance,
+    config: DsConfig,
+}
+
+impl DriverStationBuilder {
+    pub(crate) fn new(team_number: u16, alliance: Alliance) -> DriverStationBuilder {
+        DriverStationBuilder {
+            targets: TargetSpec::Static,
+            extra_targets: Vec::new(),
+            team_number,
+            alliance,
+            config: DsConfig::default(),
+        }
+    }
+
+    /// Connects to the roboRIO at `ip` rather than 10.TE.AM.2
+    ///
+    /// Hostnames and IPv6 addresses are accepted too, and are resolved again each time the connection is lost.
+    /// Reaching an IPv6 address requires [`bind_address`](#method.bind_address) to be given an IPv6 address.
+    pub fn target(mut self, ip: impl Into<String>) -> Self {
+        self.targets = TargetSpec::Custom(vec![ip.into()]);
+        self
+    }
+
+    /// Looks for the roboRIO at each of `targets`, such as a static address, an mDNS hostname, then `10.0.0.2`
+    ///
+    /// All targets are probed until one answers. While connected, the targets earlier in the list keep being probed,
+    /// and the connection moves to them as soon as they answer. If the roboRIO stops answering, all targets are probed again.
+    /// This suits robots that move between networks, such as a shop network and the field network.
+    pub fn targets(mut self, targets: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.targets = TargetSpec::Custom(targets.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Adds an address to look for the roboRIO at, alongside the main target
+    ///
+    /// All targets are probed concurrently, and the driver station locks onto whichever answers first.
+    /// Added targets are the least preferred, see [`targets`](#method.targets).
+    pub fn add_target(mut self, ip: impl Into<String>) -> Self {
+        self.extra_targets.push(ip.into());
+        self
+    }
+
+    /// Looks for the roboRIO at all of the addresses the official driver station does, locking onto whichever answers first
+    ///
+    /// The USB address (172.22.11.2), the mDNS hostname, and 10.TE.AM.2 are probed concurrently,
+    /// along with any addresses added with [`add_target`](#method.add_target).
+    ///
+    /// While connected over the radio, the USB address keeps being probed, and the driver station switches to it
+    /// as soon as a cable is plugged in. Once unplugged, the other addresses are probed again after the roboRIO timeout.
+    pub fn auto_target(mut self) -> Self {
+        self.targets = TargetSpec::Auto;
+        self
+    }
+
+    /// Connects to the roboRIO by its mDNS hostname, `roboRIO-TEAM-frc.local`, rather than 10.TE.AM.2
+    ///
+    /// This finds roboRIOs that were given an address by DHCP, as is common on home and shop networks.
+    pub fn mdns(mut self) -> Self {
+        self.targets = TargetSpec::Mdns;
+        self
+    }
+
+    /// Binds the driver station's sockets to the given local address instead of `0.0.0.0`
+    ///
+    /// On machines connected to more than one network, this ensures control traffic leaves through the interface facing the robot.
+    /// Binding to `::` allows both IPv4 and IPv6 roboRIOs to be reached on systems with dual-stack sockets.
+    pub fn bind_address(mut self, addr: IpAddr) -> Self {
+        self.config.bind_addr = addr;
+        self
+    }
+
+    /// Binds the driver station's sockets to the named network interface, such as `wlan0`
+    ///
+    /// Binding to an interface may require elevated privileges.
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub fn interface(mut self, name: impl Into<String>) -> Self {
+        self.config.interface = Some(name.into());
+        self
+    }
+
+    /// Receives status packets from the roboRIO on `port` rather than 1150
+    ///
+    /// The roboRIO always answers to port 1150, so this is only useful behind a proxy or port forward, or with a
+    /// simulator that can be told where to send its packets.
+    pub fn receive_port(mut self, port: u16) -> Self {
+        self.config.rx_port = port;
+        self
+    }
+
+    /// Sets `SO_REUSEADDR` on the receiving sockets, so they can be bound while another program holds their ports
+    ///
+    /// Without this, building fails with [`DsError::PortInUse`](enum.DsError.html#variant.PortInUse) while the
+    /// official driver station or another instance is running. With it, which socket receives each packet is up to the
+    /// platform, so the other program should be stopped before driving the robot.
+    pub fn reuse_address(mut self) -> Self {
+        self.config.reuse_address = true;
+        self
+    }
+
+    /// Doesn't listen for a simulator to start, which would switch the driver station to simulation mode
+    ///
+    /// Only one program can listen for the simulator, so this allows more than one driver station to run at once.
+    pub fn without_simulator(mut self) -> Self {
+        self.config.detect_simulator = false;
+        self
+    }
+
+    /// Listens for a simulator to start on `addr`, rather than `127.0.0.1:1135`
+    ///
+    /// Each driver station driving its own simulator needs an address of its own, as only one program can listen on
+    /// each. Listening on an unspecified address such as `0.0.0.0:1135` detects a simulator on another machine, which
+    /// is driven once [`simulator_target`](#method.simulator_target) is pointed at it.
+    pub fn simulator_address(mut self, addr: SocketAddr) -> Self {
+        self.config.sim_detect_addr = addr;
+        self
+    }
+
+    /// Sends control packets to `addr` in simulation mode, rather than `127.0.0.1:1110`
+    pub fn simulator_target(mut self, addr: SocketAddr) -> Self {
+        self.config.sim_target = addr;
+        self
+    }
+
+    /// Listens for the robot's console output on the NetConsole port, 6666, merging it into the console
+    ///
+    /// Some robot programs and older roboRIO images only print over NetConsole, rather than the TCP connection. Their
+    /// output reaches the TCP consumers and [`tcp_packets`](struct.DriverStation.html#method.tcp_packets) as
+    /// [`Stdout`](struct.Stdout.html) messages from [`ConsoleSource::NetConsole`](enum.ConsoleSource.html#variant.NetConsole).
+    /// Building fails with [`DsError::PortInUse`](enum.DsError.html#variant.PortInUse) if another program, such as a
+    /// standalone NetConsole viewer, is already listening unless [`reuse_address`](#method.reuse_address) is used.
+    pub fn netconsole(mut self) -> Self {
+        self.config.netconsole = true;
+        self
+    }
+
+    /// Reads the robot's console from riolog on TCP port 1741, rather than the driver station's TCP connection
+    ///
+    /// This is the console VS Code shows, which is useful when comparing the two. It is connected to once the roboRIO
+    /// is found, and again whenever the connection closes. Its messages reach the TCP consumers and
+    /// [`tcp_packets`](struct.DriverStation.html#method.tcp_packets) as [`Stdout`](struct.Stdout.html) messages from
+    /// [`ConsoleSource::Riolog`](enum.ConsoleSource.html#variant.Riolog), and those sent over the driver station's TCP
+    /// connection are dropped, as they would repeat the same output.
+    pub fn riolog(mut self) -> Self {
+        self.config.riolog = true;
+        self
+    }
+
+    /// Drives the desktop simulation whose HALSim WebSocket server is at `addr`, usually `127.0.0.1:3300`
+    ///
+    /// The simulated driver station state and joysticks are set to what is sent to the roboRIO, and the joystick outputs
+    /// that robot code sets are read back. It is connected to while the driver station runs, and again whenever the
+    /// connection closes. See the [`halsim`](halsim/index.html) module.
+    #[cfg(feature = "halsim")]
+    pub fn halsim(mut self, addr: SocketAddr) -> Self {
+        self.config.halsim = Some(addr);
+        self
+    }
+
+    /// Spawns the tasks managing the connection on the runtime behind `handle`
+    ///
+    /// By default they are spawned on the runtime that [`build`](#method.build) is called from, which panics if there
+    /// isn't one. With a handle, the driver station can be built and used from any thread or executor, and the
+    /// application controls which threads its tasks run on. Tasks started later, such as by
+    /// [`add_async_tcp_consumer`](struct.DriverStation.html#method.add_async_tcp_consumer) or the telemetry exports,
+    /// are spawned on the same runtime.
+    pub fn runtime(mut self, handle: Handle) -> Self {
+        self.config.runtime = Some(handle);
+        self
+    }
+
+    /// Handles SIGINT and SIGTERM, or Ctrl-C and the console closing on Windows, by stopping the robot before exiting
+    ///
+    /// The robot is disabled or E-stopped according to `action`, and the process exits once the final control packets
+    /// have been sent, with the exit code it would have had without the handler. This ensures that stopping a
+    /// headless driver station never leaves the robot enabled.
+    ///
+    /// The handlers stay installed for the life of the process, so the signals no longer terminate it once the
+    /// driver station has been dropped.
+    #[cfg(feature = "signals")]
+    pub fn stop_on_termination(mut self, action: TerminationAction) -> Self {
+        self.config.termination = Some(action);
+        self
+    }
+
+    /// Marks control packets with the given DSCP value, so that managed networks can prioritize them
+    ///
+    /// Field and venue networks commonly prioritize Expedited Forwarding, 46. Only the lower 6 bits of `dscp` are used.
+    /// Some platforms ignore the marking without elevated privileges, such as Windows without a QoS policy.
+    /// Sockets bound to an IPv6 address are marked through their traffic class on Linux, Android, macOS and the BSDs.
+    #[cfg(not(any(
+        target_os = "fuchsia",
+        target_os = "redox",
+        target_os = "solaris",
+        target_os = "illumos",
+        target_os = "haiku"
+    )))]
+    pub fn dscp(mut self, dscp: u8) -> Self {
+        self.config.dscp = Some(dscp & 0x3f);
+        self
+    }
+
+    /// Sets how long the roboRIO may go without responding before it is considered disconnected
+    ///
+    /// Defaults to 2 seconds. Simulators on the same machine can use a much shorter timeout,
+    /// while congested field networks may need a longer one to avoid spurious disconnects.
+    pub fn rio_timeout(mut self, timeout: Duration) -> Self {
+        self.config.rio_timeout = timeout;
+        self
+    }
+
+    /// Sets how sending control packets is retried after the socket reports failures
+    ///
+    /// The default policy waits 1ms, 20ms, 400ms, then 5s between attempts.
+    pub fn backoff(mut self, policy: BackoffPolicy) -> Self {
+        self.config.backoff = policy;
+        self
+    }
+
+    /// Sets what happens to the robot's enable state when the roboRIO stops responding for the
+    /// [`rio_timeout`](#method.rio_timeout)
+    ///
+    /// Defaults to [`DisconnectPolicy::Disable`](enum.DisconnectPolicy.html#variant.Disable), so that a robot coming
+    /// back from a dropout doesn't start moving on its own.
+    pub fn disconnect_policy(mut self, policy: DisconnectPolicy) -> Self {
+        self.config.disconnect_policy = policy;
+        self
+    }
+
+    /// Disables the robot if the application goes longer than `timeout` without calling
+    /// [`feed_watchdog`](struct.DriverStation.html#method.feed_watchdog) while it is enabled
+    ///
+    /// This protects against the application freezing while the driver station's own tasks keep the robot enabled.
+    /// Enabling the robot feeds the watchdog.
+    pub fn watchdog(mut self, timeout: Duration) -> Self {
+        self.config.watchdog = Some(timeout);
+        self
+    }
+
+    /// Requires [`arm`](struct.DriverStation.html#method.arm) to be called no more than `window` before the robot is enabled
+    ///
+    /// Each arm allows a single enable. This makes enabling a deliberate two-step action,
+    /// which suits kiosk and demo applications where an accidental enable is dangerous.
+    pub fn interlock(mut self, window: Duration) -> Self {
+        self.config.interlock = Some(window);
+        self
+    }
+
+    /// Emits [`DsEvent::BatteryLevelChanged`](enum.DsEvent.html#variant.BatteryLevelChanged) as the robot's battery
+    /// voltage crosses the given `thresholds`
+    ///
+    /// This suits playing an alert when the battery runs low during practice, see [`BatteryThresholds`](struct.BatteryThresholds.html).
+    pub fn battery_alerts(mut self, thresholds: BatteryThresholds) -> Self {
+        self.config.battery_alerts = Some(thresholds);
+        self
+    }
+
+    /// Adds `volts` to every battery voltage received from the robot, for robots that consistently read high or low
+    ///
+    /// The corrected voltage is the one reported by [`battery_voltage`](struct.DriverStation.html#method.battery_voltage),
+    /// and checked against the [battery alerts](#method.battery_alerts). The bytes the roboRIO sent remain available from
+    /// [`battery_raw`](struct.DriverStation.html#method.battery_raw).
+    pub fn battery_offset(mut self, volts: f32) -> Self {
+        self.config.battery_filter.offset = volts;
+        self
+    }
+
+    /// Smooths the battery voltage with an exponential moving average, giving each new reading a weight of `factor`
+    ///
+    /// `factor` is clamped to `0.0..=1.0`, where smaller factors smooth more but follow real drops more slowly.
+    /// A factor of 0.1 averages over roughly the last 10 readings, or 200ms.
+    pub fn battery_smoothing(mut self, factor: f32) -> Self {
+        self.config.battery_filter.smoothing = Some(factor.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Sets the roboRIO's clock from `now` rather than the system clock, when the roboRIO asks for the date
+    ///
+    /// This suits hosts with a more accurate time source, such as GPS, and tests that need a fixed time.
+    pub fn clock(mut self, now: impl Fn() -> SystemTime + Send + Sync + 'static) -> Self {
+        self.config.clock = Clock::new(now);
+        self
+    }
+
+    /// Tells the roboRIO it is in `timezone`, an IANA name such as "America/Toronto", rather than the host's timezone
+    ///
+    /// The timezone is sent alongside the time whenever the roboRIO asks for the date, so that its logs are in local time.
+    pub fn timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.config.timezone = Some(timezone.into());
+        self
+    }
+
+    /// Stops answering the roboRIO's requests for the date, leaving its clock as it is
+    ///
+    /// This suits test benches that run the roboRIO's clock offset on purpose, such as when replaying logs.
+    /// [`DsEvent::DateRequested`](enum.DsEvent.html#variant.DateRequested) is still emitted, and the clock can be set
+    /// with [`DriverStation::sync_time`](struct.DriverStation.html#method.sync_time) if the application decides to.
+    pub fn without_time_sync(mut self) -> Self {
+        self.config.time_sync = false;
+        self
+    }
+
+    /// Advances the time sent to the roboRIO by the estimated time it takes to arrive, so its clock lands closer
+    /// to the driver station's
+    ///
+    /// The estimate is half the measured round trip time, see
+    /// [`NetStats::trip_time`](struct.NetStats.html#structfield.trip_time), plus the wait for the next control packet.
+    /// This helps line up timestamps in the driver station's and the robot's logs.
+    pub fn compensate_clock_latency(mut self) -> Self {
+        self.config.compensate_clock_latency = true;
+        self
+    }
+
+    /// Limits how many tags may wait to be sent to the roboRIO, in each of the UDP and TCP queues
+    ///
+    /// Once a queue holds `capacity` tags, `policy` decides which tag is dropped to make room.
+    /// Defaults to 16 tags, dropping the oldest. Joystick values aren't queued, and don't count towards the limit.
+    pub fn tag_queue(mut self, capacity: usize, policy: OverflowPolicy) -> Self {
+        self.config.queue_capacity = capacity;
+        self.config.overflow = policy;
+        self
+    }
+
+    /// Sets how the control packet loop catches up on packets it missed while the host application stalled it
+    ///
+    /// Defaults to [`MissedTickBehavior::Burst`], which sends the missed packets back to back so that the average rate
+    /// stays at 50Hz, with the joystick supplier's last values reused for them. `Delay` and `Skip` instead resume a
+    /// steady rate straight away, dropping the missed packets. The achieved rate can be checked with
+    /// [`DriverStation::tick_stats`](struct.DriverStation.html#method.tick_stats).
+    pub fn missed_ticks(mut self, behavior: MissedTickBehavior) -> Self {
+        self.config.missed_ticks = behavior;
+        self
+    }
+
+    /// Sends control packets from a dedicated OS thread, rather than a task on the application's runtime
+    ///
+    /// Heavy UI or logging work on the runtime delays the packet loop, which robots feel as laggy driving. The thread
+    /// runs its own single threaded runtime, and is given the highest priority the platform allows, which on Linux
+    /// needs `CAP_SYS_NICE`. The joystick supplier is called from this thread too.
+    ///
+    /// The thread's runtime keeps real time, so the packet loop doesn't follow `tokio::time::pause` on the
+    /// application's runtime.
+    pub fn sender_thread(mut self) -> Self {
+        self.config.sender_thread = true;
+        self
+    }
+
+    /// Times the control packet loop with a high resolution sleep, rather than the runtime's timer
+    ///
+    /// The runtime's timer is only as precise as the platform's sleep. On Windows, that is 15.6ms by default, which makes
+    /// the 20ms packet loop jitter by most of a period. This starts a thread that sleeps using a high resolution timer,
+    /// spinning for the last moments before each packet is due, so packets are sent as steadily as the official driver
+    /// station sends them. Other platforms rarely need this, but it is honoured on all of them.
+    ///
+    /// The thread keeps real time, so the packet loop doesn't follow `tokio::time::pause`.
+    pub fn high_resolution_timer(mut self) -> Self {
+        self.config.high_resolution_timer = true;
+        self
+    }
+
+    /// Processes status packets from any address, rather than only from the roboRIO that was found
+    ///
+    /// By default, once the roboRIO has answered, packets arriving from other addresses are ignored and reported with
+    /// [`DsEvent::ForeignPacket`](enum.DsEvent.html#variant.ForeignPacket), so that a simulator or another robot on the
+    /// same network can't change the state of this one. Packets from preferred targets are still accepted, see [`targets`](#method.targets).
+    pub fn accept_any_source(mut self) -> Self {
+        self.config.filter_source = false;
+        self
+    }
+
+    /// Binds the sockets used to communicate with the roboRIO, and spawns the tasks managing the connection
+    ///
+    /// The connection to the roboRIO is made in the background, and this function returns as soon as the
+    /// sockets have been bound. Use [`connected`](struct.DriverStation.html#method.connected) or
+    /// [`events`](struct.DriverStation.html#method.events) to observe its progress.
+    ///
+    /// Returns an error if the team number is larger than [`MAX_TEAM_NUMBER`](util/constant.MAX_TEAM_NUMBER.html),
+    /// or if the sockets could not be bound. If another driver station is already listening on port 1150, the error is
+    /// [`DsError::PortInUse`](enum.DsError.html#variant.PortInUse).
+    pub async fn build(self) -> Result<DriverStation> {
+        match self.config.runtime.clone() {
+            // Sockets have to be created within the runtime that drives them, so the whole build happens there
+            Some(handle) => match TaskGuard::spawn_on(&handle, self.connect()).await {
+                Ok(ds) => ds,
+                Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+                // The runtime was shut down before the driver station was built
+                Err(_) => Err(DsError::ChannelClosed),
+            },
+            None => self.connect().await,
+        }
+    }
+
+    async fn connect(self) -> Result<DriverStation> {
+        let mut targets = self.targets.targets(self.team_number)?;
+        targets.extend(self.extra_targets.iter().cloned());
+
+        let udp_rx = bind_rx(&self.config).await?;
+        let udp_tx = bind_tx(&self.config).await?;
+        let sim_sock = if self.config.detect_simulator {
+            Some(bind_sim(&self.config).await?)
+        } else {
+            None
+        };
+        let netconsole_sock = if self.config.netconsole {
+            Some(bind_netconsole(&self.config).await?)
+        } else {
+            None
+        };
+        let sender = if self.config.sender_thread {
+            Some(SenderThread::spawn()?)
+        } else {
+            None
+        };
+
+        // Channels to communicate to the threads that make up the application, used to break out of infinite loops when the struct is dropped
+        let (tx, rx) = unbounded_channel::<Signal>();
+
+        #[cfg(feature = "signals")]
+        let termination = self.config.termination;
+
+        // Global state of the driver station
+        let state = Arc::new(DsState::new(self.alliance, self.team_number, self.config));
+
+        let mut tasks = vec![TaskGuard::spawn(udp_conn(
+            state.clone(),
+            targets,
+            rx,
+            udp_rx,
+            udp_tx,
+            sender,
+        ))];
+        if let Some(sim_sock) = sim_sock {
+            tasks.push(TaskGuard::spawn(sim_conn(tx.clone(), sim_sock)));
+        }
+        if state.config().riolog {
+            tasks.push(TaskGuard::spawn(riolog_conn(state.clone(), RIOLOG_PORT)));
+        }
+        #[cfg(feature = "halsim")]
+        if let Some(addr) = state.config().halsim {
+            tasks.push(TaskGuard::spawn(halsim_conn(state.clone(), addr)));
+        }
+        if let Some(netconsole_sock) = netconsole_sock {
+            tasks.push(TaskGuard::spawn(netconsole_conn(
+                state.clone(),
+                netconsole_sock,
+            )));
+        }
+        #[cfg(feature = "signals")]
+        if let Some(action) = termination {
+            tasks.push(TaskGuard::spawn(stop_on_termination(state.clone(), action)));
+        }
+
+        Ok(DriverStation {
+            thread_tx: tx,
+            monitor: DsMonitor::new(state.clone()),
+            state,
+            team_number: self.team_number,
+            targets: self.targets,
+            extra_targets: self.extra_targets,
+            tasks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ds::{CsvLog, DsEvent, DsMode, MatchPhase, MatchTiming};
+
+    /// A driver station on localhost that doesn't clash with the ports of others, or wait for a simulator
+    fn local_builder() -> DriverStationBuilder {
+        DriverStation::builder(4533, Alliance::new_red(1))
+            .bind_address(Ipv4Addr::LOCALHOST.into())
+            .receive_port(0)
+            .without_simulator()
+    }
+
+    #[test]
+    fn tasks_run_on_the_given_runtime() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        // Without IO or timers, building the driver station on this runtime would panic
+        let caller = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        caller.block_on(async {
+            let mut ds = local_builder()
+                .runtime(runtime.handle().clone())
+                .build()
+                .await
+                .unwrap();
+            let _consumer = ds.add_async_tcp_consumer(|_| async {}).await;
+            ds.enable().await.unwrap();
+            assert!(ds.enabled().await);
+            ds.shutdown().await;
+        });
+    }
+
+    #[test]
+    fn shut_down_runtimes_are_reported() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        let caller = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        caller.block_on(async {
+            let ds = local_builder()
+                .runtime(runtime.handle().clone())
+                .build()
+                .await
+                .unwrap();
+            runtime.shutdown_background();
+            let log = CsvLog::new(std::env::temp_dir().join("ds-shut-down-runtime"));
+            assert!(matches!(ds.log_csv(log).await, Err(DsError::ChannelClosed)));
+        });
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watchdog_follows_paused_time() {
+        let mut ds = local_builder()
+            .watchdog(Duration::from_millis(500))
+            .build()
+            .await
+            .unwrap();
+        let mut events = ds.events();
+
+        ds.enable().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(ds.enabled().await);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!ds.enabled().await);
+        assert!(
+            std::iter::from_fn(|| events.try_recv().ok())
+                .any(|event| matches!(event, DsEvent::WatchdogExpired))
+        );
+        ds.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn shutdown_ends_the_practice_match() {
+        let mut ds = local_builder().build().await.unwrap();
+        let mut enabled = ds.watch_enabled();
+        let mut events = ds.events();
+
+        ds.start_practice_match(MatchTiming::default())
+            .await
+            .unwrap();
+        enabled.wait_for(|&enabled| enabled).await.unwrap();
+        ds.shutdown().await;
+        assert!(!*enabled.borrow());
+        assert!(
+            std::iter::from_fn(|| events.try_recv().ok())
+                .any(|event| event == DsEvent::MatchPhaseChanged(MatchPhase::Ended))
+        );
+    }
+
+    #[tokio::test]
+    async fn team_changes_keep_how_targets_were_chosen() {
+        let mut ds = local_builder()
+            .mdns()
+            .add_target("10.0.0.2")
+            .build()
+            .await
+            .unwrap();
+        ds.set_team_number(254).await.unwrap();
+        assert_eq!(
+            ds.targets_for(ds.team_number()).unwrap(),
+            ["roboRIO-254-frc.local", "10.0.0.2"]
+        );
+        ds.shutdown().await;
+
+        let mut ds = local_builder().target("robot.local").build().await.unwrap();
+        ds.set_team_number(254).await.unwrap();
+        assert_eq!(ds.targets_for(ds.team_number()).unwrap(), ["robot.local"]);
+        ds.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn leaving_usb_restores_the_configured_targets() {
+        let mut ds = local_builder()
+            .targets(["robot.local", "10.0.0.2"])
+            .build()
+            .await
+            .unwrap();
+        let (tx, mut rx) = unbounded_channel();
+        let _connection = std::mem::replace(&mut ds.thread_tx, tx);
+
+        ds.set_use_usb(true);
+        ds.set_use_usb(false);
+        let mut sent = std::iter::from_fn(|| match rx.try_recv() {
+            Ok(Signal::NewTarget(targets)) => Some(targets),
+            _ => None,
+        });
+        assert_eq!(sent.next().unwrap(), ["172.22.11.2"]);
+        assert_eq!(sent.next().unwrap(), ["robot.local", "10.0.0.2"]);
+    }
+
+    #[tokio::test]
+    async fn simulators_are_found_at_their_configured_addresses() {
+        use tokio::net::UdpSocket;
+
+        let sim = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        // A free port for the driver station to listen for the simulator on
+        let detect = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut builder = local_builder()
+            .simulator_address(detect)
+            .simulator_target(sim.local_addr().unwrap());
+        // Unlike the other tests, this one listens for the simulator
+        builder.config.detect_simulator = true;
+        let ds = builder.build().await.unwrap();
+
+        sim.send_to(&[0], detect).await.unwrap();
+        let mut buf = [0; 64];
+        let (_, from) = tokio::time::timeout(Duration::from_secs(5), sim.recv_from(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(from.ip(), Ipv4Addr::LOCALHOST);
+        assert_eq!(ds.ds_mode().await, DsMode::Simulation);
+        ds.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn simulation_mode_changes_are_published() {
+        use tokio::net::UdpSocket;
+
+        let sim = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let detect = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut builder = local_builder()
+            .simulator_address(detect)
+            .simulator_target(sim.local_addr().unwrap());
+        // Unlike the other tests, this one listens for the simulator
+        builder.config.detect_simulator = true;
+        let ds = builder.build().await.unwrap();
+        let mut mode = ds.watch_ds_mode();
+        let mut events = ds.events();
+        assert_eq!(*mode.borrow_and_update(), DsMode::Normal);
+
+        // The simulator is considered stopped once it has been silent for 250ms
+        for expected in [DsMode::Simulation, DsMode::Normal] {
+            if expected == DsMode::Simulation {
+                sim.send_to(&[0], detect).await.unwrap();
+            }
+            tokio::time::timeout(Duration::from_secs(5), mode.changed())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(*mode.borrow_and_update(), expected);
+            let changed = loop {
+                match events.recv().await.unwrap() {
+                    DsEvent::DsModeChanged(changed) => break changed,
+                    _ => continue,
+                }
+            };
+            assert_eq!(changed, expected);
+        }
+        ds.shutdown().await;
+    }
+}
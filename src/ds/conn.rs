@@ -1,185 +1,419 @@
-use super::Signal;
+use super::{Signal, now};
 
 use crate::proto::udp::inbound::UdpResponsePacket;
-use crate::proto::udp::outbound::types::{DateTime as DTTag, *};
+use crate::proto::udp::inbound::types::Trace;
+use crate::proto::udp::outbound::UdpControlPacket;
+use crate::proto::udp::outbound::types::{Alliance, Control};
 
-use chrono::{Datelike, Timelike, Utc};
+use bytes::{Bytes, BytesMut};
 use futures_util::sink::SinkExt;
 use futures_util::stream::StreamExt;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::net::{TcpStream, UdpSocket};
+use tokio::net::{TcpSocket, TcpStream, UdpSocket, lookup_host};
 use tokio::sync::mpsc::UnboundedReceiver;
 use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
-use tokio::time::timeout;
+use tokio::sync::watch;
+use tokio::time::timeout_at;
 use tokio_util::codec::Decoder;
 use tokio_util::udp::UdpFramed;
 
-use crate::Result;
 use crate::proto::tcp::DsTcpCodec;
+use crate::proto::tcp::outbound::TcpTag;
 use crate::proto::udp::DsUdpCodec;
+use crate::{DsError, Result, TcpPacket};
 
-use crate::ds::state::{DsMode, DsState};
-use crate::proto::tcp::outbound::TcpTag;
+use crate::ds::battery::BatteryAlert;
+use crate::ds::builder::DsConfig;
+use crate::ds::event::{DsEvent, EstopSource};
+use crate::ds::queue::SharedQueue;
+use crate::ds::received::PacketTime;
+use crate::ds::robot::{RobotState, RobotStateChange};
+use crate::ds::state::{DsMode, DsState, PACKET_PERIOD, call_supplier};
+use crate::netconsole::NETCONSOLE_PORT;
 
 mod backoff;
+mod chaos;
+mod clock;
+mod disconnect;
+mod mdns;
+mod task;
+mod thread;
+mod ticker;
 
+pub use backoff::BackoffPolicy;
 use backoff::ExponentialBackoff;
-use std::io::ErrorKind;
+use chaos::Chaos;
+pub use chaos::ChaosProfile;
+pub(crate) use clock::Clock;
+pub(crate) use clock::{clock_latency, date_time_tag, timezone_tag};
+pub use disconnect::DisconnectPolicy;
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+use std::io::{self, ErrorKind};
+use std::net::{IpAddr, SocketAddr};
+pub(crate) use task::TaskGuard;
+pub(crate) use thread::SenderThread;
+use ticker::Ticker;
+
+/// Binds a UDP socket to `addr`, sharing the port with other sockets if `reuse_address` is set
+fn bind_shared(addr: SocketAddr, reuse_address: bool) -> Result<UdpSocket> {
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(reuse_address)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into()).map_err(|e| match e.kind() {
+        ErrorKind::AddrInUse => DsError::PortInUse(addr.port()),
+        _ => DsError::Io(e),
+    })?;
+    Ok(UdpSocket::from_std(socket.into())?)
+}
+
+/// Binds a UDP socket to `port` on the local address and interface specified by `config`
+async fn bind_udp(config: &DsConfig, port: u16) -> Result<UdpSocket> {
+    let socket = bind_shared(
+        SocketAddr::new(config.bind_addr, port),
+        config.reuse_address,
+    )?;
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    if let Some(ref interface) = config.interface {
+        socket.bind_device(Some(interface.as_bytes()))?;
+    }
+    Ok(socket)
+}
+
+/// Binds the socket that receives status packets from the roboRIO
+pub(crate) async fn bind_rx(config: &DsConfig) -> Result<UdpSocket> {
+    bind_udp(config, config.rx_port).await
+}
+
+/// Binds a socket for sending control packets
+///
+/// The socket is connected to the roboRIO by the send task once its address has been resolved.
+pub(crate) async fn bind_tx(config: &DsConfig) -> Result<UdpSocket> {
+    let socket = bind_udp(config, 0).await?;
+    #[cfg(not(any(
+        target_os = "fuchsia",
+        target_os = "redox",
+        target_os = "solaris",
+        target_os = "illumos",
+        target_os = "haiku"
+    )))]
+    if let Some(dscp) = config.dscp {
+        // DSCP is the upper 6 bits of the TOS byte and of the IPv6 traffic class, the lower 2 are left to ECN
+        let tos = u32::from(dscp) << 2;
+        let marked = SockRef::from(&socket);
+        if config.bind_addr.is_ipv4() {
+            marked.set_tos(tos)?;
+        } else {
+            #[cfg(any(
+                target_os = "android",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "linux",
+                target_os = "macos",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            marked.set_tclass_v6(tos)?;
+            // Dual-stack sockets reach IPv4 roboRIOs too, which not every platform lets an IPv6 socket mark
+            let _ = marked.set_tos(tos);
+        }
+    }
+    Ok(socket)
+}
+
+/// How long to wait for an answer to an mDNS query
+const MDNS_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Resolves `host` to an address for `port` that a socket bound to `local` can send to
+///
+/// `host` may be a hostname, an IPv4 address, or an IPv6 address with or without brackets.
+/// Names in the `.local` domain are queried with mDNS first, as not every system resolves them natively.
+pub(crate) async fn resolve(host: &str, port: u16, local: IpAddr) -> Result<SocketAddr> {
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+
+    if host.ends_with(".local")
+        && let Ok(ip) = mdns::resolve(host, MDNS_TIMEOUT).await
+        && let Some(addr) = reachable(SocketAddr::new(ip.into(), port), local)
+    {
+        return Ok(addr);
+    }
+
+    lookup_host((host, port))
+        .await?
+        .find_map(|addr| reachable(addr, local))
+        .ok_or_else(|| {
+            io::Error::new(ErrorKind::NotFound, format!("Could not resolve {}", host)).into()
+        })
+}
+
+/// Adapts `addr` to the address family of a socket bound to `local`, if that socket can reach it at all
+///
+/// IPv6 sockets are assumed to be dual-stack, and reach IPv4 addresses through their IPv4-mapped form.
+fn reachable(addr: SocketAddr, local: IpAddr) -> Option<SocketAddr> {
+    match (addr.ip(), local) {
+        (IpAddr::V6(_), IpAddr::V4(_)) => None,
+        (IpAddr::V4(ip), IpAddr::V6(_)) => {
+            Some(SocketAddr::new(ip.to_ipv6_mapped().into(), addr.port()))
+        }
+        _ => Some(addr),
+    }
+}
+
+/// Opens a TCP connection to `addr` from the local address and interface specified by `config`
+pub(crate) async fn connect_tcp(config: &DsConfig, addr: SocketAddr) -> Result<TcpStream> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    if config.bind_addr.is_ipv4() == addr.is_ipv4() {
+        socket.bind(SocketAddr::new(config.bind_addr, 0))?;
+    }
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    if let Some(ref interface) = config.interface {
+        socket.bind_device(Some(interface.as_bytes()))?;
+    }
+    Ok(socket.connect(addr).await?)
+}
+
+/// How long to wait before opening the TCP connection again, once it has closed or couldn't be opened
+const TCP_RETRY_DELAY: Duration = Duration::from_millis(500);
 
 /// The root task of the tokio runtime.
 ///
-/// This task connects to the receiving UDP port, and spawns tasks for UDP sending, and for TCP communications once the connection to the RIO has been established.
+/// This task listens on the receiving UDP socket, and spawns tasks for UDP sending, and for TCP communications once the connection to the RIO has been established.
+/// The sockets are bound by the caller so that failures can be reported before any tasks are spawned.
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "udp", skip_all))]
 pub(crate) async fn udp_conn(
     state: Arc<DsState>,
-    mut target_ip: String,
+    mut targets: Vec<String>,
     mut rx: UnboundedReceiver<Signal>,
+    udp_rx: UdpSocket,
+    udp_tx: UdpSocket,
+    sender: Option<SenderThread>,
 ) -> Result<()> {
     let mut tcp_connected = false;
-    let mut tcp_tx = None;
+    let mut tcp_tx: Option<UnboundedSender<Signal>> = None;
+    let mut tcp_task: Option<TaskGuard<Result<()>>> = None;
+    // When the TCP connection may next be opened, so a roboRIO refusing it isn't asked again with every status packet
+    let mut tcp_retry = tokio::time::Instant::now();
 
-    let udp_rx = UdpSocket::bind("0.0.0.0:1150").await?;
     let mut udp_rx = UdpFramed::new(udp_rx, DsUdpCodec);
 
-    let (fwd_tx, mut fwd_rx) = unbounded_channel::<Signal>();
-
-    let send_state = state.clone();
-    let target = target_ip.clone();
-    tokio::spawn(async move {
-        let mut udp_tx = UdpSocket::bind("0.0.0.0:0")
-            .await
-            .expect("Failed to bind tx socket");
-        udp_tx
-            .connect(&format!("{}:1110", target))
-            .await
-            .expect("Failed to connect to target");
-
-        let mut interval = tokio::time::interval(Duration::from_millis(20));
+    let (fwd_tx, fwd_rx) = unbounded_channel::<Signal>();
 
-        //let mut stream = select(interval, fwd_rx);
-        let mut backoff = ExponentialBackoff::new(Duration::new(5, 0));
-
-        loop {
-            tokio::select! {
-                _ = interval.tick() => {
-                    let mut state = send_state.send().write().await;
-                    let v = state.control().encode();
-                    // Massively overengineered considering the _only_ time that this actually starts
-                    // to come into play is directly after the simulator is closed before the DS switches to Normal mode again
-                    // but I don't feel like changing it, and now it's fail safe
-                    match backoff.run(udp_tx.send(&v[..])).await {
-                        Ok(_) => {}
-                        Err((e, dc)) => {
-                            if e.kind() == ErrorKind::ConnectionRefused && dc {
-                                println!("Send socket disconnected");
-                                send_state.recv().write().await.reset();
-                            }
-                        }
-                    }
-                    state.increment_seqnum();
-                }
-                sig = fwd_rx.recv() => match sig {
-                    Some(Signal::NewTarget(ip)) => {
-                        let mut state = send_state.send().write().await;
-                        state.reset_seqnum();
-                        state.disable();
-                        send_state.recv().write().await.reset();
-                        udp_tx = UdpSocket::bind("0.0.0.0:0")
-                            .await
-                            .expect("Failed to bind tx socket");
-                        udp_tx
-                            .connect(&format!("{}:1110", &ip))
-                            .await
-                            .expect("Failed to connect to new target");
-                        backoff.reset();
-                    }
-                    Some(Signal::NewMode(DsMode::Simulation)) => {
-                        let mut state = send_state.send().write().await;
-                        state.reset_seqnum();
-                        state.disable();
-                        send_state.recv().write().await.reset();
-                        udp_tx
-                            .connect("127.0.0.1:1110")
-                            .await
-                            .expect("Failed to connect to simulator socket");
-                        backoff.reset();
-                    }
-                    _ => {}
-                },
-            }
+    // The addresses the send task has resolved the targets to, most preferred first
+    let (ranking_tx, ranking) = watch::channel(Vec::new());
+    let send_task = match sender {
+        Some(ref sender) => {
+            // The socket is moved over to the sender's runtime, so that it is driven by that thread too
+            let udp_tx = udp_tx.into_std()?;
+            let state = state.clone();
+            let targets = targets.clone();
+            sender.spawn_task(async move {
+                let udp_tx = UdpSocket::from_std(udp_tx).inspect_err(|e| {
+                    warn!("Failed to move the send socket to the sender thread: {}", e)
+                })?;
+                send_conn(state, targets, fwd_rx, udp_tx, ranking_tx).await
+            })
         }
-    });
+        None => TaskGuard::spawn(send_conn(
+            state.clone(),
+            targets.clone(),
+            fwd_rx,
+            udp_tx,
+            ranking_tx,
+        )),
+    };
 
     // I need the tokio extension for this, the futures extension to split codecs, and I can't import them both
     // Thanks for coordinating trait names to make using both nicely impossible
 
-    let mut connected = true;
+    let mut recv_chaos_rx = state.chaos().subscribe();
+    let mut chaos = inbound_chaos(&mut recv_chaos_rx);
+
+    let mut connected = false;
+    // The address the send task has been told the roboRIO answered from
+    let mut target_found: Option<IpAddr> = None;
+    // The roboRIO times out from its last packet, so that packets from other addresses don't keep it connected
+    let mut deadline = tokio::time::Instant::now() + state.config().rio_timeout;
+    // The other addresses that packets have been ignored from since the roboRIO was found, each reported once
+    let mut foreign: Vec<IpAddr> = Vec::new();
+    let mut battery = state.config().battery_alerts.map(BatteryAlert::new);
+    let mut battery_filter = state.config().battery_filter;
+    // Whether the last status packet asked for the date
+    let mut date_requested = false;
     loop {
         tokio::select! {
-            packet = timeout(Duration::from_secs(2), udp_rx.next()) => match packet {
+            packet = timeout_at(deadline, udp_rx.next()) => match packet {
                 Ok(timeout_result) => match timeout_result {
                     Some(Ok(packet)) => {
-                        if !connected {
-                            connected = true;
-                        }
-                        let (packet, _): (UdpResponsePacket, _) = packet;
-
-                        if packet.need_date {
-                            let local = Utc::now();
-                            let micros = local.naive_utc().and_utc().timestamp_subsec_micros();
-                            let second = local.time().second() as u8;
-                            let minute = local.time().minute() as u8;
-                            let hour = local.time().hour() as u8;
-                            let day = local.date_naive().day() as u8;
-                            let month = local.date_naive().month0() as u8;
-                            let year = (local.date_naive().year() - 1900) as u8;
-                            let tag = DTTag::new(micros, second, minute, hour, day, month, year);
-                            state.send().write().await.queue_udp(UdpTag::DateTime(tag));
+                        // Faults are injected first, so that packets lost to them count towards the roboRIO timing out
+                        if recv_chaos_rx.has_changed().unwrap_or(false) {
+                            chaos = inbound_chaos(&mut recv_chaos_rx);
                         }
+                        let packets = match chaos {
+                            Some(ref mut chaos) => chaos.apply(packet, now()),
+                            None => vec![packet],
+                        };
+
+                        for (packet, addr) in packets {
+                            // Dual-stack sockets report IPv4 senders by their IPv4-mapped address
+                            let ip = addr.ip().to_canonical();
 
-                        if !tcp_connected {
-                            let (tx, rx) = unbounded_channel::<Signal>();
-                            tcp_tx = Some(tx);
-                            let mode = state.send().read().await.ds_mode();
-                            if mode == DsMode::Normal {
-                                tokio::spawn(tcp_conn(state.clone(), target_ip.clone(), rx));
-                            } else {
-                                tokio::spawn(tcp_conn(state.clone(), "127.0.0.1".to_string(), rx));
+                            // The roboRIO is switched to whenever it answers from a target earlier in the list than the current one
+                            let preferred = target_found.is_some_and(|current| {
+                                let ranking = ranking.borrow();
+                                let rank = |ip| ranking.iter().position(|&other| other == ip).unwrap_or(usize::MAX);
+                                rank(ip) < rank(current)
+                            });
+                            // A stray simulator or second robot on the network would otherwise corrupt the state of this one
+                            if state.config().filter_source
+                                && target_found.is_some_and(|current| current != ip)
+                                && !preferred
+                            {
+                                if !foreign.contains(&ip) {
+                                    foreign.push(ip);
+                                    state.emit(DsEvent::ForeignPacket { from: ip });
+                                }
+                                continue;
                             }
-                            tcp_connected = true;
-                        }
+                            deadline = tokio::time::Instant::now() + state.config().rio_timeout;
 
-                        if packet.status.emergency_stopped() {
-                            let mut send = state.send().write().await;
-                            if !send.estopped() {
-                                send.estop();
+                            if !connected {
+                                connected = true;
+                                state.recv().write().await.set_connected(true);
+                                // The roboRIO may have restarted its seqnums while it was away
+                                state.arrivals().reset();
+                                state.emit(DsEvent::Connected);
+                            }
+                            if target_found.is_none() || preferred {
+                                if preferred && let Some(ref tcp_tx) = tcp_tx {
+                                    let _ = tcp_tx.send(Signal::Disconnect);
+                                    tcp_connected = false;
+                                }
+                                info!("Found roboRIO at {}", ip);
+                                target_found = Some(ip);
+                                foreign.clear();
+                                state.recv().write().await.set_rio_address(Some(ip));
+                                fwd_tx.send(Signal::TargetFound(ip))?;
+                            }
+
+                            let received = PacketTime::now(&state.config().clock);
+                            // The roboRIO echoes the seqnum of the control packet it is responding to
+                            let sent = state.ticks().sent_at(packet.seqnum);
+                            state.arrivals().packet(packet.seqnum, received.instant, sent);
+
+                            // The roboRIO asks with every packet until it is answered, so the request is reported once
+                            if packet.need_date && !date_requested {
+                                state.emit(DsEvent::DateRequested);
+                            }
+                            date_requested = packet.need_date;
+                            if packet.need_date && state.config().time_sync {
+                                state.queue_time().await;
+                            }
+
+                            // The TCP task ends when the connection closes or can't be opened, such as while the roboRIO reboots
+                            if tcp_connected && tcp_task.as_ref().is_some_and(|task| task.is_finished()) {
+                                tcp_connected = false;
+                                tcp_retry = tokio::time::Instant::now() + TCP_RETRY_DELAY;
+                            }
+                            if !tcp_connected && tokio::time::Instant::now() >= tcp_retry {
+                                let (tx, rx) = unbounded_channel::<Signal>();
+                                tcp_tx = Some(tx);
+                                // Replacing the handle aborts any previous TCP task that is still trying to connect
+                                tcp_task = Some(TaskGuard::spawn(tcp_conn(state.clone(), ip, rx)));
+                                tcp_connected = true;
+                            }
+
+                            {
+                                let mut send = state.send().write().await;
+                                if packet.status.emergency_stopped() {
+                                    send.estop(EstopSource::Robot);
+                                }
+                                send.observe_code(packet.trace.is_code_started());
                             }
-                        }
 
-                        let mut state = state.recv().write().await;
-                        state.set_trace(packet.trace);
-                        state.set_battery_voltage(packet.battery);
+                            state.joystick_outputs().send_if_modified(|outputs| {
+                                let changed = *outputs != packet.joystick_outputs;
+                                if changed {
+                                    outputs.clone_from(&packet.joystick_outputs);
+                                }
+                                changed
+                            });
+
+                            state.set_trace(packet.trace);
+                            let robot_state = RobotState::new(packet.status, packet.trace);
+                            let mut recv = state.recv().write().await;
+                            recv.set_received(received);
+                            recv.set_trace(packet.trace);
+                            let voltage = battery_filter.apply(packet.battery);
+                            recv.set_battery_voltage(voltage);
+                            recv.set_battery_raw(packet.battery_raw);
+                            let _ = state.battery().send((received.instant, voltage));
+                            if let Some(level) = battery.as_mut().and_then(|battery| battery.update(voltage)) {
+                                state.emit(DsEvent::BatteryLevelChanged(level));
+                            }
+                            if let Some(from) = recv.set_robot_state(robot_state) {
+                                state.emit(DsEvent::RobotStateChanged(RobotStateChange::new(from, robot_state, received.timestamp)));
+                            }
+                        }
                     }
-                    Some(Err(e)) => println!("Error decoding packet: {:?}", e),
+                    Some(Err(e)) => warn!("Error decoding packet: {:?}", e),
                     None => break,
                 },
                 Err(_) => {
+                    deadline = tokio::time::Instant::now() + state.config().rio_timeout;
                     if connected {
-                        println!("RIO disconnected");
-                        state.recv().write().await.reset();
+                        warn!("RIO disconnected");
+                        if state.config().disconnect_policy != DisconnectPolicy::Resume {
+                            state.disable().await;
+                        }
+                        // The roboRIO rebooting restarts robot code as well
+                        state.send().write().await.observe_code(false);
+                        let mut recv = state.recv().write().await;
+                        recv.reset();
+                        recv.set_connected(false);
+                        if let Some(from) = recv.set_robot_state(RobotState::Disconnected) {
+                            state.emit(DsEvent::RobotStateChanged(RobotStateChange::new(from, RobotState::Disconnected, state.config().clock.millis())));
+                        }
+                        // Nothing is left to stop the controllers rumbling once the roboRIO goes quiet
+                        state.joystick_outputs().send_replace(Vec::new());
+                        state.set_trace(Trace::empty());
+                        if let Some(ref mut battery) = battery {
+                            battery.reset();
+                        }
+                        battery_filter.reset();
+                        date_requested = false;
                         connected = false;
+                        target_found = None;
+                        // The connection is opened again once the roboRIO answers, which may be at a new address
+                        if let Some(ref tcp_tx) = tcp_tx {
+                            let _ = tcp_tx.send(Signal::Disconnect);
+                        }
+                        tcp_connected = false;
+                        fwd_tx.send(Signal::TargetLost)?;
+                        state.emit(DsEvent::Disconnected {
+                            timeout: state.config().rio_timeout,
+                        });
                     }
                 }
             },
             sig = rx.recv() => match sig {
-                Some(Signal::Disconnect) => return Ok(()),
-                Some(Signal::NewTarget(ref target)) => {
+                Some(Signal::Disconnect) => break,
+                Some(Signal::NewTarget(ref new_targets)) => {
                     if let Some(ref tcp_tx) = tcp_tx {
                         let _ = tcp_tx.send(Signal::Disconnect);
                         tcp_connected = false;
                     }
 
-                    target_ip = target.clone();
+                    debug!("Looking for the roboRIO at {:?}", new_targets);
+                    targets = new_targets.clone();
+                    target_found = None;
+                    state.arrivals().reset();
 
                     fwd_tx.send(sig.unwrap())?;
                 }
@@ -191,85 +425,691 @@ pub(crate) async fn udp_conn(
                             tcp_connected = false;
                         }
                         state.send().write().await.set_ds_mode(mode);
+                        state.publish_ds_mode(mode);
+                        target_found = None;
                         if mode == DsMode::Normal {
-                            println!("Exiting simulation mode");
-                            fwd_tx.send(Signal::NewTarget(target_ip.clone()))?;
+                            info!("Exiting simulation mode");
+                            fwd_tx.send(Signal::NewTarget(targets.clone()))?;
+                        } else {
+                            info!("Entering simulation mode");
                         }
                         fwd_tx.send(sig.unwrap())?;
                     }
                 }
+                Some(Signal::TargetFound(_)) | Some(Signal::TargetLost) => {}
                 None => break,
             },
         }
     }
+
+    // Wind down the tasks spawned by this one before returning
+    if let Some(ref tcp_tx) = tcp_tx {
+        let _ = tcp_tx.send(Signal::Disconnect);
+    }
+    if let Some(tcp_task) = tcp_task {
+        // The TCP task may still be waiting on the connection to be established
+        tcp_task.abort();
+        let _ = tcp_task.await;
+    }
+    let _ = fwd_tx.send(Signal::Disconnect);
+    // The send task only stops before being told to if it failed, such as when its timer thread stopped
+    if let Ok(Err(e)) = send_task.await {
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// tokio task sending control packets to the roboRIO every 20ms
+///
+/// Until a roboRIO has responded, control packets are sent to every address in `targets` that can be resolved.
+/// Once the root task reports which of them answered with `Signal::TargetFound`, the socket is connected to that address alone.
+/// Targets earlier in the list than the one that answered keep being probed, so that the connection moves back to them when they come up.
+/// The resolved addresses are published to `ranking` in order, for the root task to compare where the roboRIO answers from.
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "send", skip_all))]
+async fn send_conn(
+    state: Arc<DsState>,
+    mut targets: Vec<String>,
+    mut rx: UnboundedReceiver<Signal>,
+    mut udp_tx: UdpSocket,
+    ranking: watch::Sender<Vec<IpAddr>>,
+) -> Result<()> {
+    let mut ticker = Ticker::new(
+        PACKET_PERIOD,
+        state.config().missed_ticks,
+        state.config().high_resolution_timer,
+    );
+
+    let mut backoff = ExponentialBackoff::new(state.config().backoff);
+    let mut chaos_rx = state.chaos().subscribe();
+    let mut chaos = outbound_chaos(&mut chaos_rx);
+
+    // The address udp_tx is connected to, once a target has answered
+    let mut locked: Option<SocketAddr> = None;
+    let mut simulation = false;
+    // Candidate addresses in order of preference, refreshed periodically in the background
+    let mut candidates: Vec<SocketAddr> = Vec::new();
+    let mut resolving: Option<TaskGuard<Vec<SocketAddr>>> = None;
+    let mut next_resolve = now();
+    // Socket used to probe preferred candidates while connected to another one
+    let mut probe: Option<UdpSocket> = None;
+    // Each packet is encoded into this buffer and split off, reusing its memory once the packet has been sent
+    let mut buf = BytesMut::new();
+    // The joystick supplier due for the next packet, picked while the lock is held to build this one. It is called
+    // without the lock held, so a slow supplier never holds up changes to the control state.
+    let mut supplier = state.send().write().await.due_supplier();
+    // The seqnum, control byte and alliance of the last packet, from which an E-stop is built while the send state is held elsewhere
+    let mut last: Option<(u16, Control, Alliance)> = None;
+    // How many packets have been sent without the send state, whose seqnums it hasn't counted yet
+    let mut sent_unlocked = 0;
+
+    loop {
+        tokio::select! {
+            due = ticker.tick() => {
+                let due = due?;
+                if chaos_rx.has_changed().unwrap_or(false) {
+                    chaos = outbound_chaos(&mut chaos_rx);
+                }
+
+                if let Some(task) = resolving.take_if(|task| task.is_finished()) {
+                    candidates = task.await.unwrap_or_default();
+                    ranking.send_replace(candidates.iter().map(|addr| addr.ip().to_canonical()).collect());
+                }
+
+                // The number of candidates preferred to the one currently connected to, which are all of them until one answers
+                let preferred = match locked {
+                    _ if simulation => 0,
+                    None => candidates.len(),
+                    Some(addr) => candidates.iter().position(|&other| other == addr).unwrap_or(candidates.len()),
+                };
+                let refresh = (locked.is_none() || preferred > 0) && now() >= next_resolve;
+                if refresh {
+                    next_resolve = now() + RESOLVE_INTERVAL;
+                    if resolving.is_none() {
+                        resolving = Some(TaskGuard::spawn(resolve_all(targets.clone(), 1110, state.config().bind_addr)));
+                    }
+                    if locked.is_some() && probe.is_none() {
+                        probe = bind_tx(state.config()).await.ok();
+                    }
+                }
+
+                // The lock is taken once per packet and released before anything is sent, so a slow socket never
+                // holds up changes to the control state either
+                let supplied = supplier.take().map(call_supplier);
+                // An E-stop requested with estop_now doesn't wait for another task to release the send state
+                let locked_send = loop {
+                    tokio::select! {
+                        biased;
+                        send = state.send().write() => break Ok(send),
+                        _ = state.estop_requested_notified() => {
+                            if state.estop_requested()
+                                && let Some(last) = last
+                            {
+                                break Err(last);
+                            }
+                        }
+                    }
+                };
+                let (v, seqnum) = match locked_send {
+                    Ok(mut send) => {
+                        for _ in 0..std::mem::take(&mut sent_unlocked) {
+                            send.increment_seqnum();
+                        }
+                        state.apply_estop_request(&mut send);
+                        send.expire_enable(now());
+                        if let Some(timeout) = state.config().watchdog
+                            && send.expire_watchdog(now(), timeout)
+                        {
+                            state.emit(DsEvent::WatchdogExpired);
+                        }
+                        send.update_practice(now());
+                        send.update_countdown(now());
+                        let packet = send.control_with(supplied);
+                        state.publish_control(&send);
+                        packet.encode_into(&mut buf);
+                        let v = buf.split().freeze();
+                        for event in send.take_events() {
+                            state.emit(event);
+                        }
+                        send.increment_seqnum();
+                        supplier = send.due_supplier();
+                        last = Some((packet.seqnum, packet.control, packet.alliance));
+                        (v, packet.seqnum)
+                    }
+                    Err((seqnum, control, alliance)) => {
+                        warn!("E-stop requested while the send state is held, sending it without the lock");
+                        let packet = UdpControlPacket {
+                            seqnum: seqnum.wrapping_add(1),
+                            control: control.difference(Control::ENABLED) | Control::ESTOP,
+                            request: None,
+                            alliance,
+                            tags: Vec::new(),
+                        };
+                        packet.encode_into(&mut buf);
+                        sent_unlocked += 1;
+                        last = Some((packet.seqnum, packet.control, packet.alliance));
+                        (buf.split().freeze(), packet.seqnum)
+                    }
+                };
+                state.ticks().tick(due.into_std(), now(), seqnum);
+                if refresh
+                    && locked.is_some()
+                    && let Some(ref probe) = probe
+                {
+                    // Preferred candidates are only probed once per interval, as they are likely the same roboRIO
+                    for addr in &candidates[..preferred] {
+                        let _ = probe.send_to(&v[..], addr).await;
+                    }
+                }
+                let packets = match chaos {
+                    Some(ref mut chaos) => chaos.apply(v, now()),
+                    None => vec![v],
+                };
+
+                for v in packets {
+                    if locked.is_none() {
+                        for addr in &candidates {
+                            let _ = udp_tx.send_to(&v[..], addr).await;
+                        }
+                        continue;
+                    }
+
+                    // Massively overengineered considering the _only_ time that this actually starts
+                    // to come into play is directly after the simulator is closed before the DS switches to Normal mode again
+                    // but I don't feel like changing it, and now it's fail safe
+                    match backoff.run(udp_tx.send(&v[..])).await {
+                        Ok(_) => {}
+                        Err((e, dc)) => {
+                            if e.kind() == ErrorKind::ConnectionRefused && dc {
+                                warn!("Send socket disconnected");
+                                state.recv().write().await.reset();
+                            }
+                        }
+                    }
+                }
+            }
+            sig = rx.recv() => match sig {
+                Some(Signal::NewTarget(new_targets)) => {
+                    let mut send = state.send().write().await;
+                    send.reset_seqnum();
+                    send.disable();
+                    state.recv().write().await.reset();
+                    targets = new_targets;
+                    simulation = false;
+                    candidates.clear();
+                    resolving = None;
+                    next_resolve = now();
+                    unlock(&state, &mut udp_tx, &mut locked).await;
+                    backoff.reset();
+                }
+                Some(Signal::TargetFound(ip)) if !simulation => {
+                    if let Some(addr) = reachable(SocketAddr::new(ip, 1110), state.config().bind_addr) {
+                        match udp_tx.connect(addr).await {
+                            Ok(_) => locked = Some(addr),
+                            Err(e) => warn!("Failed to connect to {}: {}", addr, e),
+                        }
+                    }
+                    resolving = None;
+                    backoff.reset();
+                }
+                Some(Signal::TargetLost) if !simulation => {
+                    unlock(&state, &mut udp_tx, &mut locked).await;
+                    next_resolve = now();
+                }
+                Some(Signal::NewMode(DsMode::Simulation)) => {
+                    let mut send = state.send().write().await;
+                    send.reset_seqnum();
+                    send.disable();
+                    state.recv().write().await.reset();
+                    simulation = true;
+                    resolving = None;
+                    if let Some(addr) = reachable(state.config().sim_target, state.config().bind_addr) {
+                        match udp_tx.connect(addr).await {
+                            Ok(_) => locked = Some(addr),
+                            Err(e) => warn!("Failed to connect to simulator socket: {}", e),
+                        }
+                    }
+                    backoff.reset();
+                }
+                Some(Signal::Disconnect) | None => {
+                    // Leave the robot with a disabled packet as the last thing it heard from us
+                    let v = {
+                        let mut send = state.send().write().await;
+                        send.disable();
+                        let packet = send.control_with(None);
+                        state.publish_control(&send);
+                        for event in send.take_events() {
+                            state.emit(event);
+                        }
+                        packet.encode()
+                    };
+                    if locked.is_some() {
+                        let _ = udp_tx.send(&v[..]).await;
+                    } else {
+                        for addr in &candidates {
+                            let _ = udp_tx.send_to(&v[..], addr).await;
+                        }
+                    }
+                    break;
+                }
+                _ => {}
+            },
+        }
+    }
     Ok(())
 }
 
+/// How often candidate targets are resolved again while none of them has answered
+const RESOLVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Resolves every target that can be, concurrently
+async fn resolve_all(targets: Vec<String>, port: u16, local: IpAddr) -> Vec<SocketAddr> {
+    futures::future::join_all(targets.iter().map(|target| resolve(target, port, local)))
+        .await
+        .into_iter()
+        .filter_map(|addr| addr.ok())
+        .collect()
+}
+
+/// Replaces a socket that was connected to a target with a fresh one, so that candidates can be probed again
+async fn unlock(state: &DsState, udp_tx: &mut UdpSocket, locked: &mut Option<SocketAddr>) {
+    if locked.take().is_some() {
+        match bind_tx(state.config()).await {
+            Ok(socket) => *udp_tx = socket,
+            Err(e) => warn!("Failed to rebind send socket: {}", e),
+        }
+    }
+}
+
 /// tokio task for all TCP communications
 ///
-/// This task will decode incoming TCP packets, and call the tcp consumer defined in `state` if it exists.
+/// This task will decode incoming TCP packets, broadcast them, and call the tcp consumers defined in `state` if there are any.
 /// It will also accept packets to send from a channel set in `state`, for tasks such as defining game data.
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "tcp", skip_all, fields(rio = %target_ip)))]
 pub(crate) async fn tcp_conn(
     state: Arc<DsState>,
-    target_ip: String,
+    target_ip: IpAddr,
     mut rx: UnboundedReceiver<Signal>,
 ) -> Result<()> {
-    let conn = TcpStream::connect(&format!("{}:1740", target_ip)).await?;
+    let conn = connect_tcp(
+        state.config(),
+        SocketAddr::new(target_ip, state.config().tcp_port),
+    )
+    .await?;
     let codec = DsTcpCodec.framed(conn);
     let (mut codec_tx, mut codec_rx) = codec.split();
 
-    let (tag_tx, mut tag_rx) = unbounded_channel::<TcpTag>();
-    state.tcp().write().await.set_tcp_tx(Some(tag_tx));
+    let queue = Arc::new(SharedQueue::new(
+        state.config().queue_capacity,
+        state.config().overflow,
+    ));
+    let _link = TcpLink::up(state.clone(), queue.clone());
+    state.tcp().write().await.set_tcp_queue(Some(queue.clone()));
 
-    let state = state.tcp();
     loop {
         tokio::select! {
             packet = codec_rx.next() => match packet {
                 Some(packet) => {
-                    if let Ok(packet) = packet {
-                        let mut state = state.write().await;
-                        if let Some(ref mut consumer) = state.tcp_consumer {
-                            consumer(packet);
-                        }
+                    // The same output is read from riolog when it is used
+                    if let Ok(packet) = packet
+                        && !(state.config().riolog && matches!(packet, TcpPacket::Stdout(_)))
+                    {
+                        let received = PacketTime::now(&state.config().clock);
+                        state.publish_tcp(packet, received).await;
                     }
                 },
                 None => break,
             },
-            _ = rx.recv() => {
-                state.write().await.set_tcp_tx(None);
-            },
-            tag = tag_rx.recv() => match tag {
-                Some(tag) => {
+            _ = rx.recv() => break,
+            tags = queue.next() => {
+                for tag in tags {
                     let _ = codec_tx.send(tag).await;
-                },
-                None => break,
+                }
             }
         }
     }
     Ok(())
 }
 
-pub(crate) async fn sim_conn(tx: UnboundedSender<Signal>) -> Result<()> {
+/// Marks the TCP connection as open until dropped, which happens however the TCP task ends, including being aborted
+///
+/// Dropping it also closes the task's queue, so tags queued afterwards are refused rather than silently lost.
+struct TcpLink(Arc<DsState>, Arc<SharedQueue<TcpTag>>);
+
+impl TcpLink {
+    fn up(state: Arc<DsState>, queue: Arc<SharedQueue<TcpTag>>) -> TcpLink {
+        state.set_tcp_connected(true);
+        TcpLink(state, queue)
+    }
+}
+
+impl Drop for TcpLink {
+    fn drop(&mut self) {
+        self.1.close();
+        self.0.set_tcp_connected(false);
+    }
+}
+
+/// Creates the fault injector for control packets from the latest chaos profile
+fn outbound_chaos(rx: &mut watch::Receiver<Option<ChaosProfile>>) -> Option<Chaos<Bytes>> {
+    (*rx.borrow_and_update())
+        .filter(|profile| profile.outbound)
+        .map(Chaos::new)
+}
+
+/// Creates the fault injector for status packets from the latest chaos profile
+fn inbound_chaos(
+    rx: &mut watch::Receiver<Option<ChaosProfile>>,
+) -> Option<Chaos<(UdpResponsePacket, SocketAddr)>> {
+    (*rx.borrow_and_update())
+        .filter(|profile| profile.inbound)
+        .map(Chaos::new)
+}
+
+/// Binds the socket used to detect a running simulator
+pub(crate) async fn bind_sim(config: &DsConfig) -> Result<UdpSocket> {
+    bind_shared(config.sim_detect_addr, config.reuse_address)
+}
+
+/// Binds the socket that receives NetConsole output from the roboRIO
+pub(crate) async fn bind_netconsole(config: &DsConfig) -> Result<UdpSocket> {
+    bind_udp(config, NETCONSOLE_PORT).await
+}
+
+/// tokio task watching for a simulator
+///
+/// This task exits once the receiving end of `tx` has been dropped, i.e. when the root task has stopped.
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "sim", skip_all))]
+pub(crate) async fn sim_conn(tx: UnboundedSender<Signal>, sock: UdpSocket) -> Result<()> {
     use tokio::time::timeout;
     const SOCK_TIMEOUT: Duration = Duration::from_millis(250);
 
-    let sock = UdpSocket::bind("127.0.0.1:1135").await?;
     let mut buf = [0];
     let mut opmode = DsMode::Normal;
     loop {
-        match timeout(SOCK_TIMEOUT, sock.recv(&mut buf[..])).await {
+        let packet = tokio::select! {
+            _ = tx.closed() => return Ok(()),
+            packet = timeout(SOCK_TIMEOUT, sock.recv(&mut buf[..])) => packet,
+        };
+
+        match packet {
             Ok(_) => {
                 if opmode != DsMode::Simulation {
                     opmode = DsMode::Simulation;
-                    tx.send(Signal::NewMode(DsMode::Simulation)).unwrap();
+                    tx.send(Signal::NewMode(DsMode::Simulation))?;
                 }
             }
             Err(_) => {
                 if opmode != DsMode::Normal {
                     opmode = DsMode::Normal;
-                    tx.send(Signal::NewMode(DsMode::Normal)).unwrap();
+                    tx.send(Signal::NewMode(DsMode::Normal))?;
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ds::queue::OverflowPolicy;
+    use crate::proto::tcp::outbound::GameData;
+    use crate::proto::udp::outbound::types::Alliance;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use tokio::time::timeout;
+
+    #[test]
+    fn reachable_adapts_address_family() {
+        let v4 = SocketAddr::from(([10, 45, 33, 2], 1110));
+        let v6 = SocketAddr::from((Ipv6Addr::LOCALHOST, 1110));
+
+        assert_eq!(reachable(v4, Ipv4Addr::UNSPECIFIED.into()), Some(v4));
+        assert_eq!(reachable(v6, Ipv4Addr::UNSPECIFIED.into()), None);
+        assert_eq!(reachable(v6, Ipv6Addr::UNSPECIFIED.into()), Some(v6));
+        assert_eq!(
+            reachable(v4, Ipv6Addr::UNSPECIFIED.into()).map(|addr| addr.ip().to_canonical()),
+            Some(v4.ip())
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_ipv6_literals() {
+        let local = Ipv6Addr::UNSPECIFIED.into();
+        let expected = SocketAddr::from((Ipv6Addr::LOCALHOST, 1110));
+        assert_eq!(resolve("::1", 1110, local).await.unwrap(), expected);
+        assert_eq!(resolve("[::1]", 1110, local).await.unwrap(), expected);
+        assert!(
+            resolve("::1", 1110, Ipv4Addr::UNSPECIFIED.into())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn ports_in_use_are_reported() {
+        let config = DsConfig {
+            bind_addr: Ipv4Addr::LOCALHOST.into(),
+            rx_port: 0,
+            ..DsConfig::default()
+        };
+        let first = bind_rx(&config).await.unwrap();
+        let port = first.local_addr().unwrap().port();
+
+        let config = DsConfig {
+            rx_port: port,
+            ..config
+        };
+        assert!(matches!(
+            bind_rx(&config).await,
+            Err(DsError::PortInUse(p)) if p == port
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn control_socket_is_marked() {
+        let config = DsConfig {
+            bind_addr: Ipv4Addr::LOCALHOST.into(),
+            dscp: Some(46),
+            ..DsConfig::default()
+        };
+        let socket = bind_tx(&config).await.unwrap();
+        assert_eq!(SockRef::from(&socket).tos().unwrap(), 184);
+
+        let config = DsConfig {
+            bind_addr: Ipv6Addr::LOCALHOST.into(),
+            ..config
+        };
+        let socket = bind_tx(&config).await.unwrap();
+        assert_eq!(SockRef::from(&socket).tclass_v6().unwrap(), 184);
+    }
+
+    #[tokio::test]
+    async fn estop_now_is_sent_while_the_state_is_held() {
+        let rio = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let config = DsConfig {
+            bind_addr: Ipv4Addr::LOCALHOST.into(),
+            sim_target: rio.local_addr().unwrap(),
+            ..DsConfig::default()
+        };
+        let udp_tx = bind_tx(&config).await.unwrap();
+        let state = Arc::new(DsState::new(Alliance::new_red(1), 4533, config));
+        let (tx, rx) = unbounded_channel();
+        let (ranking, _) = watch::channel(Vec::new());
+        let _task = TaskGuard::spawn(send_conn(state.clone(), Vec::new(), rx, udp_tx, ranking));
+        tx.send(Signal::NewMode(DsMode::Simulation)).unwrap();
+
+        let mut buf = [0; 64];
+        let estopped = async |buf: &mut [u8]| {
+            let n = timeout(Duration::from_secs(1), rio.recv(buf))
+                .await
+                .unwrap()
+                .unwrap();
+            n > 3 && buf[3] & Control::ESTOP.bits() != 0
+        };
+        assert!(!estopped(&mut buf).await);
+
+        let held = state.send().write().await;
+        state.estop_now();
+        while !estopped(&mut buf).await {}
+        drop(held);
+
+        tokio::time::sleep(PACKET_PERIOD * 3).await;
+        assert!(state.send().read().await.estopped());
+        assert!(!state.estop_requested());
+    }
+
+    #[tokio::test]
+    async fn tcp_link_follows_task() {
+        let state = Arc::new(DsState::test_default());
+        let mut events = state.events().subscribe();
+
+        let queue = Arc::new(SharedQueue::new(1, OverflowPolicy::DropOldest));
+        let link = TcpLink::up(state.clone(), queue.clone());
+        assert!(state.tcp_connected());
+        // Aborting the task drops its link the same as returning does
+        drop(link);
+        assert!(!state.tcp_connected());
+        assert!(queue.is_closed());
+
+        assert_eq!(events.recv().await.unwrap(), DsEvent::TcpConnected);
+        assert_eq!(events.recv().await.unwrap(), DsEvent::TcpDisconnected);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn tcp_queue_is_closed_with_the_connection() {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let config = DsConfig {
+            bind_addr: Ipv4Addr::LOCALHOST.into(),
+            tcp_port: listener.local_addr().unwrap().port(),
+            ..DsConfig::default()
+        };
+        let state = Arc::new(DsState::new(Alliance::new_red(1), 4533, config));
+        let (_tx, rx) = unbounded_channel();
+        let task = tokio::spawn(tcp_conn(state.clone(), Ipv4Addr::LOCALHOST.into(), rx));
+
+        let gsm = || {
+            TcpTag::GameData(GameData {
+                gsm: "LRL".to_string(),
+            })
+        };
+        let (conn, _) = listener.accept().await.unwrap();
+        while state.tcp().read().await.queue_tcp(gsm()).is_err() {
+            tokio::task::yield_now().await;
+        }
+        // The roboRIO closes the connection, ending the task
+        drop(conn);
+        timeout(Duration::from_secs(1), task)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        assert!(!state.tcp_connected());
+        let tcp = state.tcp().read().await;
+        assert!(matches!(tcp.queue_tcp(gsm()), Err(DsError::NotConnected)));
+        assert_eq!(tcp.tcp_queue_len(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dropped_status_packets_time_out() {
+        let config = DsConfig {
+            bind_addr: Ipv4Addr::LOCALHOST.into(),
+            rx_port: 0,
+            ..DsConfig::default()
+        };
+        let udp_rx = bind_rx(&config).await.unwrap();
+        let rx_addr = udp_rx.local_addr().unwrap();
+        let udp_tx = bind_tx(&config).await.unwrap();
+        let state = Arc::new(DsState::new(Alliance::new_red(1), 4533, config));
+        let mut events = state.events().subscribe();
+        let (_tx, rx) = unbounded_channel();
+        let _task = TaskGuard::spawn(udp_conn(
+            state.clone(),
+            vec!["127.0.0.1".to_string()],
+            rx,
+            udp_rx,
+            udp_tx,
+            None,
+        ));
+
+        let rio = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let _status = TaskGuard::spawn(async move {
+            loop {
+                let _ = rio
+                    .send_to(&[0x00, 0x01, 0x01, 0x00, 0x30, 0x0c, 0x80, 0x00], rx_addr)
+                    .await;
+                tokio::time::sleep(PACKET_PERIOD).await;
+            }
+        });
+
+        let mut wait_for = async |expected: fn(&DsEvent) -> bool| {
+            timeout(Duration::from_secs(5), async {
+                while !expected(&events.recv().await.unwrap()) {}
+            })
+            .await
+            .unwrap()
+        };
+        wait_for(|event| matches!(event, DsEvent::Connected)).await;
+        // The roboRIO keeps answering, but none of it gets through
+        state.chaos().send_replace(Some(ChaosProfile {
+            drop_rate: 1.0,
+            ..ChaosProfile::none()
+        }));
+        wait_for(|event| matches!(event, DsEvent::Disconnected { .. })).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tcp_is_reopened_after_closing() {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let tcp_addr = listener.local_addr().unwrap();
+        let config = DsConfig {
+            bind_addr: Ipv4Addr::LOCALHOST.into(),
+            rx_port: 0,
+            tcp_port: tcp_addr.port(),
+            ..DsConfig::default()
+        };
+        let udp_rx = bind_rx(&config).await.unwrap();
+        let rx_addr = udp_rx.local_addr().unwrap();
+        let udp_tx = bind_tx(&config).await.unwrap();
+        let state = Arc::new(DsState::new(Alliance::new_red(1), 4533, config));
+        let (_tx, rx) = unbounded_channel();
+        let _task = TaskGuard::spawn(udp_conn(
+            state.clone(),
+            vec!["127.0.0.1".to_string()],
+            rx,
+            udp_rx,
+            udp_tx,
+            None,
+        ));
+
+        // A roboRIO that keeps answering while its TCP server goes away and comes back
+        let rio = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let _status = TaskGuard::spawn(async move {
+            loop {
+                let _ = rio
+                    .send_to(&[0x00, 0x01, 0x01, 0x00, 0x30, 0x0c, 0x80, 0x00], rx_addr)
+                    .await;
+                tokio::time::sleep(PACKET_PERIOD).await;
+            }
+        });
+
+        let (conn, _) = listener.accept().await.unwrap();
+        drop(conn);
+        drop(listener);
+        // Connections are refused for a while, which mustn't stop the connection from being opened later
+        tokio::time::sleep(TCP_RETRY_DELAY * 4).await;
+        assert!(!state.tcp_connected());
+
+        let listener = TcpListener::bind(tcp_addr).await.unwrap();
+        let _conn = tokio::time::timeout(Duration::from_secs(5), listener.accept())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -2,21 +2,57 @@ use std::future::Future;
 use std::time::Duration;
 use tokio::time;
 
+use crate::util::XorShift;
+
+/// Describes how long to wait between retries when sending to the roboRIO fails
+///
+/// The `n`th consecutive failure waits `initial * multiplier^n`, capped at `max`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BackoffPolicy {
+    /// The wait after the first failure
+    pub initial: Duration,
+    /// The factor the wait grows by with each further failure
+    pub multiplier: f64,
+    /// The longest the wait may grow to
+    pub max: Duration,
+    /// The fraction of each wait, in `0.0..=1.0`, that is randomly shaved off to spread out retries
+    pub jitter: f32,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        BackoffPolicy {
+            initial: Duration::from_millis(1),
+            multiplier: 20.0,
+            max: Duration::from_secs(5),
+            jitter: 0.0,
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// Returns the wait for the given number of previous consecutive failures, before jitter is applied
+    fn delay(&self, attempt: u32) -> Duration {
+        let nanos = self.initial.as_nanos() as f64
+            * self.multiplier.powi(attempt.min(i32::MAX as u32) as i32);
+        Duration::from_nanos(nanos.min(self.max.as_nanos() as f64).round() as u64)
+    }
+}
+
 pub struct ExponentialBackoff {
     attempt: u32,
-    max_timeout: Duration,
-    use_max: bool,
+    policy: BackoffPolicy,
     timeout: Option<Duration>,
+    rng: XorShift,
 }
 
 impl ExponentialBackoff {
-    #[inline(always)]
-    pub const fn new(max_timeout: Duration) -> ExponentialBackoff {
+    pub fn new(policy: BackoffPolicy) -> ExponentialBackoff {
         ExponentialBackoff {
             attempt: 0,
-            max_timeout,
-            use_max: false,
+            policy,
             timeout: None,
+            rng: XorShift::from_time(),
         }
     }
 
@@ -25,7 +61,7 @@ impl ExponentialBackoff {
         fut: impl Future<Output = Result<O, E>>,
     ) -> Result<O, (E, bool)> {
         if let Some(timeout) = self.timeout {
-            println!("Backoff: waiting {:?}", timeout);
+            debug!("Backoff: waiting {:?}", timeout);
             time::sleep(timeout).await;
         }
         match fut.await {
@@ -36,31 +72,36 @@ impl ExponentialBackoff {
             Err(e) => {
                 let disconnected = self.attempt == 0;
                 self.calculate_wait();
-                self.attempt += 1;
+                self.attempt = self.attempt.saturating_add(1);
                 Err((e, disconnected))
             }
         }
     }
 
     pub fn reset(&mut self) {
-        self.use_max = false;
         self.attempt = 0;
         self.timeout = None;
     }
 
     fn calculate_wait(&mut self) {
-        // Short circuit path if we're already at the point of reaching the max timeout
-        if self.use_max {
-            return;
-        }
+        let delay = self.policy.delay(self.attempt);
+        let jitter = self.policy.jitter.clamp(0.0, 1.0) * self.rng.next_f32();
 
-        let backoff_millis = 20u64.pow(self.attempt);
+        self.timeout = Some(delay.mul_f32(1.0 - jitter));
+    }
+}
 
-        let delay = Duration::from_millis(backoff_millis).min(self.max_timeout);
-        if delay == self.max_timeout {
-            self.use_max = true;
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
 
-        self.timeout = Some(delay);
+    #[test]
+    fn default_policy_progression() {
+        let policy = BackoffPolicy::default();
+        assert_eq!(policy.delay(0), Duration::from_millis(1));
+        assert_eq!(policy.delay(1), Duration::from_millis(20));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+        assert_eq!(policy.delay(3), Duration::from_secs(5));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(5));
     }
 }
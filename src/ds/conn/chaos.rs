@@ -0,0 +1,169 @@
+use std::time::{Duration, Instant};
+
+use crate::util::XorShift;
+
+/// Describes how packets should be disturbed while chaos mode is active
+///
+/// All rates are probabilities in `0.0..=1.0`, rolled independently for each packet.
+/// Chaos mode is intended for validating that applications behave sanely on a bad link,
+/// and should never be enabled while driving a robot for real.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ChaosProfile {
+    /// Probability that a packet is silently dropped
+    pub drop_rate: f32,
+    /// Probability that a packet is held back for a random duration up to `max_delay`
+    pub delay_rate: f32,
+    /// Probability that a packet is delivered twice
+    pub duplicate_rate: f32,
+    /// Probability that a packet is held back and delivered after the one following it
+    pub reorder_rate: f32,
+    /// The longest a delayed packet will be held back
+    pub max_delay: Duration,
+    /// Whether the profile applies to control packets sent to the roboRIO
+    pub outbound: bool,
+    /// Whether the profile applies to status packets received from the roboRIO
+    ///
+    /// Delayed inbound packets are released when the next packet arrives.
+    pub inbound: bool,
+    /// Seed for the random number generator, so that a run can be reproduced
+    pub seed: u64,
+}
+
+impl ChaosProfile {
+    /// A profile that leaves all traffic untouched, to be customized with struct update syntax
+    pub const fn none() -> ChaosProfile {
+        ChaosProfile {
+            drop_rate: 0.0,
+            delay_rate: 0.0,
+            duplicate_rate: 0.0,
+            reorder_rate: 0.0,
+            max_delay: Duration::from_millis(100),
+            outbound: true,
+            inbound: true,
+            seed: 0x5eed_1bad_f1e1_d000,
+        }
+    }
+
+    /// A rough approximation of a congested field network
+    pub const fn field_wifi() -> ChaosProfile {
+        ChaosProfile {
+            drop_rate: 0.05,
+            delay_rate: 0.10,
+            duplicate_rate: 0.01,
+            reorder_rate: 0.02,
+            max_delay: Duration::from_millis(150),
+            ..ChaosProfile::none()
+        }
+    }
+}
+
+impl Default for ChaosProfile {
+    fn default() -> Self {
+        ChaosProfile::none()
+    }
+}
+
+/// Applies a `ChaosProfile` to a stream of packets
+pub(crate) struct Chaos<T> {
+    profile: ChaosProfile,
+    rng: XorShift,
+    /// A packet waiting to be delivered after its successor
+    held: Option<T>,
+    /// Packets waiting until the paired `Instant` to be delivered
+    delayed: Vec<(Instant, T)>,
+}
+
+impl<T: Clone> Chaos<T> {
+    pub fn new(profile: ChaosProfile) -> Chaos<T> {
+        Chaos {
+            profile,
+            rng: XorShift::new(profile.seed),
+            held: None,
+            delayed: Vec::new(),
+        }
+    }
+
+    /// Passes `item` through the profile, returning every packet that should be delivered at `now`
+    pub fn apply(&mut self, item: T, now: Instant) -> Vec<T> {
+        let mut out = self.due(now);
+
+        if self.roll(self.profile.drop_rate) {
+            return out;
+        }
+
+        if self.roll(self.profile.delay_rate) {
+            let delay = self.profile.max_delay.mul_f32(self.rng.next_f32());
+            self.delayed.push((now + delay, item));
+            return out;
+        }
+
+        if self.held.is_none() && self.roll(self.profile.reorder_rate) {
+            self.held = Some(item);
+            return out;
+        }
+
+        if self.roll(self.profile.duplicate_rate) {
+            out.push(item.clone());
+        }
+        out.push(item);
+
+        if let Some(held) = self.held.take() {
+            out.push(held);
+        }
+
+        out
+    }
+
+    /// Releases all delayed packets whose delay has elapsed by `now`
+    pub fn due(&mut self, now: Instant) -> Vec<T> {
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < self.delayed.len() {
+            if self.delayed[i].0 <= now {
+                out.push(self.delayed.remove(i).1);
+            } else {
+                i += 1;
+            }
+        }
+        out
+    }
+
+    fn roll(&mut self, rate: f32) -> bool {
+        rate > 0.0 && self.rng.next_f32() < rate
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn passthrough_profile_preserves_order() {
+        let mut chaos = Chaos::new(ChaosProfile::none());
+        let now = Instant::now();
+        let out: Vec<u16> = (0..100).flat_map(|i| chaos.apply(i, now)).collect();
+        assert_eq!(out, (0..100).collect::<Vec<u16>>());
+    }
+
+    #[test]
+    fn drop_everything() {
+        let mut chaos = Chaos::new(ChaosProfile {
+            drop_rate: 1.0,
+            ..ChaosProfile::none()
+        });
+        let now = Instant::now();
+        assert!((0..100).all(|i| chaos.apply(i, now).is_empty()));
+    }
+
+    #[test]
+    fn delayed_packets_are_released() {
+        let mut chaos = Chaos::new(ChaosProfile {
+            delay_rate: 1.0,
+            max_delay: Duration::from_millis(50),
+            ..ChaosProfile::none()
+        });
+        let now = Instant::now();
+        assert!(chaos.apply(1u8, now).is_empty());
+        assert_eq!(chaos.due(now + Duration::from_millis(50)), vec![1]);
+    }
+}
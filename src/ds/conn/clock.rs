@@ -0,0 +1,96 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime as ChronoDateTime, Datelike, Timelike, Utc};
+
+use crate::ds::PACKET_PERIOD;
+use crate::proto::udp::outbound::types::{DateTime, Timezone};
+
+/// The source of the time that the roboRIO's clock is set to
+#[derive(Clone, Default)]
+pub(crate) struct Clock(Option<Arc<dyn Fn() -> SystemTime + Send + Sync>>);
+
+impl Clock {
+    pub fn new(now: impl Fn() -> SystemTime + Send + Sync + 'static) -> Clock {
+        Clock(Some(Arc::new(now)))
+    }
+
+    /// Returns the current time, from the system clock unless another source was given
+    pub fn now(&self) -> SystemTime {
+        match self.0 {
+            Some(ref now) => now(),
+            None => SystemTime::now(),
+        }
+    }
+
+    /// Returns the current time in milliseconds since the Unix epoch
+    pub fn millis(&self) -> u64 {
+        self.now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64)
+    }
+}
+
+impl fmt::Debug for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() {
+            "Clock(custom)"
+        } else {
+            "Clock(system)"
+        })
+    }
+}
+
+/// Encodes `time` as the DateTime tag sent when the roboRIO asks for the date
+pub(crate) fn date_time_tag(time: SystemTime) -> DateTime {
+    let time = ChronoDateTime::<Utc>::from(time);
+    DateTime::new(
+        time.timestamp_subsec_micros(),
+        time.second() as u8,
+        time.minute() as u8,
+        time.hour() as u8,
+        time.day() as u8,
+        time.month0() as u8,
+        (time.year() - 1900) as u8,
+    )
+}
+
+/// Estimates how long the DateTime tag takes to reach the roboRIO once it is created
+///
+/// The tag waits half a packet period on average to be sent, then takes about half the round trip time to arrive.
+pub(crate) fn clock_latency(trip_time: Option<Duration>) -> Duration {
+    PACKET_PERIOD / 2 + trip_time.unwrap_or_default() / 2
+}
+
+/// Builds the Timezone tag sent alongside the DateTime tag, naming `timezone` or else the host's timezone
+///
+/// The roboRIO is left in UTC if the host's timezone can't be found.
+pub(crate) fn timezone_tag(timezone: Option<&str>) -> Timezone {
+    match timezone {
+        Some(timezone) => Timezone::new(timezone),
+        None => Timezone::new(iana_time_zone::get_timezone().unwrap_or_else(|e| {
+            warn!("Couldn't find the host's timezone, using UTC: {}", e);
+            "UTC".to_string()
+        })),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::proto::udp::outbound::types::Tag;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn tag_encodes_the_given_time() {
+        // 2024-03-05 17:23:04.5 UTC
+        let time = UNIX_EPOCH + Duration::from_millis(1_709_659_384_500);
+        let expected = DateTime::new(500_000, 4, 23, 17, 5, 2, 124);
+        assert_eq!(date_time_tag(time).construct(), expected.construct());
+
+        let clock = Clock::new(move || time);
+        assert_eq!(clock.now(), time);
+        assert_eq!(clock.millis(), 1_709_659_384_500);
+    }
+}
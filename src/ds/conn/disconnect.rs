@@ -0,0 +1,22 @@
+/// What happens to the robot's enable state when the roboRIO stops responding
+///
+/// See [`DriverStationBuilder::disconnect_policy`](struct.DriverStationBuilder.html#method.disconnect_policy).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DisconnectPolicy {
+    /// Disables the robot, so it stays disabled when it reconnects until it is enabled again
+    ///
+    /// The robot can be enabled while disconnected, and is then enabled as soon as it reconnects.
+    #[default]
+    Disable,
+    /// Keeps sending the enable state from before the disconnect, so a robot that was enabled is enabled again as soon
+    /// as it reconnects
+    ///
+    /// This suits test rigs that should carry on after a brief dropout, but a robot can start moving without warning.
+    Resume,
+    /// Disables the robot, and rejects enabling it until it has reconnected
+    ///
+    /// [`enable`](struct.DriverStation.html#method.enable) returns
+    /// [`DsError::NotConnected`](enum.DsError.html#variant.NotConnected) while the roboRIO isn't responding, so the
+    /// robot is only ever enabled by a request made while it was connected.
+    RequireReenable,
+}
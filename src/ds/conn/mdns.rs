@@ -0,0 +1,173 @@
+//! A minimal mDNS resolver, sufficient to find a roboRIO advertising `roboRIO-TEAM-frc.local`
+//!
+//! Queries are sent from an ephemeral port, which per RFC 6762 §6.7 makes responders answer
+//! directly to us with a conventional unicast DNS response.
+
+use std::io::{self, ErrorKind};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio::net::UdpSocket;
+use tokio::time::{Instant, timeout_at};
+
+use crate::ext::BufExt;
+use crate::util::XorShift;
+
+const MDNS_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+
+const TYPE_A: u16 = 1;
+const CLASS_IN: u16 = 1;
+
+/// Resolves `host` to an IPv4 address by querying the local network with mDNS
+pub(crate) async fn resolve(host: &str, wait: Duration) -> io::Result<Ipv4Addr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    let id = XorShift::from_time().next_u64() as u16;
+    socket
+        .send_to(&query(id, host)?, SocketAddr::from((MDNS_ADDR, MDNS_PORT)))
+        .await?;
+
+    let deadline = Instant::now() + wait;
+    let mut buf = [0; 1500];
+    loop {
+        let n = match timeout_at(deadline, socket.recv(&mut buf)).await {
+            Ok(n) => n?,
+            Err(_) => {
+                return Err(io::Error::new(
+                    ErrorKind::TimedOut,
+                    format!("No mDNS response for {}", host),
+                ));
+            }
+        };
+
+        if let Some(ip) = parse_response(id, &buf[..n]) {
+            return Ok(ip);
+        }
+    }
+}
+
+/// Encodes a DNS query for the A record of `host`
+fn query(id: u16, host: &str) -> io::Result<BytesMut> {
+    let mut buf = BytesMut::with_capacity(12 + host.len() + 6);
+    buf.put_u16(id);
+    buf.put_u16(0); // Flags, a standard query
+    buf.put_u16(1); // Question count
+    buf.put_u16(0); // Answer count
+    buf.put_u16(0); // Authority count
+    buf.put_u16(0); // Additional count
+
+    for label in host.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid hostname {}", host),
+            ));
+        }
+        buf.put_u8(label.len() as u8);
+        buf.put_slice(label.as_bytes());
+    }
+    buf.put_u8(0);
+
+    buf.put_u16(TYPE_A);
+    buf.put_u16(CLASS_IN);
+    Ok(buf)
+}
+
+/// Extracts the first A record from a response to the query with the given `id`
+fn parse_response(id: u16, mut buf: &[u8]) -> Option<Ipv4Addr> {
+    if buf.read_u16_be().ok()? != id {
+        return None;
+    }
+    let flags = buf.read_u16_be().ok()?;
+    // Only responses are of interest
+    if flags & 0x8000 == 0 {
+        return None;
+    }
+    let questions = buf.read_u16_be().ok()?;
+    let answers = buf.read_u16_be().ok()?;
+    let _authority = buf.read_u16_be().ok()?;
+    let _additional = buf.read_u16_be().ok()?;
+
+    for _ in 0..questions {
+        skip_name(&mut buf)?;
+        // Type and class
+        buf.read_u32_be().ok()?;
+    }
+
+    for _ in 0..answers {
+        skip_name(&mut buf)?;
+        let ty = buf.read_u16_be().ok()?;
+        // The top bit of the class is the mDNS cache-flush bit
+        let class = buf.read_u16_be().ok()? & 0x7fff;
+        let _ttl = buf.read_u32_be().ok()?;
+        let len = buf.read_u16_be().ok()? as usize;
+        if buf.remaining() < len {
+            return None;
+        }
+
+        if ty == TYPE_A && class == CLASS_IN && len == 4 {
+            return Some(Ipv4Addr::new(buf[0], buf[1], buf[2], buf[3]));
+        }
+        buf.advance(len);
+    }
+
+    None
+}
+
+/// Skips over a possibly compressed domain name
+fn skip_name(buf: &mut &[u8]) -> Option<()> {
+    loop {
+        let len = buf.read_u8().ok()?;
+        match len {
+            0 => return Some(()),
+            // A compression pointer ends the name
+            len if len & 0xc0 == 0xc0 => {
+                buf.read_u8().ok()?;
+                return Some(());
+            }
+            len => {
+                if buf.remaining() < len as usize {
+                    return None;
+                }
+                buf.advance(len as usize);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encodes_query() {
+        let buf = query(0x1234, "roboRIO-4533-frc.local").unwrap();
+        let mut expected = vec![0x12, 0x34, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 16];
+        expected.extend_from_slice(b"roboRIO-4533-frc");
+        expected.push(5);
+        expected.extend_from_slice(b"local");
+        expected.extend_from_slice(&[0, 0, 1, 0, 1]);
+        assert_eq!(&buf[..], &expected[..]);
+    }
+
+    #[test]
+    fn parses_compressed_answer() {
+        let mut response = query(0x1234, "roboRIO-4533-frc.local").unwrap().to_vec();
+        // Mark as an authoritative response with one answer
+        response[2] = 0x84;
+        response[7] = 1;
+        // Name is a pointer to the question, class has the cache-flush bit set
+        response.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0x80, 1, 0, 0, 0, 120, 0, 4, 10, 45, 33, 2]);
+
+        assert_eq!(
+            parse_response(0x1234, &response),
+            Some(Ipv4Addr::new(10, 45, 33, 2))
+        );
+        assert_eq!(parse_response(0x4321, &response), None);
+        assert_eq!(
+            parse_response(0x1234, &response[..response.len() - 2]),
+            None
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::runtime::Handle;
+use tokio::task::{JoinError, JoinHandle};
+
+/// A handle to a spawned task that aborts the task when dropped
+///
+/// `JoinHandle` detaches its task when dropped, which lets connection tasks outlive the `DriverStation` that spawned them.
+pub(crate) struct TaskGuard<T>(JoinHandle<T>);
+
+impl<T> TaskGuard<T> {
+    pub fn spawn<F>(fut: F) -> TaskGuard<T>
+    where
+        F: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        TaskGuard(tokio::spawn(fut))
+    }
+
+    /// Spawns `fut` on the runtime behind `handle`, rather than the current one
+    pub fn spawn_on<F>(handle: &Handle, fut: F) -> TaskGuard<T>
+    where
+        F: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        TaskGuard(handle.spawn(fut))
+    }
+
+    pub fn abort(&self) {
+        self.0.abort();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+}
+
+impl<T> Future for TaskGuard<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx)
+    }
+}
+
+impl<T> Drop for TaskGuard<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
@@ -0,0 +1,65 @@
+use std::future::Future;
+use std::io;
+use std::thread;
+
+use thread_priority::ThreadPriority;
+use tokio::runtime::{Builder, Handle};
+use tokio::sync::oneshot;
+
+use super::TaskGuard;
+
+/// A single threaded runtime driven by its own OS thread
+///
+/// Tasks spawned on it aren't delayed by whatever else the application is running on its own runtime, such as UI or
+/// logging work. The runtime shuts down, cancelling its tasks, when this is dropped.
+pub(crate) struct SenderThread {
+    handle: Handle,
+    // Dropping the sender wakes the thread, which then exits
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl SenderThread {
+    /// Starts the thread, raising its scheduling priority where the platform allows it
+    pub fn spawn() -> io::Result<SenderThread> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let handle = runtime.handle().clone();
+        let (shutdown, stopped) = oneshot::channel::<()>();
+
+        thread::Builder::new()
+            .name("ds-sender".to_string())
+            .spawn(move || {
+                // Raising the priority needs elevated privileges on most platforms, such as CAP_SYS_NICE on Linux,
+                // without which the thread keeps the default priority
+                let _ = thread_priority::set_current_thread_priority(ThreadPriority::Max);
+                let _ = runtime.block_on(stopped);
+            })?;
+
+        Ok(SenderThread {
+            handle,
+            _shutdown: shutdown,
+        })
+    }
+
+    pub fn spawn_task<F>(&self, fut: F) -> TaskGuard<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        TaskGuard::spawn_on(&self.handle, fut)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn tasks_run_on_the_thread() {
+        let sender = SenderThread::spawn().unwrap();
+        let name = sender
+            .spawn_task(async { thread::current().name().map(str::to_string) })
+            .await
+            .unwrap();
+        assert_eq!(name.as_deref(), Some("ds-sender"));
+    }
+}
@@ -0,0 +1,95 @@
+use std::thread;
+use std::time::Duration;
+
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+
+use crate::{DsError, Result};
+
+/// The schedule control packets are sent on
+pub(crate) enum Ticker {
+    /// Ticks from the runtime's timer, which is only as precise as the platform's sleep
+    Runtime(Interval),
+    /// Ticks from a thread that sleeps precisely, for platforms with a coarse timer
+    Precise {
+        ticks: UnboundedReceiver<Instant>,
+        behavior: MissedTickBehavior,
+    },
+}
+
+impl Ticker {
+    pub fn new(period: Duration, behavior: MissedTickBehavior, precise: bool) -> Ticker {
+        if !precise {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(behavior);
+            return Ticker::Runtime(interval);
+        }
+
+        let (tx, ticks) = unbounded_channel();
+        thread::spawn(move || {
+            let sleeper = spin_sleep::SpinSleeper::default();
+            let mut due = Instant::now();
+            // The thread stops once the ticker has been dropped
+            while tx.send(due).is_ok() {
+                due += period;
+                let now = Instant::now();
+                if due > now {
+                    sleeper.sleep(due - now);
+                }
+            }
+        });
+        Ticker::Precise { ticks, behavior }
+    }
+
+    /// Waits for the next tick, returning when it was due
+    ///
+    /// Returns [`DsError::ChannelClosed`] if the thread timing precise ticks has stopped.
+    pub async fn tick(&mut self) -> Result<Instant> {
+        match self {
+            Ticker::Runtime(interval) => Ok(interval.tick().await),
+            Ticker::Precise { ticks, behavior } => {
+                let mut due = ticks.recv().await.ok_or(DsError::ChannelClosed)?;
+                // Ticks missed while the loop was stalled have queued up, which is a burst unless they're dropped
+                if *behavior != MissedTickBehavior::Burst {
+                    while let Ok(next) = ticks.try_recv() {
+                        due = next;
+                    }
+                }
+                Ok(due)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn precise_ticks_keep_the_period() {
+        let period = Duration::from_millis(5);
+        let mut ticker = Ticker::new(period, MissedTickBehavior::Burst, true);
+        let first = ticker.tick().await.unwrap();
+        let second = ticker.tick().await.unwrap();
+        assert_eq!(second - first, period);
+
+        // Stalled ticks are caught up on in a burst, or skipped to the latest
+        tokio::time::sleep(period * 3).await;
+        assert_eq!(ticker.tick().await.unwrap() - second, period);
+
+        let mut ticker = Ticker::new(period, MissedTickBehavior::Skip, true);
+        let first = ticker.tick().await.unwrap();
+        tokio::time::sleep(period * 5).await;
+        assert!(ticker.tick().await.unwrap() - first >= period * 2);
+    }
+
+    #[tokio::test]
+    async fn stopped_ticker_threads_are_reported() {
+        let (_, ticks) = unbounded_channel();
+        let mut ticker = Ticker::Precise {
+            ticks,
+            behavior: MissedTickBehavior::Burst,
+        };
+        assert!(matches!(ticker.tick().await, Err(DsError::ChannelClosed)));
+    }
+}
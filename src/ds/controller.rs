@@ -0,0 +1,240 @@
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::mode::Mode;
+use super::monitor::DsMonitor;
+use super::now;
+use super::state::DsState;
+use crate::Result;
+use crate::proto::udp::outbound::types::Request;
+
+/// A cloneable handle controlling a [`DriverStation`](struct.DriverStation.html), obtained with
+/// [`DriverStation::controller`](struct.DriverStation.html#method.controller)
+///
+/// Each part of an application, such as its UI, its input thread, and a logger, can hold its own handle rather than
+/// sharing the driver station behind a lock. The connection tasks are still owned by the `DriverStation`, so once it
+/// is dropped the robot is disabled and changes made through its handles no longer reach the robot.
+///
+/// Settings that change where the driver station connects, like the team number, remain on the `DriverStation`.
+/// The getters and event streams are those of [`DsMonitor`](struct.DsMonitor.html), which this dereferences to.
+///
+/// ```no_run
+/// # async fn run(ds: &ds::DriverStation) -> ds::Result<()> {
+/// let controller = ds.controller();
+/// let input = controller.clone();
+/// tokio::spawn(async move { input.update_joystick(0, &[0.5], &[true], &[]).await });
+///
+/// controller.enable().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct DsController {
+    state: Arc<DsState>,
+    monitor: DsMonitor,
+}
+
+impl DsController {
+    pub(crate) fn new(state: Arc<DsState>) -> DsController {
+        DsController {
+            monitor: DsMonitor::new(state.clone()),
+            state,
+        }
+    }
+
+    /// Returns a read-only handle to the same driver station, to pass on where control isn't needed
+    pub fn monitor(&self) -> DsMonitor {
+        self.monitor.clone()
+    }
+
+    /// Enables outputs on the robot
+    ///
+    /// Returns an error if an interlock is configured, and the driver station wasn't [armed](#method.arm) beforehand.
+    pub async fn enable(&self) -> Result<()> {
+        self.state.enable().await
+    }
+
+    /// Enables outputs on the robot for `duration`, after which it is disabled again
+    ///
+    /// See [`DriverStation::enable_for`](struct.DriverStation.html#method.enable_for).
+    pub async fn enable_for(&self, duration: Duration) -> Result<()> {
+        self.state.enable_for(duration).await
+    }
+
+    /// Disables outputs on the robot
+    ///
+    /// This also ends any practice match in progress.
+    pub async fn disable(&self) {
+        self.state.disable().await;
+    }
+
+    /// Disables outputs on the robot and disallows enabling it until the code is restarted.
+    pub async fn estop(&self) {
+        self.state.estop().await;
+    }
+
+    /// E-stops the robot from any context without blocking or waiting for a lock, taking effect with the next control packet
+    ///
+    /// This suits GUI callbacks and other synchronous code, where [`estop`](#method.estop) can't be awaited.
+    /// The robot is E-stopped within 20ms, even if the driver station's state is locked by another task in the meantime.
+    pub fn estop_now(&self) {
+        self.state.estop_now();
+    }
+
+    /// Clears the E-stop once robot code has restarted since it was raised
+    ///
+    /// See [`DriverStation::clear_estop_after_code_restart`](struct.DriverStation.html#method.clear_estop_after_code_restart).
+    pub async fn clear_estop_after_code_restart(&self) -> Result<()> {
+        self.state.send().write().await.clear_estop()
+    }
+
+    /// Arms the interlock, allowing the robot to be enabled once within the window it was configured with
+    pub async fn arm(&self) {
+        self.state.send().write().await.arm(now());
+    }
+
+    /// Tells the driver station that the application is still running, when a watchdog has been configured
+    pub async fn feed_watchdog(&self) {
+        self.state.send().write().await.feed_watchdog(now());
+    }
+
+    /// Changes the given `mode` the robot will be in
+    pub async fn set_mode(&self, mode: Mode) {
+        self.state.send().write().await.set_mode(mode);
+    }
+
+    /// Reports `remaining` as the time left in the current period to robot code, counting down with each control packet
+    ///
+    /// A Countdown tag is sent with every packet until [`clear_remaining_time`](#method.clear_remaining_time) is called,
+    /// and holds at 0 once the time has run out. A practice match in progress sends its own countdown instead.
+    pub async fn set_remaining_time(&self, remaining: Duration) {
+        self.state
+            .send()
+            .write()
+            .await
+            .set_countdown(Some(now() + remaining));
+    }
+
+    /// Stops reporting the time remaining set with [`set_remaining_time`](#method.set_remaining_time)
+    pub async fn clear_remaining_time(&self) {
+        self.state.send().write().await.set_countdown(None);
+    }
+
+    /// Queues the game specific message to be sent to the robot, which must be 3 characters long
+    pub async fn set_game_specific_message(&self, message: &str) -> Result<()> {
+        self.state.set_game_specific_message(message).await
+    }
+
+    /// Sets whether the robot is told it is connected to a field management system
+    ///
+    /// Robot code can behave differently on the field, such as WPILib skipping some timeouts, so this lets it be
+    /// tested off the field. This crate doesn't connect to an FMS itself, so the bit is only set by this method.
+    pub async fn set_fms_connected(&self, connected: bool) {
+        self.state.send().write().await.set_fms_connected(connected);
+    }
+
+    /// Sets the roboRIO's clock to the configured clock's time, along with its timezone
+    ///
+    /// See [`DriverStation::sync_time`](struct.DriverStation.html#method.sync_time).
+    pub async fn sync_time(&self) {
+        self.state.queue_time().await;
+    }
+
+    /// Sets the current state of the joystick in `port`
+    ///
+    /// See [`DriverStation::update_joystick`](struct.DriverStation.html#method.update_joystick).
+    pub async fn update_joystick(&self, port: usize, axes: &[f32], buttons: &[bool], povs: &[i16]) {
+        self.state.update_joystick(port, axes, buttons, povs).await;
+    }
+
+    /// Restarts the seqnums of control packets from 0
+    ///
+    /// See [`DriverStation::reset_seqnum`](struct.DriverStation.html#method.reset_seqnum).
+    pub async fn reset_seqnum(&self) {
+        self.state.reset_seqnum().await;
+    }
+
+    /// Instructs the roboRIO to restart robot code
+    pub async fn restart_code(&self) {
+        self.state
+            .send()
+            .write()
+            .await
+            .request(Request::RESTART_CODE);
+    }
+
+    /// Instructs the roboRIO to reboot
+    pub async fn restart_roborio(&self) {
+        self.state
+            .send()
+            .write()
+            .await
+            .request(Request::REBOOT_ROBORIO);
+    }
+}
+
+impl Deref for DsController {
+    type Target = DsMonitor;
+
+    fn deref(&self) -> &DsMonitor {
+        &self.monitor
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Alliance;
+    use crate::ds::builder::DsConfig;
+
+    #[tokio::test]
+    async fn clones_control_the_same_driver_station() {
+        let state = Arc::new(DsState::test_default());
+        let controller = DsController::new(state.clone());
+        let other = controller.clone();
+        let enabled = controller.watch_enabled();
+
+        controller.set_mode(Mode::Autonomous).await;
+        other.enable().await.unwrap();
+        assert_eq!(other.mode().await, Mode::Autonomous);
+        assert!(controller.enabled().await);
+        assert!(*enabled.borrow());
+
+        other.estop().await;
+        assert!(state.send().read().await.estopped());
+        assert!(!controller.enabled().await);
+        assert!(!*enabled.borrow());
+        assert!(*controller.watch_estopped().borrow());
+    }
+
+    #[tokio::test]
+    async fn estop_now_applies_with_the_next_packet() {
+        let state = Arc::new(DsState::test_default());
+        let controller = DsController::new(state.clone());
+        controller.enable().await.unwrap();
+
+        controller.estop_now();
+        assert!(!controller.estopped().await);
+        state.apply_estop_request(&mut *state.send().write().await);
+        assert!(controller.estopped().await);
+        assert!(!controller.enabled().await);
+    }
+
+    #[tokio::test]
+    async fn enabling_can_require_a_connection() {
+        let config = DsConfig {
+            disconnect_policy: crate::DisconnectPolicy::RequireReenable,
+            ..DsConfig::default()
+        };
+        let state = Arc::new(DsState::new(Alliance::new_red(1), 4533, config));
+        let controller = DsController::new(state.clone());
+
+        assert!(matches!(
+            controller.enable().await,
+            Err(crate::DsError::NotConnected)
+        ));
+        state.recv().write().await.set_connected(true);
+        controller.enable().await.unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio::time::MissedTickBehavior;
+
+use super::conn::TaskGuard;
+use super::state::{DsState, Mode};
+use super::telemetry::Telemetry;
+use crate::Result;
+
+/// The columns of each file, in order
+const HEADER: &str = "timestamp,connected,tcp_connected,enabled,estopped,mode,trace,battery_voltage,\
+    sent_rate,recv_rate,jitter_ms,received,lost,trip_time_ms,tick_rate,late_ticks\n";
+
+/// Describes where and how often telemetry is logged by [`DriverStation::log_csv`](struct.DriverStation.html#method.log_csv)
+///
+/// Each row is a [`Telemetry`](struct.Telemetry.html) snapshot. Timestamps are in milliseconds since the Unix epoch,
+/// and the trace is written as its raw bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvLog {
+    /// The directory log files are created in, which is created if it doesn't exist
+    pub dir: PathBuf,
+    /// How often a row is written
+    pub period: Duration,
+    /// The size a file may grow to before the next one is started
+    pub max_file_size: u64,
+    /// How many files are kept, after which the oldest is deleted as each new one is started
+    ///
+    /// Only files written by the same logger are deleted. `0` keeps every file.
+    pub max_files: usize,
+}
+
+impl CsvLog {
+    /// Logs to `dir` every 20ms, the rate control packets are sent at, in files of up to 16MiB with the last 10 kept
+    pub fn new(dir: impl Into<PathBuf>) -> CsvLog {
+        CsvLog {
+            dir: dir.into(),
+            period: Duration::from_millis(20),
+            max_file_size: 16 * 1024 * 1024,
+            max_files: 10,
+        }
+    }
+}
+
+/// Keeps a logger started by [`DriverStation::log_csv`](struct.DriverStation.html#method.log_csv) running,
+/// stopping it when dropped
+#[must_use = "logging is stopped as soon as its handle is dropped"]
+pub struct CsvLogHandle {
+    _task: TaskGuard<()>,
+}
+
+impl fmt::Debug for CsvLogHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CsvLogHandle").finish_non_exhaustive()
+    }
+}
+
+/// The file being written, and the ones written before it that are still kept
+struct Rotation {
+    log: CsvLog,
+    /// Identifies the files of this logger, from when it was started
+    prefix: String,
+    written: VecDeque<PathBuf>,
+    file: File,
+    size: u64,
+}
+
+impl Rotation {
+    async fn start(log: CsvLog) -> Result<Rotation> {
+        fs::create_dir_all(&log.dir).await?;
+        let prefix = chrono::Utc::now().format("ds-%Y%m%d-%H%M%S").to_string();
+        let path = file_path(&log.dir, &prefix, 0);
+        let file = create(&path).await?;
+        Ok(Rotation {
+            log,
+            prefix,
+            written: VecDeque::from([path]),
+            file,
+            size: HEADER.len() as u64,
+        })
+    }
+
+    async fn write(&mut self, row: &str) -> Result<()> {
+        // A row too large for a file of its own is still written, rather than rotating endlessly
+        if self.size > HEADER.len() as u64 && self.size + row.len() as u64 > self.log.max_file_size
+        {
+            self.rotate().await?;
+        }
+        self.file.write_all(row.as_bytes()).await?;
+        self.file.flush().await?;
+        self.size += row.len() as u64;
+        Ok(())
+    }
+
+    async fn rotate(&mut self) -> Result<()> {
+        let path = file_path(&self.log.dir, &self.prefix, self.written.len());
+        self.file = create(&path).await?;
+        self.size = HEADER.len() as u64;
+        self.written.push_back(path);
+        while self.log.max_files > 0 && self.written.len() > self.log.max_files {
+            if let Some(oldest) = self.written.pop_front() {
+                let _ = fs::remove_file(oldest).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn file_path(dir: &Path, prefix: &str, index: usize) -> PathBuf {
+    dir.join(format!("{}-{}.csv", prefix, index))
+}
+
+/// Creates a log file, starting it with the header
+async fn create(path: &Path) -> Result<File> {
+    let mut file = File::create(path).await?;
+    file.write_all(HEADER.as_bytes()).await?;
+    Ok(file)
+}
+
+/// Formats `telemetry` as a row of the log
+fn row(telemetry: &Telemetry) -> String {
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let mode = match telemetry.mode {
+        Mode::Autonomous => "autonomous",
+        Mode::Teleoperated => "teleoperated",
+        Mode::Test => "test",
+    };
+    let mut row = String::with_capacity(128);
+    let _ = writeln!(
+        row,
+        "{},{},{},{},{},{},{},{:.2},{:.1},{:.1},{:.2},{},{},{},{:.1},{}",
+        telemetry.timestamp,
+        telemetry.connected,
+        telemetry.tcp_connected,
+        telemetry.enabled,
+        telemetry.estopped,
+        mode,
+        telemetry.trace.bits(),
+        telemetry.battery_voltage,
+        telemetry.net.sent_rate,
+        telemetry.net.recv_rate,
+        millis(telemetry.net.jitter),
+        telemetry.net.received,
+        telemetry.net.lost,
+        telemetry
+            .net
+            .trip_time
+            .map(|trip| format!("{:.2}", millis(trip)))
+            .unwrap_or_default(),
+        telemetry.ticks.rate,
+        telemetry.ticks.late,
+    );
+    row
+}
+
+pub(crate) async fn start(state: Arc<DsState>, log: CsvLog) -> Result<CsvLogHandle> {
+    let period = log.period;
+    let mut rotation = Rotation::start(log).await?;
+    let task = TaskGuard::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            let row = row(&Telemetry::capture(&state).await);
+            if let Err(e) = rotation.write(&row).await {
+                warn!("CSV logging stopped: {}", e);
+                break;
+            }
+        }
+    });
+    Ok(CsvLogHandle { _task: task })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn files_are_rotated_and_pruned() {
+        let dir = std::env::temp_dir().join(format!("ds-csv-test-{}", std::process::id()));
+        let state = DsState::test_default();
+        let row = row(&Telemetry::capture(&state).await);
+
+        let log = CsvLog {
+            // Room for two rows per file
+            max_file_size: (HEADER.len() + row.len() * 2) as u64,
+            max_files: 2,
+            ..CsvLog::new(&dir)
+        };
+        let mut rotation = Rotation::start(log).await.unwrap();
+        for _ in 0..5 {
+            rotation.write(&row).await.unwrap();
+        }
+
+        let files: Vec<_> = rotation.written.iter().cloned().collect();
+        assert_eq!(files.len(), 2);
+        assert!(files[1].to_string_lossy().ends_with("-2.csv"));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        let last = std::fs::read_to_string(&files[1]).unwrap();
+        assert_eq!(last, format!("{}{}", HEADER, row));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::JoystickValue;
+use crate::ds::battery::BatteryLevel;
+use crate::ds::mode::DsMode;
+use crate::ds::practice::MatchPhase;
+use crate::ds::robot::RobotStateChange;
+
+/// Events emitted by the driver station as the state of the robot and the connection changes
+///
+/// Events can be received by subscribing with [`DriverStation::events`](struct.DriverStation.html#method.events).
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum DsEvent {
+    /// The roboRIO has started responding to control packets
+    Connected,
+    /// The roboRIO has stopped responding to control packets
+    Disconnected {
+        /// How long the roboRIO was silent for before being considered disconnected
+        timeout: Duration,
+    },
+    /// A status packet arrived from an address other than the roboRIO's, and was ignored
+    ///
+    /// Each address is reported once until the roboRIO is found again. This usually means a simulator or another robot
+    /// is on the same network, see [`DriverStationBuilder::accept_any_source`](struct.DriverStationBuilder.html#method.accept_any_source).
+    ForeignPacket { from: IpAddr },
+    /// The TCP connection to the roboRIO, which carries console output and game data, has been opened
+    TcpConnected,
+    /// The TCP connection to the roboRIO has closed
+    ///
+    /// This can happen independently of the control packets, which are sent over UDP.
+    TcpDisconnected,
+    /// The driver station has entered or left simulation mode, as a simulator started or stopped
+    ///
+    /// The current mode can also be watched with [`DriverStation::watch_ds_mode`](struct.DriverStation.html#method.watch_ds_mode).
+    DsModeChanged(DsMode),
+    /// The robot has reported a different [`RobotState`](enum.RobotState.html), or has stopped responding
+    ///
+    /// These can also be received on their own with [`DriverStation::robot_state_changes`](struct.DriverStation.html#method.robot_state_changes).
+    RobotStateChanged(RobotStateChange),
+    /// The battery voltage has crossed one of the thresholds configured with
+    /// [`DriverStationBuilder::battery_alerts`](struct.DriverStationBuilder.html#method.battery_alerts)
+    BatteryLevelChanged(BatteryLevel),
+    /// The robot has been E-stopped, for the given reason
+    EStopped(EstopSource),
+    /// The E-stop has been cleared after robot code restarted, see
+    /// [`DriverStation::clear_estop_after_code_restart`](struct.DriverStation.html#method.clear_estop_after_code_restart)
+    EStopCleared,
+    /// The roboRIO has asked for the date, having no clock of its own after it boots
+    ///
+    /// Emitted once per request rather than with every packet that repeats it. The date is sent automatically unless
+    /// [`DriverStationBuilder::without_time_sync`](struct.DriverStationBuilder.html#method.without_time_sync) was used.
+    DateRequested,
+    /// A practice match has moved into a new period
+    ///
+    /// `MatchPhase::Ended` is also emitted when the match is ended early, such as by disabling or E-stopping the robot,
+    /// or by the watchdog expiring.
+    MatchPhaseChanged(MatchPhase),
+    /// The joystick supplier panicked with the given message, so it was removed and the robot was disabled
+    JoystickSupplierPanicked(String),
+    /// The joystick supplier took longer than the 20ms between control packets
+    ///
+    /// Its last values are reused for the `skipped` packets that were delayed, rather than calling it again.
+    /// Delayed packets are only caught up on with [`MissedTickBehavior::Burst`](tokio::time::MissedTickBehavior), otherwise `skipped` is 0.
+    JoystickSupplierSlow {
+        /// How long the supplier took to return
+        elapsed: Duration,
+        /// How many packets will be sent with the previous values
+        skipped: u32,
+    },
+    /// The robot was disabled because the application stopped feeding the watchdog
+    WatchdogExpired,
+    /// The endgame of a practice match has started, as configured by [`MatchTiming::endgame`](struct.MatchTiming.html#structfield.endgame)
+    Endgame,
+    /// A value given for the joystick in `port` couldn't be sent as is
+    ///
+    /// Each problem is reported once when it first appears, rather than with every packet it persists for.
+    InvalidJoystickValue {
+        port: usize,
+        warning: JoystickWarning,
+    },
+}
+
+/// A problem with a joystick value that was corrected before it was sent to the roboRIO
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum JoystickWarning {
+    /// The same axis, button or POV was given more than once, and only the last value was sent
+    Duplicate(JoystickValue),
+    /// The value was outside of its range, and was clamped into it
+    ///
+    /// Axes range from `-1.0..=1.0`, and POV angles from `0..360`, or -1 if not pressed.
+    OutOfRange(JoystickValue),
+    /// The id is past the 12 axes, 32 buttons or 12 POVs WPILib reads, or is 0 for a button, so the value was dropped
+    Unsupported(JoystickValue),
+}
+
+/// What caused the robot to be E-stopped
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EstopSource {
+    /// The application E-stopped the robot, through the API, a hotkey, a remote command, or a termination signal
+    User,
+    /// The roboRIO reported being E-stopped, such as by a stop button wired to the robot
+    Robot,
+    /// The field management system E-stopped the robot
+    Fms,
+}
@@ -0,0 +1,101 @@
+use crate::proto::udp::inbound::types::{Status, Trace};
+use crate::proto::udp::outbound::types::Control;
+
+/// The operating mode of the driver station
+///
+/// Normal operating mode connects to the IP specified by a team number
+/// Simulation mode connects to a simulator, `127.0.0.1:1110` by default, and is activated by the simulator sending to
+/// `127.0.0.1:1135`. Both addresses can be changed with
+/// [`DriverStationBuilder::simulator_target`](struct.DriverStationBuilder.html#method.simulator_target) and
+/// [`simulator_address`](struct.DriverStationBuilder.html#method.simulator_address).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DsMode {
+    Normal,
+    Simulation,
+}
+
+/// Represents the current Mode that the robot is in. the `Mode` of the robot is considered separately from whether it is enabled or not
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mode {
+    Autonomous,
+    Teleoperated,
+    Test,
+}
+
+impl Mode {
+    /// Decodes the mode of the robot from the given status byte
+    #[inline]
+    pub const fn from_status(status: Status) -> Option<Mode> {
+        // The mode is a 2 bit field rather than a set of flags, with teleop being 0
+        match status.bits() & 0b11 {
+            0b00 => Some(Mode::Teleoperated),
+            0b01 => Some(Mode::Test),
+            0b10 => Some(Mode::Autonomous),
+            _ => None,
+        }
+    }
+
+    /// Decodes the mode robot code reports running in from the given trace
+    ///
+    /// Robot code only reports a mode while it is enabled, so this returns `None` while the robot is disabled.
+    #[inline]
+    pub const fn from_trace(trace: Trace) -> Option<Mode> {
+        if trace.is_teleop() {
+            Some(Mode::Teleoperated)
+        } else if trace.is_autonomous() {
+            Some(Mode::Autonomous)
+        } else if trace.is_test() {
+            Some(Mode::Test)
+        } else {
+            None
+        }
+    }
+
+    /// Converts this `Mode` into a `Control` byte that can be modified for encoding the control packet.
+    #[inline(always)]
+    pub(crate) const fn to_control(self) -> Control {
+        match self {
+            Mode::Teleoperated => Control::TELEOP,
+            Mode::Autonomous => Control::AUTO,
+            Mode::Test => Control::TEST,
+        }
+    }
+}
+
+impl From<Trace> for Option<Mode> {
+    fn from(trace: Trace) -> Option<Mode> {
+        Mode::from_trace(trace)
+    }
+}
+
+impl From<Mode> for Control {
+    fn from(mode: Mode) -> Control {
+        mode.to_control()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mode_is_read_from_the_trace() {
+        let mode: Option<Mode> = (Trace::ROBOT_CODE | Trace::IS_ROBORIO | Trace::TEST_MODE).into();
+        assert_eq!(mode, Some(Mode::Test));
+
+        let mode: Option<Mode> = (Trace::ROBOT_CODE | Trace::DISABLED).into();
+        assert_eq!(mode, None);
+
+        assert_eq!(
+            Mode::from_status(Status::AUTO | Status::ENABLED),
+            Some(Mode::Autonomous)
+        );
+        assert_eq!(
+            Mode::from_status(Status::BROWNOUT),
+            Some(Mode::Teleoperated)
+        );
+
+        assert_eq!(Control::from(Mode::Autonomous).bits(), Control::AUTO.bits());
+    }
+}
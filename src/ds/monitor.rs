@@ -0,0 +1,297 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use tokio::sync::{broadcast, watch};
+use tokio_stream::Stream;
+
+use super::event::DsEvent;
+use super::mode::{DsMode, Mode};
+use super::now;
+use super::practice::MatchPhase;
+use super::received::PacketTime;
+use super::robot::{RobotState, RobotStateChange};
+use super::state::DsState;
+use super::stats::{NetStats, TickStats};
+use super::telemetry::Telemetry;
+use crate::TcpPacket;
+use crate::proto::udp::inbound::types::{JoystickOutput, Trace};
+use crate::proto::udp::outbound::types::UdpTag;
+
+/// A cloneable, read-only handle to a [`DriverStation`](struct.DriverStation.html), obtained with
+/// [`DriverStation::monitor`](struct.DriverStation.html#method.monitor)
+///
+/// It exposes the getters and event streams of the driver station, but nothing that affects the robot, so it can be
+/// handed to plugins or remote viewers that shouldn't be able to enable it.
+///
+/// The same getters are available on the `DriverStation` and [`DsController`](struct.DsController.html), which both
+/// dereference to a monitor.
+///
+/// ```no_run
+/// # async fn run(ds: &ds::DriverStation) {
+/// let monitor = ds.monitor();
+/// tokio::spawn(async move {
+///     let mut events = monitor.events();
+///     while let Ok(event) = events.recv().await {
+///         println!("{:?}", event);
+///     }
+/// });
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct DsMonitor {
+    state: Arc<DsState>,
+}
+
+impl DsMonitor {
+    pub(crate) fn new(state: Arc<DsState>) -> DsMonitor {
+        DsMonitor { state }
+    }
+
+    /// Returns the current mode of the robot
+    pub async fn mode(&self) -> Mode {
+        self.state.send().read().await.mode()
+    }
+
+    /// Returns the mode robot code reports running in, which lags behind [`mode`](#method.mode) after it is changed
+    ///
+    /// Robot code only reports a mode while it is enabled, so this is `None` while the robot is disabled or disconnected.
+    pub async fn reported_mode(&self) -> Option<Mode> {
+        Mode::from_trace(self.state.recv().read().await.trace())
+    }
+
+    /// Returns whether the driver station is driving a simulator, rather than the roboRIO
+    pub async fn ds_mode(&self) -> DsMode {
+        self.state.send().read().await.ds_mode()
+    }
+
+    /// Returns whether the robot is currently enabled
+    pub async fn enabled(&self) -> bool {
+        self.state.send().read().await.enabled()
+    }
+
+    /// Returns whether the robot is currently E-stopped
+    pub async fn estopped(&self) -> bool {
+        self.state.send().read().await.estopped()
+    }
+
+    /// Returns whether the robot is being told it is connected to a field management system
+    pub async fn fms_connected(&self) -> bool {
+        self.state.send().read().await.fms_connected()
+    }
+
+    /// Returns the current period of the practice match in progress, if any
+    ///
+    /// Changes between periods are also emitted as [`DsEvent::MatchPhaseChanged`](enum.DsEvent.html#variant.MatchPhaseChanged).
+    pub async fn match_phase(&self) -> Option<MatchPhase> {
+        self.state.send().read().await.match_phase()
+    }
+
+    /// Watches whether the robot is being enabled, to render its state without polling
+    ///
+    /// Changes made through this driver station are seen straight away, others within a control packet.
+    pub fn watch_enabled(&self) -> watch::Receiver<bool> {
+        self.state.enabled().subscribe()
+    }
+
+    /// Watches whether the robot has been E-stopped, to render its state without polling
+    pub fn watch_estopped(&self) -> watch::Receiver<bool> {
+        self.state.estopped().subscribe()
+    }
+
+    /// Watches whether the driver station is in simulation mode, to show that it is driving a simulator without polling
+    pub fn watch_ds_mode(&self) -> watch::Receiver<DsMode> {
+        self.state.ds_mode().subscribe()
+    }
+
+    /// Returns the state the robot last reported being in
+    pub async fn robot_state(&self) -> RobotState {
+        self.state.recv().read().await.robot_state()
+    }
+
+    /// Returns the last received Trace from the robot
+    pub async fn trace(&self) -> Trace {
+        self.state.recv().read().await.trace()
+    }
+
+    /// Watches the Trace received from the robot, to react to its code crashing or changing mode without polling
+    ///
+    /// The watched value only changes when the Trace does, and is empty while the roboRIO is disconnected.
+    pub fn watch_trace(&self) -> watch::Receiver<Trace> {
+        self.state.trace().subscribe()
+    }
+
+    /// Returns whether the roboRIO is currently responding to control packets
+    pub async fn connected(&self) -> bool {
+        self.state.recv().read().await.connected()
+    }
+
+    /// Returns whether the TCP connection to the roboRIO, which carries console output and game data, is open
+    ///
+    /// The TCP connection can fail while control packets are still being answered, see [`connected`](#method.connected).
+    pub fn tcp_connected(&self) -> bool {
+        self.state.tcp_connected()
+    }
+
+    /// Returns the address of the roboRIO currently being communicated with, if one has answered
+    pub async fn rio_address(&self) -> Option<IpAddr> {
+        self.state.recv().read().await.rio_address()
+    }
+
+    /// Returns how closely control packets are keeping to their 20ms schedule
+    ///
+    /// The loop can fall behind when the host application blocks the runtime, see
+    /// [`DriverStationBuilder::missed_ticks`](struct.DriverStationBuilder.html#method.missed_ticks).
+    pub fn tick_stats(&self) -> TickStats {
+        self.state.ticks().stats(now())
+    }
+
+    /// Returns the rates of the packets sent to and received from the roboRIO, along with their jitter and loss
+    ///
+    /// These are the figures the official driver station graphs, and help tell a poor wireless link from a busy roboRIO.
+    pub fn net_stats(&self) -> NetStats {
+        let ticks = self.state.ticks();
+        self.state.arrivals().stats(&ticks, now())
+    }
+
+    /// Returns the seqnum that the next control packet will be sent with
+    pub async fn seqnum(&self) -> u16 {
+        self.state.send().read().await.seqnum()
+    }
+
+    /// Returns the seqnum echoed by the latest status packet from the roboRIO, if any have been received
+    ///
+    /// The roboRIO echoes the seqnum of the control packet it is responding to, so this trails
+    /// [`seqnum`](#method.seqnum) by the packets still in flight.
+    pub fn echoed_seqnum(&self) -> Option<u16> {
+        self.state.arrivals().echoed()
+    }
+
+    /// Returns the last received battery voltage from the robot
+    pub async fn battery_voltage(&self) -> f32 {
+        self.state.recv().read().await.battery_voltage()
+    }
+
+    /// Returns the high and low bytes of the last battery voltage received from the robot, as the roboRIO sent them
+    ///
+    /// These are before any [offset](struct.DriverStationBuilder.html#method.battery_offset) or
+    /// [smoothing](struct.DriverStationBuilder.html#method.battery_smoothing) is applied.
+    pub async fn battery_raw(&self) -> [u8; 2] {
+        self.state.recv().read().await.battery_raw()
+    }
+
+    /// Streams the battery voltages received from the robot, each with when its packet arrived
+    ///
+    /// Every sample is yielded, unless the stream falls more than about 5 seconds behind, in which case the oldest are skipped.
+    pub fn battery_stream(&self) -> impl Stream<Item = (Instant, f32)> + use<> {
+        self.state.battery_stream()
+    }
+
+    /// Returns when the last status packet was received from the roboRIO, if one has been
+    ///
+    /// This is kept once the roboRIO disconnects, showing when it was last heard from.
+    pub async fn last_udp_received(&self) -> Option<PacketTime> {
+        self.state.recv().read().await.received()
+    }
+
+    /// Returns when the last TCP packet was received from the roboRIO, if one has been
+    pub async fn last_tcp_received(&self) -> Option<PacketTime> {
+        self.state.tcp().read().await.received()
+    }
+
+    /// Returns a Vec of the current contents of the UDP queue
+    pub async fn udp_queue(&self) -> Vec<UdpTag> {
+        self.state.send().read().await.pending_udp()
+    }
+
+    /// Returns how many tags are waiting to be sent over UDP
+    pub async fn udp_queue_len(&self) -> usize {
+        self.state.send().read().await.udp_queue_len()
+    }
+
+    /// Returns how many tags are waiting to be sent over TCP, which is 0 while the TCP connection is closed
+    pub async fn tcp_queue_len(&self) -> usize {
+        self.state.tcp().read().await.tcp_queue_len()
+    }
+
+    /// Returns a snapshot of the state of the driver station and the robot, for logging or display
+    pub async fn telemetry(&self) -> Telemetry {
+        Telemetry::capture(&self.state).await
+    }
+
+    /// Streams the changes in the state the robot reports, such as being enabled or losing its code
+    ///
+    /// Only changes made after calling this are received. If the stream falls too far behind, the oldest changes are skipped.
+    pub fn robot_state_changes(&self) -> impl Stream<Item = RobotStateChange> + use<> {
+        self.state.robot_state_changes()
+    }
+
+    /// Subscribes to events emitted by the driver station
+    ///
+    /// Only events emitted after subscribing are received. If the receiver falls too far behind, the oldest events are skipped.
+    pub fn events(&self) -> broadcast::Receiver<DsEvent> {
+        self.state.events().subscribe()
+    }
+
+    /// Watches the outputs and rumble that robot code sets for each joystick, in port order
+    ///
+    /// The input backends forward rumble to their devices on their own once attached. The outputs are cleared when the roboRIO disconnects.
+    pub fn joystick_outputs(&self) -> watch::Receiver<Vec<JoystickOutput>> {
+        self.state.joystick_outputs().subscribe()
+    }
+
+    /// Subscribes to the TCP packets received from the roboRIO, as an alternative to the consumer closures
+    ///
+    /// Only packets received after subscribing are received. If the receiver falls too far behind, the oldest packets are skipped.
+    pub fn tcp_packets(&self) -> broadcast::Receiver<TcpPacket> {
+        self.state.tcp_packets().subscribe()
+    }
+
+    /// Streams the TCP packets received from the roboRIO, each with when it was received
+    ///
+    /// Only packets received after calling this are yielded. If the stream falls too far behind, the oldest packets are skipped.
+    pub fn timed_tcp_packets(&self) -> impl Stream<Item = (PacketTime, TcpPacket)> + use<> {
+        self.state.timed_tcp_packet_stream()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn observes_changes_made_elsewhere() {
+        let state = Arc::new(DsState::test_default());
+        let monitor = DsMonitor::new(state.clone());
+        let mut events = monitor.events();
+        let mut trace = monitor.watch_trace();
+
+        state.enable().await.unwrap();
+        assert!(monitor.enabled().await);
+
+        state.set_tcp_connected(true);
+        assert!(monitor.tcp_connected());
+        assert!(matches!(events.recv().await, Ok(DsEvent::TcpConnected)));
+
+        state.set_trace(Trace::ROBOT_CODE);
+        state.set_trace(Trace::ROBOT_CODE);
+        assert!(trace.has_changed().unwrap());
+        assert_eq!(*trace.borrow_and_update(), Trace::ROBOT_CODE);
+        assert!(!trace.has_changed().unwrap());
+    }
+
+    #[tokio::test]
+    async fn streams_robot_state_changes() {
+        use tokio_stream::StreamExt;
+
+        let state = Arc::new(DsState::test_default());
+        let monitor = DsMonitor::new(state.clone());
+        let mut changes = Box::pin(monitor.robot_state_changes());
+
+        state.set_tcp_connected(true);
+        let change = RobotStateChange::new(RobotState::Disconnected, RobotState::DisabledTeleop, 0);
+        state.emit(DsEvent::RobotStateChanged(change));
+
+        assert_eq!(changes.next().await, Some(change));
+    }
+}
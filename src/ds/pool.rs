@@ -0,0 +1,156 @@
+use futures::future::join_all;
+
+use super::{DriverStation, DriverStationBuilder};
+use crate::Result;
+
+/// Runs several driver stations at once, such as for a demo field or a bench testing more than one robot
+///
+/// Stations are looked up by their current team number, so each robot in the pool should have its own.
+/// The stations don't listen for a simulator, see
+/// [`DriverStationBuilder::without_simulator`](struct.DriverStationBuilder.html#method.without_simulator).
+///
+/// Each station needs a receive socket of its own. Robots answer to the address that contacted them, so stations
+/// driving real robots can be given different local addresses with
+/// [`bind_address`](struct.DriverStationBuilder.html#method.bind_address), while simulators can be pointed at
+/// different ports with [`receive_port`](struct.DriverStationBuilder.html#method.receive_port).
+///
+/// ```no_run
+/// use ds::{DriverStation, DsPool, Alliance};
+///
+/// # async fn run() -> ds::Result<()> {
+/// let mut pool = DsPool::new();
+/// pool.insert(DriverStation::builder(4533, Alliance::new_red(1)).bind_address([10, 0, 0, 5].into())).await?;
+/// pool.insert(DriverStation::builder(254, Alliance::new_blue(1)).bind_address([10, 0, 0, 6].into())).await?;
+///
+/// // Stop every robot on the field at once
+/// pool.disable_all().await;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct DsPool {
+    stations: Vec<DriverStation>,
+}
+
+impl DsPool {
+    /// Creates an empty pool
+    pub fn new() -> DsPool {
+        DsPool::default()
+    }
+
+    /// Builds a driver station and adds it to the pool
+    ///
+    /// If the pool already had a station for the same team, it is shut down first, so that two stations never drive
+    /// the same robot and the new one can bind the same ports.
+    pub async fn insert(&mut self, builder: DriverStationBuilder) -> Result<()> {
+        if let Some(replaced) = self.remove(builder.team_number) {
+            replaced.shutdown().await;
+        }
+        let station = builder.without_simulator().build().await?;
+        self.stations.push(station);
+        Ok(())
+    }
+
+    /// Removes the station driving `team` from the pool, which stops it once dropped
+    pub fn remove(&mut self, team: u16) -> Option<DriverStation> {
+        let index = self
+            .stations
+            .iter()
+            .position(|station| station.team_number() == team)?;
+        Some(self.stations.remove(index))
+    }
+
+    pub fn get(&self, team: u16) -> Option<&DriverStation> {
+        self.stations
+            .iter()
+            .find(|station| station.team_number() == team)
+    }
+
+    pub fn get_mut(&mut self, team: u16) -> Option<&mut DriverStation> {
+        self.stations
+            .iter_mut()
+            .find(|station| station.team_number() == team)
+    }
+
+    /// Returns the team numbers of the stations in the pool, in the order they were added
+    pub fn teams(&self) -> impl Iterator<Item = u16> + '_ {
+        self.stations.iter().map(DriverStation::team_number)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DriverStation> {
+        self.stations.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut DriverStation> {
+        self.stations.iter_mut()
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.stations.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty()
+    }
+
+    /// Disables every robot in the pool
+    pub async fn disable_all(&mut self) {
+        join_all(self.stations.iter_mut().map(DriverStation::disable)).await;
+    }
+
+    /// E-stops every robot in the pool
+    pub async fn estop_all(&mut self) {
+        join_all(self.stations.iter_mut().map(DriverStation::estop)).await;
+    }
+
+    /// Disables every robot and stops every station, resolving once they have all shut down
+    pub async fn shutdown(self) {
+        join_all(self.stations.into_iter().map(DriverStation::shutdown)).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Alliance, DriverStation};
+    use std::net::Ipv4Addr;
+
+    fn station(team: u16) -> DriverStationBuilder {
+        DriverStation::builder(team, Alliance::new_red(1))
+            .bind_address(Ipv4Addr::LOCALHOST.into())
+            .receive_port(0)
+    }
+
+    #[tokio::test]
+    async fn stations_are_keyed_by_team() {
+        let mut pool = DsPool::new();
+        pool.insert(station(4533)).await.unwrap();
+        pool.insert(station(254)).await.unwrap();
+        assert_eq!(pool.teams().collect::<Vec<_>>(), [4533, 254]);
+
+        pool.insert(station(4533)).await.unwrap();
+        assert_eq!(pool.teams().collect::<Vec<_>>(), [254, 4533]);
+
+        assert!(pool.remove(254).is_some());
+        assert!(pool.get(254).is_none());
+        assert_eq!(pool.len(), 1);
+        pool.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn replaced_stations_release_their_ports() {
+        let port = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut pool = DsPool::new();
+
+        pool.insert(station(4533).receive_port(port)).await.unwrap();
+        pool.insert(station(4533).receive_port(port)).await.unwrap();
+        assert_eq!(pool.len(), 1);
+        pool.shutdown().await;
+    }
+}
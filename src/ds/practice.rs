@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+/// The length of each period of a practice match
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MatchTiming {
+    /// How long the robot is enabled in autonomous
+    pub autonomous: Duration,
+    /// How long the robot is disabled between autonomous and teleop
+    pub delay: Duration,
+    /// How long the robot is enabled in teleop
+    pub teleop: Duration,
+    /// How long before the end of teleop the endgame starts
+    pub endgame: Duration,
+}
+
+impl Default for MatchTiming {
+    /// The timing of a standard FRC match: 15 seconds of autonomous, then 2:15 of teleop with a 20 second endgame
+    fn default() -> Self {
+        MatchTiming {
+            autonomous: Duration::from_secs(15),
+            delay: Duration::from_secs(1),
+            teleop: Duration::from_secs(135),
+            endgame: Duration::from_secs(20),
+        }
+    }
+}
+
+/// The periods of a practice match
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MatchPhase {
+    Autonomous,
+    /// The pause between autonomous and teleop, during which the robot is disabled
+    Delay,
+    Teleop,
+    Ended,
+}
+
+/// A practice match in progress, sequenced by the driver station itself
+pub(crate) struct PracticeMatch {
+    timing: MatchTiming,
+    start: Instant,
+    /// The phase the robot was last put into
+    phase: Option<MatchPhase>,
+    /// Whether the start of the endgame has been reported
+    endgame: bool,
+}
+
+impl PracticeMatch {
+    pub fn new(timing: MatchTiming, start: Instant) -> PracticeMatch {
+        PracticeMatch {
+            timing,
+            start,
+            phase: None,
+            endgame: false,
+        }
+    }
+
+    /// Returns the phase of the match at `now`, and how much time is left in it
+    pub fn phase_at(&self, now: Instant) -> (MatchPhase, Duration) {
+        let mut elapsed = now.saturating_duration_since(self.start);
+        let periods = [
+            (MatchPhase::Autonomous, self.timing.autonomous),
+            (MatchPhase::Delay, self.timing.delay),
+            (MatchPhase::Teleop, self.timing.teleop),
+        ];
+
+        for (phase, length) in periods {
+            if elapsed < length {
+                return (phase, length - elapsed);
+            }
+            elapsed -= length;
+        }
+
+        (MatchPhase::Ended, Duration::ZERO)
+    }
+
+    /// Moves the match along to `now`, returning the new phase if it has changed
+    pub fn advance(&mut self, now: Instant) -> Option<MatchPhase> {
+        let (phase, _) = self.phase_at(now);
+        if self.phase == Some(phase) {
+            return None;
+        }
+
+        self.phase = Some(phase);
+        Some(phase)
+    }
+
+    /// Returns true the first time it is called once the endgame has started
+    pub fn enter_endgame(&mut self, now: Instant) -> bool {
+        if self.endgame {
+            return false;
+        }
+
+        let (phase, remaining) = self.phase_at(now);
+        self.endgame = phase == MatchPhase::Teleop && remaining <= self.timing.endgame;
+        self.endgame
+    }
+
+    #[inline(always)]
+    pub const fn phase(&self) -> Option<MatchPhase> {
+        self.phase
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn phases_follow_timing() {
+        let start = Instant::now();
+        let practice = PracticeMatch::new(MatchTiming::default(), start);
+        let at = |secs: f32| practice.phase_at(start + Duration::from_secs_f32(secs));
+
+        assert_eq!(at(0.0), (MatchPhase::Autonomous, Duration::from_secs(15)));
+        assert_eq!(at(15.5).0, MatchPhase::Delay);
+        assert_eq!(at(16.0), (MatchPhase::Teleop, Duration::from_secs(135)));
+        assert_eq!(at(151.0), (MatchPhase::Ended, Duration::ZERO));
+    }
+
+    #[test]
+    fn advance_reports_each_phase_once() {
+        let start = Instant::now();
+        let mut practice = PracticeMatch::new(MatchTiming::default(), start);
+
+        assert_eq!(practice.advance(start), Some(MatchPhase::Autonomous));
+        assert_eq!(practice.advance(start + Duration::from_secs(1)), None);
+        assert_eq!(
+            practice.advance(start + Duration::from_secs(20)),
+            Some(MatchPhase::Teleop)
+        );
+        assert!(!practice.enter_endgame(start + Duration::from_secs(20)));
+        assert!(practice.enter_endgame(start + Duration::from_secs(131)));
+        assert!(!practice.enter_endgame(start + Duration::from_secs(132)));
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+use std::mem;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::Notify;
+
+/// What a tag queue does with a new tag once it is full
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Discards the tag that has been waiting the longest to make room
+    #[default]
+    DropOldest,
+    /// Discards the new tag, keeping those already queued
+    DropNewest,
+    /// Replaces the oldest queued tag of the same type, such as earlier game data, falling back to dropping the oldest tag
+    Coalesce,
+}
+
+/// A queue of tags waiting to be sent, holding at most `capacity` tags
+#[derive(Debug, Clone)]
+pub(crate) struct TagQueue<T> {
+    tags: VecDeque<T>,
+    capacity: usize,
+    policy: OverflowPolicy,
+}
+
+impl<T> TagQueue<T> {
+    pub const fn new(capacity: usize, policy: OverflowPolicy) -> TagQueue<T> {
+        TagQueue {
+            tags: VecDeque::new(),
+            capacity,
+            policy,
+        }
+    }
+
+    /// Changes the limit and overflow policy, dropping the oldest tags if the queue is now over the limit
+    pub fn configure(&mut self, capacity: usize, policy: OverflowPolicy) {
+        self.capacity = capacity;
+        self.policy = policy;
+        while self.tags.len() > capacity {
+            self.tags.pop_front();
+        }
+    }
+
+    /// Adds `tag` to the back of the queue, returning false if a tag had to be dropped to respect the limit
+    pub fn push(&mut self, tag: T) -> bool {
+        if self.tags.len() < self.capacity {
+            self.tags.push_back(tag);
+            return true;
+        }
+
+        let same_type = self
+            .tags
+            .iter()
+            .position(|queued| mem::discriminant(queued) == mem::discriminant(&tag));
+        match (self.policy, same_type) {
+            (OverflowPolicy::DropNewest, _) => {}
+            (OverflowPolicy::Coalesce, Some(index)) => {
+                self.tags.remove(index);
+                self.tags.push_back(tag);
+            }
+            (OverflowPolicy::DropOldest | OverflowPolicy::Coalesce, _) => {
+                if self.tags.pop_front().is_some() {
+                    self.tags.push_back(tag);
+                }
+            }
+        }
+        false
+    }
+
+    /// Removes and returns every queued tag, oldest first
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.tags.drain(..)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.tags.iter()
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+}
+
+/// A tag queue shared between the API and a connection task, which is woken as tags are queued
+pub(crate) struct SharedQueue<T> {
+    queue: Mutex<TagQueue<T>>,
+    notify: Notify,
+    /// Set once the task sending the tags has stopped, after which nothing will drain the queue
+    closed: AtomicBool,
+}
+
+impl<T> SharedQueue<T> {
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> SharedQueue<T> {
+        SharedQueue {
+            queue: Mutex::new(TagQueue::new(capacity, policy)),
+            notify: Notify::new(),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    /// Adds `tag` to the queue and wakes the task sending them, returning false if a tag had to be dropped
+    pub fn push(&self, tag: T) -> bool {
+        let pushed = self
+            .queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(tag);
+        self.notify.notify_one();
+        pushed
+    }
+
+    /// Waits for tags to be queued, then removes and returns all of them
+    pub async fn next(&self) -> Vec<T> {
+        self.notify.notified().await;
+        self.queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .drain()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Marks the queue as no longer drained, discarding the tags left in it
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .drain()
+            .for_each(drop);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Tag {
+        A(u8),
+        B(u8),
+    }
+
+    #[test]
+    fn overflow_policies() {
+        let fill = |policy| {
+            let mut queue = TagQueue::new(2, policy);
+            assert!(queue.push(Tag::A(1)));
+            assert!(queue.push(Tag::B(2)));
+            assert!(!queue.push(Tag::A(3)));
+            queue.drain().collect::<Vec<_>>()
+        };
+
+        assert_eq!(fill(OverflowPolicy::DropOldest), vec![Tag::B(2), Tag::A(3)]);
+        assert_eq!(fill(OverflowPolicy::DropNewest), vec![Tag::A(1), Tag::B(2)]);
+        assert_eq!(fill(OverflowPolicy::Coalesce), vec![Tag::B(2), Tag::A(3)]);
+
+        // Coalescing keeps other tags in order, rather than always dropping the oldest
+        let mut queue = TagQueue::new(3, OverflowPolicy::Coalesce);
+        queue.push(Tag::A(1));
+        queue.push(Tag::B(2));
+        queue.push(Tag::A(3));
+        queue.push(Tag::B(4));
+        assert_eq!(
+            queue.drain().collect::<Vec<_>>(),
+            vec![Tag::A(1), Tag::A(3), Tag::B(4)]
+        );
+    }
+}
@@ -0,0 +1,26 @@
+use std::time::Instant;
+
+use super::conn::Clock;
+use super::now;
+
+/// When a packet from the roboRIO was received and decoded
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PacketTime {
+    /// When the packet was received on the runtime's monotonic clock, for measuring the time between packets
+    pub instant: Instant,
+    /// When the packet was received in milliseconds since the Unix epoch, for lining up with the robot's logs
+    ///
+    /// This is read from the same clock that the roboRIO's clock is set from, see
+    /// [`DriverStationBuilder::clock`](struct.DriverStationBuilder.html#method.clock).
+    pub timestamp: u64,
+}
+
+impl PacketTime {
+    /// Returns the time now, reading the wall clock from `clock`
+    pub(crate) fn now(clock: &Clock) -> PacketTime {
+        PacketTime {
+            instant: now(),
+            timestamp: clock.millis(),
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! Control of the driver station by other programs, over the network
+
+#[cfg(feature = "json")]
+mod command;
+#[cfg(feature = "grpc")]
+pub(crate) mod grpc;
+#[cfg(feature = "http")]
+pub(crate) mod http;
+#[cfg(feature = "websocket")]
+pub(crate) mod websocket;
+
+#[cfg(feature = "json")]
+pub use self::command::Command;
+#[cfg(any(feature = "websocket", feature = "http"))]
+pub(crate) use self::command::Outgoing;
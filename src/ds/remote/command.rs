@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+use crate::ds::state::{DsState, Mode};
+use crate::ds::telemetry::Telemetry;
+
+/// A command sent to the driver station by a remote client, as JSON
+///
+/// Commands are tagged by name, e.g. `{"command": "enable"}` or `{"command": "set_mode", "mode": "Autonomous"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Command {
+    /// Enables the robot, which fails if an interlock is configured and hasn't been armed
+    Enable,
+    Disable,
+    Estop,
+    SetMode {
+        mode: Mode,
+    },
+    /// Sets the game specific message, which must be 3 characters long
+    GameData {
+        message: String,
+    },
+}
+
+impl Command {
+    pub(crate) async fn apply(self, state: &DsState) -> Result<()> {
+        match self {
+            Command::Enable => state.enable().await?,
+            Command::Disable => state.disable().await,
+            Command::Estop => state.estop().await,
+            Command::SetMode { mode } => state.send().write().await.set_mode(mode),
+            Command::GameData { message } => state.set_game_specific_message(&message).await?,
+        }
+        Ok(())
+    }
+}
+
+/// A message sent to a remote client, tagged by `type`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum Outgoing {
+    Telemetry(Telemetry),
+    /// The outcome of a command, sent in the order commands were received
+    Reply {
+        ok: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+impl Outgoing {
+    /// Parses and applies a command, returning the reply to send
+    pub async fn reply(command: &str, state: &DsState) -> Outgoing {
+        let result = match serde_json::from_str::<Command>(command) {
+            Ok(command) => command.apply(state).await.map_err(|e| e.to_string()),
+            Err(e) => Err(format!("Invalid command: {}", e)),
+        };
+        Outgoing::from(result)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("outgoing messages are always serializable")
+    }
+}
+
+impl From<std::result::Result<(), String>> for Outgoing {
+    fn from(result: std::result::Result<(), String>) -> Outgoing {
+        Outgoing::Reply {
+            ok: result.is_ok(),
+            error: result.err(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn commands_are_parsed_and_applied() {
+        let state = DsState::test_default();
+
+        let reply =
+            Outgoing::reply(r#"{"command": "set_mode", "mode": "Autonomous"}"#, &state).await;
+        assert_eq!(reply.to_json(), r#"{"type":"reply","ok":true}"#);
+        assert_eq!(state.send().read().await.mode(), Mode::Autonomous);
+
+        Outgoing::reply(r#"{"command": "enable"}"#, &state).await;
+        assert!(state.send().read().await.enabled());
+
+        let reply = Outgoing::reply(r#"{"command": "game_data", "message": "LR"}"#, &state).await;
+        assert!(matches!(
+            reply,
+            Outgoing::Reply {
+                ok: false,
+                error: Some(_)
+            }
+        ));
+        let reply = Outgoing::reply(r#"{"command": "fly"}"#, &state).await;
+        assert!(matches!(
+            reply,
+            Outgoing::Reply {
+                ok: false,
+                error: Some(_)
+            }
+        ));
+    }
+}
@@ -0,0 +1,417 @@
+//! The gRPC service described by `grpc.proto`
+//!
+//! The messages and routing are written out here rather than generated, so that building the crate doesn't need `protoc`.
+//! They must be kept in step with the schema.
+
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::Stream;
+use tokio::net::TcpListener;
+use tokio::time::MissedTickBehavior;
+use tonic::codec::ProstCodec;
+use tonic::codegen::{Body, BoxFuture, Service, StdError, http};
+use tonic::server::{Grpc, NamedService};
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Code, Request, Response, Status};
+
+use crate::ds::conn::TaskGuard;
+use crate::ds::state::{self, DsState};
+use crate::{DsError, Result};
+
+/// How often telemetry is streamed when the client doesn't say
+const DEFAULT_PERIOD: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct Empty {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Mode {
+    Teleoperated = 0,
+    Autonomous = 1,
+    Test = 2,
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct SetModeRequest {
+    #[prost(enumeration = "Mode", tag = "1")]
+    pub mode: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GameDataRequest {
+    #[prost(string, tag = "1")]
+    pub message: String,
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct TelemetryRequest {
+    #[prost(uint32, tag = "1")]
+    pub period_ms: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct Telemetry {
+    #[prost(uint64, tag = "1")]
+    pub timestamp: u64,
+    #[prost(bool, tag = "2")]
+    pub connected: bool,
+    #[prost(bool, tag = "3")]
+    pub tcp_connected: bool,
+    #[prost(bool, tag = "4")]
+    pub enabled: bool,
+    #[prost(bool, tag = "5")]
+    pub estopped: bool,
+    #[prost(enumeration = "Mode", tag = "6")]
+    pub mode: i32,
+    #[prost(uint32, tag = "7")]
+    pub trace: u32,
+    #[prost(float, tag = "8")]
+    pub battery_voltage: f32,
+    #[prost(double, tag = "9")]
+    pub sent_rate: f64,
+    #[prost(double, tag = "10")]
+    pub recv_rate: f64,
+    #[prost(double, tag = "11")]
+    pub jitter_ms: f64,
+    #[prost(uint64, tag = "12")]
+    pub received: u64,
+    #[prost(uint64, tag = "13")]
+    pub lost: u64,
+    #[prost(double, optional, tag = "14")]
+    pub trip_time_ms: Option<f64>,
+    #[prost(double, tag = "15")]
+    pub tick_rate: f64,
+    #[prost(uint64, tag = "16")]
+    pub late_ticks: u64,
+    #[prost(uint64, optional, tag = "17")]
+    pub udp_received: Option<u64>,
+    #[prost(uint64, optional, tag = "18")]
+    pub tcp_received: Option<u64>,
+}
+
+impl From<state::Mode> for Mode {
+    fn from(mode: state::Mode) -> Mode {
+        match mode {
+            state::Mode::Teleoperated => Mode::Teleoperated,
+            state::Mode::Autonomous => Mode::Autonomous,
+            state::Mode::Test => Mode::Test,
+        }
+    }
+}
+
+impl From<Mode> for state::Mode {
+    fn from(mode: Mode) -> state::Mode {
+        match mode {
+            Mode::Teleoperated => state::Mode::Teleoperated,
+            Mode::Autonomous => state::Mode::Autonomous,
+            Mode::Test => state::Mode::Test,
+        }
+    }
+}
+
+impl From<crate::ds::telemetry::Telemetry> for Telemetry {
+    fn from(telemetry: crate::ds::telemetry::Telemetry) -> Telemetry {
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        Telemetry {
+            timestamp: telemetry.timestamp,
+            connected: telemetry.connected,
+            tcp_connected: telemetry.tcp_connected,
+            enabled: telemetry.enabled,
+            estopped: telemetry.estopped,
+            mode: Mode::from(telemetry.mode) as i32,
+            trace: telemetry.trace.bits().into(),
+            battery_voltage: telemetry.battery_voltage,
+            sent_rate: telemetry.net.sent_rate,
+            recv_rate: telemetry.net.recv_rate,
+            jitter_ms: millis(telemetry.net.jitter),
+            received: telemetry.net.received,
+            lost: telemetry.net.lost,
+            trip_time_ms: telemetry.net.trip_time.map(millis),
+            tick_rate: telemetry.ticks.rate,
+            late_ticks: telemetry.ticks.late,
+            udp_received: telemetry.udp_received,
+            tcp_received: telemetry.tcp_received,
+        }
+    }
+}
+
+/// Maps the errors the driver station can return to a command to their closest status
+fn status(e: DsError) -> Status {
+    let code = match e {
+        DsError::InvalidGameData(_) => Code::InvalidArgument,
+        DsError::NotArmed => Code::FailedPrecondition,
+        _ => Code::Internal,
+    };
+    Status::new(code, e.to_string())
+}
+
+async fn capture(state: &DsState) -> Telemetry {
+    crate::ds::telemetry::Telemetry::capture(state).await.into()
+}
+
+/// Adapts an async closure to the services tonic routes requests to
+struct Handler<F>(F);
+
+impl<F, Fut, Req, Res> Service<Request<Req>> for Handler<F>
+where
+    F: FnMut(Req) -> Fut,
+    Fut: Future<Output = std::result::Result<Res, Status>> + Send + 'static,
+{
+    type Response = Response<Res>;
+    type Error = Status;
+    type Future = BoxFuture<Response<Res>, Status>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<std::result::Result<(), Status>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Req>) -> Self::Future {
+        let fut = (self.0)(request.into_inner());
+        Box::pin(async move { fut.await.map(Response::new) })
+    }
+}
+
+type TelemetryStream = Pin<Box<dyn Stream<Item = std::result::Result<Telemetry, Status>> + Send>>;
+
+fn stream_telemetry(state: Arc<DsState>, period: Duration) -> TelemetryStream {
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    Box::pin(futures::stream::unfold(
+        (state, interval),
+        |(state, mut interval)| async move {
+            interval.tick().await;
+            let telemetry = capture(&state).await;
+            Some((Ok(telemetry), (state, interval)))
+        },
+    ))
+}
+
+/// Each method is handled with its own codec, as they are typed by the messages they encode and decode
+fn codec<T, U>() -> Grpc<ProstCodec<T, U>>
+where
+    T: prost::Message + Send + 'static,
+    U: prost::Message + Default + Send + 'static,
+{
+    Grpc::new(ProstCodec::default())
+}
+
+/// Routes requests for the `ds.DriverStation` service
+#[derive(Clone)]
+struct DsService {
+    state: Arc<DsState>,
+}
+
+impl NamedService for DsService {
+    const NAME: &'static str = "ds.DriverStation";
+}
+
+impl<B> Service<http::Request<B>> for DsService
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Infallible>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<std::result::Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let state = self.state.clone();
+        Box::pin(async move {
+            let response = match req.uri().path() {
+                "/ds.DriverStation/Enable" => {
+                    let handler = Handler(move |_: Empty| {
+                        let state = state.clone();
+                        async move { state.enable().await.map(|_| Empty {}).map_err(status) }
+                    });
+                    codec().unary(handler, req).await
+                }
+                "/ds.DriverStation/Disable" => {
+                    let handler = Handler(move |_: Empty| {
+                        let state = state.clone();
+                        async move {
+                            state.disable().await;
+                            Ok(Empty {})
+                        }
+                    });
+                    codec().unary(handler, req).await
+                }
+                "/ds.DriverStation/Estop" => {
+                    let handler = Handler(move |_: Empty| {
+                        let state = state.clone();
+                        async move {
+                            state.estop().await;
+                            Ok(Empty {})
+                        }
+                    });
+                    codec().unary(handler, req).await
+                }
+                "/ds.DriverStation/SetMode" => {
+                    let handler = Handler(move |request: SetModeRequest| {
+                        let state = state.clone();
+                        async move {
+                            let mode = Mode::try_from(request.mode)
+                                .map_err(|_| Status::invalid_argument("Unknown mode"))?;
+                            state.send().write().await.set_mode(mode.into());
+                            Ok(Empty {})
+                        }
+                    });
+                    codec().unary(handler, req).await
+                }
+                "/ds.DriverStation/SetGameData" => {
+                    let handler = Handler(move |request: GameDataRequest| {
+                        let state = state.clone();
+                        async move {
+                            state
+                                .set_game_specific_message(&request.message)
+                                .await
+                                .map(|_| Empty {})
+                                .map_err(status)
+                        }
+                    });
+                    codec().unary(handler, req).await
+                }
+                "/ds.DriverStation/GetTelemetry" => {
+                    let handler = Handler(move |_: Empty| {
+                        let state = state.clone();
+                        async move { Ok(capture(&state).await) }
+                    });
+                    codec().unary(handler, req).await
+                }
+                "/ds.DriverStation/StreamTelemetry" => {
+                    let handler = Handler(move |request: TelemetryRequest| {
+                        let state = state.clone();
+                        async move {
+                            let period = match request.period_ms {
+                                0 => DEFAULT_PERIOD,
+                                millis => Duration::from_millis(millis.into()),
+                            };
+                            Ok(stream_telemetry(state, period))
+                        }
+                    });
+                    codec().server_streaming(handler, req).await
+                }
+                _ => Status::unimplemented("").into_http(),
+            };
+            Ok(response)
+        })
+    }
+}
+
+/// A gRPC server started by [`DriverStation::serve_grpc`](struct.DriverStation.html#method.serve_grpc),
+/// which stops when dropped
+#[must_use = "the server is stopped as soon as it is dropped"]
+pub struct GrpcServer {
+    addr: SocketAddr,
+    _task: TaskGuard<()>,
+}
+
+impl GrpcServer {
+    /// Returns the address the server is listening on, such as to find the port when binding to port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl fmt::Debug for GrpcServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GrpcServer")
+            .field("addr", &self.addr)
+            .finish_non_exhaustive()
+    }
+}
+
+pub(crate) async fn serve(state: Arc<DsState>, addr: SocketAddr) -> Result<GrpcServer> {
+    let listener = TcpListener::bind(addr).await?;
+    let addr = listener.local_addr()?;
+    let incoming = TcpIncoming::from_listener(listener, true, None)
+        .expect("creating the incoming stream doesn't fail");
+    let task = TaskGuard::spawn(async move {
+        let served = Server::builder()
+            .add_service(DsService { state })
+            .serve_with_incoming(incoming)
+            .await;
+        if let Err(e) = served {
+            warn!("gRPC server stopped: {}", e);
+        }
+    });
+    Ok(GrpcServer { addr, _task: task })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::StreamExt;
+    use std::net::Ipv4Addr;
+    use tonic::client::Grpc as Client;
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic::transport::Channel;
+
+    #[tokio::test]
+    async fn rpcs_control_the_driver_station() {
+        let state = Arc::new(DsState::test_default());
+        let server = serve(state.clone(), (Ipv4Addr::LOCALHOST, 0).into())
+            .await
+            .unwrap();
+        let channel = Channel::from_shared(format!("http://{}", server.local_addr()))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let mut client = Client::new(channel);
+
+        client.ready().await.unwrap();
+        let request = SetModeRequest {
+            mode: Mode::Autonomous as i32,
+        };
+        client
+            .unary::<_, Empty, _>(
+                Request::new(request),
+                PathAndQuery::from_static("/ds.DriverStation/SetMode"),
+                ProstCodec::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(state.send().read().await.mode(), state::Mode::Autonomous);
+
+        client.ready().await.unwrap();
+        let request = GameDataRequest {
+            message: "L".to_string(),
+        };
+        let error = client
+            .unary::<_, Empty, _>(
+                Request::new(request),
+                PathAndQuery::from_static("/ds.DriverStation/SetGameData"),
+                ProstCodec::default(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+
+        client.ready().await.unwrap();
+        let mut stream = client
+            .server_streaming::<_, Telemetry, _>(
+                Request::new(TelemetryRequest { period_ms: 10 }),
+                PathAndQuery::from_static("/ds.DriverStation/StreamTelemetry"),
+                ProstCodec::default(),
+            )
+            .await
+            .unwrap()
+            .into_inner();
+        let telemetry = stream.next().await.unwrap().unwrap();
+        assert_eq!(telemetry.mode, Mode::Autonomous as i32);
+        assert!(!telemetry.connected);
+    }
+}
@@ -0,0 +1,199 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode, header};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+
+use super::{Command, Outgoing};
+use crate::Result;
+use crate::ds::conn::TaskGuard;
+use crate::ds::state::DsState;
+use crate::ds::telemetry::Telemetry;
+
+/// The largest request body accepted, which is plenty for any command
+const MAX_BODY: usize = 64 * 1024;
+
+/// An HTTP server started by [`DriverStation::serve_http`](struct.DriverStation.html#method.serve_http),
+/// which stops when dropped
+#[must_use = "the server is stopped as soon as it is dropped"]
+pub struct HttpServer {
+    addr: SocketAddr,
+    _task: TaskGuard<()>,
+}
+
+impl HttpServer {
+    /// Returns the address the server is listening on, such as to find the port when binding to port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl fmt::Debug for HttpServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpServer")
+            .field("addr", &self.addr)
+            .finish_non_exhaustive()
+    }
+}
+
+pub(crate) async fn serve(state: Arc<DsState>, addr: SocketAddr) -> Result<HttpServer> {
+    let listener = TcpListener::bind(addr).await?;
+    let addr = listener.local_addr()?;
+    let task = TaskGuard::spawn(async move {
+        // Dropping the set aborts the connections along with the server
+        let mut connections = JoinSet::new();
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        let state = state.clone();
+                        let service = service_fn(move |req| handle(state.clone(), req));
+                        connections.spawn(async move {
+                            let _ = http1::Builder::new()
+                                .serve_connection(TokioIo::new(stream), service)
+                                .await;
+                        });
+                    }
+                    Err(e) => warn!("Failed to accept an HTTP connection: {}", e),
+                },
+                Some(_) = connections.join_next() => {}
+            }
+        }
+    });
+    Ok(HttpServer { addr, _task: task })
+}
+
+/// Returns the name of the command each endpoint applies
+fn command_name(path: &str) -> Option<&'static str> {
+    Some(match path {
+        "/enable" => "enable",
+        "/disable" => "disable",
+        "/estop" => "estop",
+        "/mode" => "set_mode",
+        "/game_data" => "game_data",
+        _ => return None,
+    })
+}
+
+async fn handle(
+    state: Arc<DsState>,
+    req: Request<Incoming>,
+) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+    let name = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/telemetry") => {
+            let telemetry = Telemetry::capture(&state).await;
+            let body = serde_json::to_string(&telemetry).expect("telemetry is always serializable");
+            return Ok(json(StatusCode::OK, body));
+        }
+        (&Method::POST, path) => match command_name(path) {
+            Some(name) => name,
+            None => return Ok(status(StatusCode::NOT_FOUND)),
+        },
+        (_, path) if path == "/telemetry" || command_name(path).is_some() => {
+            return Ok(status(StatusCode::METHOD_NOT_ALLOWED));
+        }
+        _ => return Ok(status(StatusCode::NOT_FOUND)),
+    };
+
+    let body = match Limited::new(req.into_body(), MAX_BODY).collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(_) => return Ok(status(StatusCode::PAYLOAD_TOO_LARGE)),
+    };
+    // The command is named by the endpoint, and its arguments are given in the body, if it has any
+    let command = if body.iter().all(u8::is_ascii_whitespace) {
+        Ok(serde_json::Map::new())
+    } else {
+        serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(&body)
+    }
+    .and_then(|mut args| {
+        args.insert("command".to_string(), name.into());
+        serde_json::from_value::<Command>(args.into())
+    });
+
+    let result = match command {
+        Ok(command) => command.apply(&state).await.map_err(|e| e.to_string()),
+        Err(e) => Err(format!("Invalid command: {}", e)),
+    };
+    let code = if result.is_ok() {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    Ok(json(code, Outgoing::from(result).to_json()))
+}
+
+fn json(code: StatusCode, body: String) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(code)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body)))
+        .expect("responses are always valid")
+}
+
+fn status(code: StatusCode) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(code)
+        .body(Full::new(Bytes::new()))
+        .expect("responses are always valid")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ds::state::Mode;
+    use std::net::Ipv4Addr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    /// Sends a request over a connection of its own, returning the response
+    async fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: ds\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn endpoints_apply_commands() {
+        let state = Arc::new(DsState::test_default());
+        let server = serve(state.clone(), (Ipv4Addr::LOCALHOST, 0).into())
+            .await
+            .unwrap();
+        let addr = server.local_addr();
+
+        let response = request(addr, "POST", "/mode", r#"{"mode": "Test"}"#).await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with(r#"{"type":"reply","ok":true}"#));
+        assert_eq!(state.send().read().await.mode(), Mode::Test);
+
+        let response = request(addr, "POST", "/enable", "").await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(state.send().read().await.enabled());
+
+        let response = request(addr, "POST", "/game_data", r#"{"message": "L"}"#).await;
+        assert!(response.starts_with("HTTP/1.1 400"));
+
+        let response = request(addr, "GET", "/telemetry", "").await;
+        assert!(response.contains(r#""enabled":true"#));
+
+        let response = request(addr, "GET", "/enable", "").await;
+        assert!(response.starts_with("HTTP/1.1 405"));
+    }
+}
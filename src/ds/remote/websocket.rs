@@ -0,0 +1,138 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+use tokio::time::MissedTickBehavior;
+use tokio_tungstenite::tungstenite::Message;
+
+use super::Outgoing;
+use crate::Result;
+use crate::ds::conn::TaskGuard;
+use crate::ds::state::DsState;
+use crate::ds::telemetry::Telemetry;
+
+/// A WebSocket server started by [`DriverStation::serve_websocket`](struct.DriverStation.html#method.serve_websocket),
+/// which stops and closes every connection when dropped
+#[must_use = "the server is stopped as soon as it is dropped"]
+pub struct WebSocketServer {
+    addr: SocketAddr,
+    _task: TaskGuard<()>,
+}
+
+impl WebSocketServer {
+    /// Returns the address the server is listening on, such as to find the port when binding to port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl fmt::Debug for WebSocketServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocketServer")
+            .field("addr", &self.addr)
+            .finish_non_exhaustive()
+    }
+}
+
+pub(crate) async fn serve(
+    state: Arc<DsState>,
+    addr: SocketAddr,
+    period: Duration,
+) -> Result<WebSocketServer> {
+    let listener = TcpListener::bind(addr).await?;
+    let addr = listener.local_addr()?;
+    let task = TaskGuard::spawn(async move {
+        // Dropping the set aborts the connections along with the server
+        let mut connections = JoinSet::new();
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        connections.spawn(connection(state.clone(), stream, peer, period));
+                    }
+                    Err(e) => warn!("Failed to accept a WebSocket connection: {}", e),
+                },
+                Some(_) = connections.join_next() => {}
+            }
+        }
+    });
+    Ok(WebSocketServer { addr, _task: task })
+}
+
+/// Streams telemetry to a client every `period`, and applies the commands it sends until it disconnects
+async fn connection(state: Arc<DsState>, stream: TcpStream, peer: SocketAddr, period: Duration) {
+    let mut socket = match tokio_tungstenite::accept_async(stream).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            debug!("WebSocket handshake with {} failed: {}", peer, e);
+            return;
+        }
+    };
+    debug!("WebSocket client connected from {}", peer);
+
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        let outgoing = tokio::select! {
+            _ = interval.tick() => Outgoing::Telemetry(Telemetry::capture(&state).await),
+            message = socket.next() => match message {
+                Some(Ok(Message::Text(command))) => Outgoing::reply(&command, &state).await,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by tungstenite itself
+                Some(Ok(_)) => continue,
+            },
+        };
+        if socket
+            .send(Message::Text(outgoing.to_json()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+    debug!("WebSocket client {} disconnected", peer);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[tokio::test]
+    async fn clients_get_telemetry_and_replies() {
+        let state = Arc::new(DsState::test_default());
+        let server = serve(
+            state.clone(),
+            (Ipv4Addr::LOCALHOST, 0).into(),
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+
+        let addr = server.local_addr();
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (mut client, _) = tokio_tungstenite::client_async(format!("ws://{}/", addr), stream)
+            .await
+            .unwrap();
+
+        let first = client.next().await.unwrap().unwrap().into_text().unwrap();
+        assert!(first.starts_with(r#"{"type":"telemetry""#));
+
+        client
+            .send(Message::Text(r#"{"command":"estop"}"#.to_string()))
+            .await
+            .unwrap();
+        loop {
+            let message = client.next().await.unwrap().unwrap().into_text().unwrap();
+            if message.contains(r#""type":"reply""#) {
+                assert_eq!(message, r#"{"type":"reply","ok":true}"#);
+                break;
+            }
+        }
+        assert!(state.send().read().await.estopped());
+    }
+}
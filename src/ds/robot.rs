@@ -0,0 +1,113 @@
+use super::mode::Mode;
+use crate::proto::udp::inbound::types::{Status, Trace};
+
+/// The overall state of the robot, as reported in its status packets
+///
+/// This combines whether robot code is running, whether it is enabled or E-stopped, and the mode it is in, which is
+/// what the official driver station shows next to its enable button.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RobotState {
+    /// The roboRIO isn't responding to control packets
+    Disconnected,
+    /// The roboRIO is responding, but robot code isn't running
+    NoRobotCode,
+    /// The robot has been E-stopped, and can't be enabled until its code is restarted
+    EStopped,
+    DisabledAuto,
+    DisabledTeleop,
+    DisabledTest,
+    EnabledAuto,
+    EnabledTeleop,
+    EnabledTest,
+}
+
+impl RobotState {
+    /// Derives the state of a connected robot from the status and trace of a status packet
+    pub fn new(status: Status, trace: Trace) -> RobotState {
+        if status.emergency_stopped() {
+            return RobotState::EStopped;
+        }
+        if !trace.is_code_started() {
+            return RobotState::NoRobotCode;
+        }
+
+        let enabled = status.contains(Status::ENABLED);
+        match (Mode::from_status(status), enabled) {
+            (Some(Mode::Autonomous), false) => RobotState::DisabledAuto,
+            (Some(Mode::Autonomous), true) => RobotState::EnabledAuto,
+            (Some(Mode::Test), false) => RobotState::DisabledTest,
+            (Some(Mode::Test), true) => RobotState::EnabledTest,
+            (_, false) => RobotState::DisabledTeleop,
+            (_, true) => RobotState::EnabledTeleop,
+        }
+    }
+
+    /// Returns whether the robot is enabled in this state
+    pub const fn enabled(self) -> bool {
+        matches!(
+            self,
+            RobotState::EnabledAuto | RobotState::EnabledTeleop | RobotState::EnabledTest
+        )
+    }
+
+    /// Returns the mode the robot is in, if it is running code and hasn't been E-stopped
+    pub const fn mode(self) -> Option<Mode> {
+        match self {
+            RobotState::DisabledAuto | RobotState::EnabledAuto => Some(Mode::Autonomous),
+            RobotState::DisabledTeleop | RobotState::EnabledTeleop => Some(Mode::Teleoperated),
+            RobotState::DisabledTest | RobotState::EnabledTest => Some(Mode::Test),
+            RobotState::Disconnected | RobotState::NoRobotCode | RobotState::EStopped => None,
+        }
+    }
+}
+
+/// A change in the [`RobotState`](enum.RobotState.html) reported by the robot
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RobotStateChange {
+    /// When the change was observed, in milliseconds since the Unix epoch
+    ///
+    /// This is when the status packet reporting the new state was received, or when the roboRIO was found to have
+    /// stopped responding.
+    pub timestamp: u64,
+    pub from: RobotState,
+    pub to: RobotState,
+}
+
+#[cfg(feature = "runtime")]
+impl RobotStateChange {
+    pub(crate) fn new(from: RobotState, to: RobotState, timestamp: u64) -> RobotStateChange {
+        RobotStateChange {
+            timestamp,
+            from,
+            to,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn state_is_derived_from_the_status_packet() {
+        let code = Trace::ROBOT_CODE | Trace::IS_ROBORIO;
+        assert_eq!(
+            RobotState::new(Status::ENABLED | Status::AUTO, code),
+            RobotState::EnabledAuto
+        );
+        assert_eq!(
+            RobotState::new(Status::TEST, code),
+            RobotState::DisabledTest
+        );
+        assert_eq!(
+            RobotState::new(Status::ENABLED, Trace::IS_ROBORIO),
+            RobotState::NoRobotCode
+        );
+        assert_eq!(
+            RobotState::new(Status::ESTOP | Status::ENABLED, code),
+            RobotState::EStopped
+        );
+    }
+}
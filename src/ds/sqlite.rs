@@ -0,0 +1,349 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use rusqlite::{Connection, params};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::MissedTickBehavior;
+use tokio_stream::StreamExt;
+
+use super::conn::TaskGuard;
+use super::event::DsEvent;
+use super::received::PacketTime;
+use super::state::{DsState, Mode};
+use super::telemetry::Telemetry;
+use crate::{ConsoleSource, DsError, Result, TcpPacket};
+
+/// The tables of each session's database
+const SCHEMA: &str = "
+    CREATE TABLE telemetry (
+        timestamp INTEGER NOT NULL,
+        connected INTEGER NOT NULL,
+        tcp_connected INTEGER NOT NULL,
+        enabled INTEGER NOT NULL,
+        estopped INTEGER NOT NULL,
+        mode TEXT NOT NULL,
+        trace INTEGER NOT NULL,
+        battery_voltage REAL NOT NULL,
+        sent_rate REAL NOT NULL,
+        recv_rate REAL NOT NULL,
+        jitter_ms REAL NOT NULL,
+        received INTEGER NOT NULL,
+        lost INTEGER NOT NULL,
+        trip_time_ms REAL,
+        tick_rate REAL NOT NULL,
+        late_ticks INTEGER NOT NULL
+    );
+    CREATE TABLE control (
+        timestamp INTEGER NOT NULL,
+        enabled INTEGER NOT NULL,
+        estopped INTEGER NOT NULL,
+        mode TEXT NOT NULL
+    );
+    CREATE TABLE console (
+        timestamp INTEGER NOT NULL,
+        source TEXT NOT NULL,
+        robot_time REAL NOT NULL,
+        seqnum INTEGER NOT NULL,
+        message TEXT NOT NULL
+    );
+    CREATE TABLE events (
+        timestamp INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        detail TEXT NOT NULL
+    );
+";
+
+/// Describes where and how often a session is recorded by
+/// [`DriverStation::record_sqlite`](struct.DriverStation.html#method.record_sqlite)
+///
+/// Each session is written to its own SQLite database, with these tables:
+///
+/// * `telemetry`, a [`Telemetry`](struct.Telemetry.html) snapshot every `period`, with the columns of
+///   [`CsvLog`](struct.CsvLog.html)
+/// * `control`, the enabled, E-stopped and mode state the robot was sent, whenever it changes
+/// * `console`, the console messages received from the robot, with their source, robot time and seqnum
+/// * `events`, each [`DsEvent`](enum.DsEvent.html), including the connection to the roboRIO being made and lost,
+///   as its variant name and its `Debug` representation
+///
+/// Every table has a `timestamp` column in milliseconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqliteLog {
+    /// The directory databases are created in, which is created if it doesn't exist
+    pub dir: PathBuf,
+    /// How often telemetry is recorded, and changes to the control state are checked for
+    pub period: Duration,
+}
+
+impl SqliteLog {
+    /// Records to `dir` every 20ms, the rate control packets are sent at
+    pub fn new(dir: impl Into<PathBuf>) -> SqliteLog {
+        SqliteLog {
+            dir: dir.into(),
+            period: Duration::from_millis(20),
+        }
+    }
+}
+
+/// Keeps a recorder started by [`DriverStation::record_sqlite`](struct.DriverStation.html#method.record_sqlite)
+/// running, stopping it when dropped
+#[must_use = "recording is stopped as soon as its handle is dropped"]
+pub struct SqliteLogHandle {
+    path: PathBuf,
+    _task: TaskGuard<()>,
+}
+
+impl SqliteLogHandle {
+    /// Returns the path of the session's database
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl fmt::Debug for SqliteLogHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqliteLogHandle")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<rusqlite::Error> for DsError {
+    fn from(e: rusqlite::Error) -> Self {
+        DsError::Database(e.to_string())
+    }
+}
+
+/// A row to be inserted into the database
+enum Record {
+    Telemetry(Telemetry),
+    Control {
+        timestamp: u64,
+        enabled: bool,
+        estopped: bool,
+        mode: Mode,
+    },
+    Console(PacketTime, TcpPacket),
+    Event(u64, DsEvent),
+}
+
+const fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Autonomous => "autonomous",
+        Mode::Teleoperated => "teleoperated",
+        Mode::Test => "test",
+    }
+}
+
+const fn source_name(source: ConsoleSource) -> &'static str {
+    match source {
+        ConsoleSource::Tcp => "tcp",
+        ConsoleSource::NetConsole => "netconsole",
+        ConsoleSource::Riolog => "riolog",
+    }
+}
+
+/// Creates the database at `path`, with the tables of a new session
+fn open(path: &Path) -> Result<Connection> {
+    let conn = Connection::open(path)?;
+    // Rows are written 50 times a second, which waiting for the disk after each transaction can't keep up with
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(conn)
+}
+
+fn insert(conn: &Connection, record: Record) -> rusqlite::Result<()> {
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    match record {
+        Record::Telemetry(telemetry) => {
+            conn.prepare_cached(
+                "INSERT INTO telemetry VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            )?
+            .execute(params![
+                telemetry.timestamp,
+                telemetry.connected,
+                telemetry.tcp_connected,
+                telemetry.enabled,
+                telemetry.estopped,
+                mode_name(telemetry.mode),
+                telemetry.trace.bits(),
+                telemetry.battery_voltage,
+                telemetry.net.sent_rate,
+                telemetry.net.recv_rate,
+                millis(telemetry.net.jitter),
+                telemetry.net.received,
+                telemetry.net.lost,
+                telemetry.net.trip_time.map(millis),
+                telemetry.ticks.rate,
+                telemetry.ticks.late,
+            ])?;
+        }
+        Record::Control {
+            timestamp,
+            enabled,
+            estopped,
+            mode,
+        } => {
+            conn.prepare_cached("INSERT INTO control VALUES (?1, ?2, ?3, ?4)")?
+                .execute(params![timestamp, enabled, estopped, mode_name(mode)])?;
+        }
+        Record::Console(received, TcpPacket::Stdout(stdout)) => {
+            conn.prepare_cached("INSERT INTO console VALUES (?1, ?2, ?3, ?4, ?5)")?
+                .execute(params![
+                    received.timestamp,
+                    source_name(stdout.source),
+                    stdout.timestamp,
+                    stdout.seqnum,
+                    stdout.message,
+                ])?;
+        }
+        Record::Console(..) => {}
+        Record::Event(timestamp, event) => {
+            let detail = format!("{:?}", event);
+            let kind = detail
+                .split(|c: char| !c.is_alphanumeric())
+                .next()
+                .unwrap_or_default();
+            conn.prepare_cached("INSERT INTO events VALUES (?1, ?2, ?3)")?
+                .execute(params![timestamp, kind, detail])?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes records to the database until every sender has been dropped
+///
+/// The records that have built up while a transaction is written are batched into the next one.
+fn write(mut conn: Connection, rx: mpsc::Receiver<Record>) -> rusqlite::Result<()> {
+    while let Ok(record) = rx.recv() {
+        let tx = conn.transaction()?;
+        insert(&tx, record)?;
+        for record in rx.try_iter() {
+            insert(&tx, record)?;
+        }
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn start(state: Arc<DsState>, log: SqliteLog) -> Result<SqliteLogHandle> {
+    tokio::fs::create_dir_all(&log.dir).await?;
+    let name = chrono::Utc::now()
+        .format("ds-%Y%m%d-%H%M%S.sqlite")
+        .to_string();
+    let path = log.dir.join(name);
+    let conn = {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || open(&path))
+            .await
+            .map_err(|_| DsError::ChannelClosed)??
+    };
+
+    // SQLite blocks, so the database is written from its own thread
+    let (tx, rx) = mpsc::channel();
+    thread::Builder::new()
+        .name("ds-sqlite".to_string())
+        .spawn(move || {
+            if let Err(e) = write(conn, rx) {
+                warn!("SQLite recording stopped: {}", e);
+            }
+        })?;
+
+    let mut events = state.events().subscribe();
+    let mut console = state.timed_tcp_packet_stream();
+    let task = TaskGuard::spawn(async move {
+        let mut interval = tokio::time::interval(log.period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut control = None;
+        loop {
+            let record = tokio::select! {
+                _ = interval.tick() => {
+                    let telemetry = Telemetry::capture(&state).await;
+                    let current = (telemetry.enabled, telemetry.estopped, telemetry.mode);
+                    if control != Some(current) {
+                        control = Some(current);
+                        let record = Record::Control {
+                            timestamp: telemetry.timestamp,
+                            enabled: telemetry.enabled,
+                            estopped: telemetry.estopped,
+                            mode: telemetry.mode,
+                        };
+                        if tx.send(record).is_err() {
+                            break;
+                        }
+                    }
+                    Record::Telemetry(telemetry)
+                }
+                event = events.recv() => match event {
+                    Ok(event) => Record::Event(state.config().clock.millis(), event),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+                Some((received, packet)) = console.next() => Record::Console(received, packet),
+            };
+            // The writer thread only stops early if writing failed, which it has already reported
+            if tx.send(record).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(SqliteLogHandle { path, _task: task })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Stdout;
+
+    #[tokio::test]
+    async fn sessions_are_queryable() {
+        let dir = std::env::temp_dir().join(format!("ds-sqlite-test-{}", std::process::id()));
+        let state = Arc::new(DsState::test_default());
+        let handle = start(state.clone(), SqliteLog::new(&dir)).await.unwrap();
+
+        state.emit(DsEvent::Connected);
+        let stdout = Stdout {
+            timestamp: 1.5,
+            message: "hello".to_string(),
+            seqnum: 3,
+            source: ConsoleSource::Tcp,
+        };
+        state
+            .publish_tcp(
+                TcpPacket::Stdout(stdout),
+                PacketTime::now(&state.config().clock),
+            )
+            .await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let path = handle.path().to_path_buf();
+        drop(handle);
+        // The writer thread finishes its last transaction once the task's sender is dropped
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let conn = Connection::open(&path).unwrap();
+        let count = |table: &str| -> u64 {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        assert!(count("telemetry") > 0);
+        assert_eq!(count("control"), 1);
+        let message: String = conn
+            .query_row("SELECT message FROM console WHERE seqnum = 3", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(message, "hello");
+        let kind: String = conn
+            .query_row("SELECT kind FROM events", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(kind, "Connected");
+
+        drop(conn);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,29 +1,40 @@
-use super::JoystickValue;
+use super::{JoystickValue, now};
 
-use crate::TcpPacket;
+use crate::ds::builder::DsConfig;
+use crate::ds::conn::{
+    ChaosProfile, DisconnectPolicy, TaskGuard, clock_latency, date_time_tag, timezone_tag,
+};
+use crate::ds::event::{DsEvent, EstopSource};
+use crate::ds::received::PacketTime;
+use crate::ds::robot::RobotStateChange;
 use crate::ds::state::recv::{RecvState, TcpState};
 use crate::ds::state::send::SendState;
-use crate::proto::udp::inbound::types::Status;
-use crate::proto::udp::outbound::types::{Alliance, Control};
-use std::fmt::Debug;
-use tokio::sync::RwLock;
+use crate::ds::stats::{RecvTracker, TickTracker};
+use crate::proto::tcp::outbound::{GameData, TcpTag};
+use crate::proto::udp::inbound::types::{JoystickOutput, Trace};
+use crate::proto::udp::outbound::types::{Alliance, UdpTag};
+use crate::{DsError, Result, TcpPacket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+use tokio::sync::{Notify, RwLock, broadcast, watch};
+use tokio::time::MissedTickBehavior;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
 
 mod recv;
 mod send;
 
+pub(crate) use self::send::MAX_JOYSTICKS;
+pub use self::send::call_supplier;
+
+pub(crate) use super::PACKET_PERIOD;
+pub use super::mode::{DsMode, Mode};
+
 type JoystickSupplier = dyn Fn() -> Vec<Vec<JoystickValue>> + Send + Sync + 'static;
 type TcpConsumer = dyn FnMut(TcpPacket) + Send + Sync + 'static;
 
-/// The operating mode of the driver station
-///
-/// Normal operating mode connects to the IP specified by a team number
-/// Simulation mode connects to localhost, and is activated by a connection to ::1135
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum DsMode {
-    Normal,
-    Simulation,
-}
-
 /// The core state of the driver station, containing locks over all relevant substates
 pub struct DsState {
     /// The state associated with the sending UDP socket
@@ -32,22 +43,90 @@ pub struct DsState {
     recv_state: RwLock<RecvState>,
     /// The state associated with the TCP socket
     tcp_state: RwLock<TcpState>,
+    /// The fault injection profile applied by the connection tasks, if any
+    chaos: watch::Sender<Option<ChaosProfile>>,
+    /// The channel that events are broadcast to subscribers on
+    events: broadcast::Sender<DsEvent>,
+    /// The channel that decoded TCP packets are broadcast to subscribers on
+    tcp_packets: broadcast::Sender<TcpPacket>,
+    /// The channel that decoded TCP packets are broadcast on with when they were received
+    timed_tcp_packets: broadcast::Sender<(PacketTime, TcpPacket)>,
+    /// Whether the TCP connection to the roboRIO is open
+    tcp_connected: AtomicBool,
+    /// Whether an E-stop has been requested without taking the lock, to be applied by the send task
+    estop_requested: AtomicBool,
+    /// Wakes the send task when an E-stop is requested, so that it doesn't wait for the send state to send it
+    estop_notify: Notify,
+    /// The latest outputs and rumble set by robot code for each joystick
+    joystick_outputs: watch::Sender<Vec<JoystickOutput>>,
+    /// The latest Trace received from the robot, which is empty while it is disconnected
+    trace: watch::Sender<Trace>,
+    /// The channel that battery voltages are broadcast to subscribers on, with when they were received
+    battery: broadcast::Sender<(Instant, f32)>,
+    /// Whether the robot is being enabled, mirroring the send state for watchers
+    enabled: watch::Sender<bool>,
+    /// Whether the robot has been E-stopped, mirroring the send state for watchers
+    estopped: watch::Sender<bool>,
+    /// Whether the driver station is in simulation mode, mirroring the send state for watchers
+    ds_mode: watch::Sender<DsMode>,
+    /// When control packets have been sent, to measure the rate achieved
+    ticks: Mutex<TickTracker>,
+    /// When status packets have been received, to measure their rate, jitter and loss
+    arrivals: Mutex<RecvTracker>,
+    /// The network configuration the driver station was built with
+    config: DsConfig,
 }
 
 impl DsState {
-    #[inline(always)]
-    pub const fn new(alliance: Alliance) -> DsState {
-        let send_state = RwLock::const_new(SendState::new(alliance));
+    pub fn new(alliance: Alliance, team_number: u16, config: DsConfig) -> DsState {
+        let mut send_state = SendState::new(alliance, team_number);
+        send_state.configure_queue(config.queue_capacity, config.overflow);
+        send_state.set_catch_up(config.missed_ticks == MissedTickBehavior::Burst);
+        let send_state = RwLock::const_new(send_state);
         let recv_state = RwLock::const_new(RecvState::new());
         let tcp_state = RwLock::const_new(TcpState::new());
+        let (chaos, _) = watch::channel(None);
+        let (events, _) = broadcast::channel(64);
+        // Robot code can print in bursts, so more packets are buffered than events
+        let (tcp_packets, _) = broadcast::channel(256);
+        let (timed_tcp_packets, _) = broadcast::channel(256);
+        let (joystick_outputs, _) = watch::channel(Vec::new());
+        let (trace, _) = watch::channel(Trace::empty());
+        // A sample arrives with every status packet, so this holds about 5 seconds of them
+        let (battery, _) = broadcast::channel(256);
+        let (enabled, _) = watch::channel(false);
+        let (estopped, _) = watch::channel(false);
+        let (ds_mode, _) = watch::channel(DsMode::Normal);
 
         DsState {
             send_state,
             recv_state,
             tcp_state,
+            chaos,
+            events,
+            tcp_packets,
+            timed_tcp_packets,
+            tcp_connected: AtomicBool::new(false),
+            estop_requested: AtomicBool::new(false),
+            estop_notify: Notify::new(),
+            joystick_outputs,
+            trace,
+            battery,
+            enabled,
+            estopped,
+            ds_mode,
+            ticks: Mutex::new(TickTracker::new()),
+            arrivals: Mutex::new(RecvTracker::new()),
+            config,
         }
     }
 
+    /// Returns the state of team 4533's driver station at red 1 with the default configuration, for tests
+    #[cfg(test)]
+    pub(crate) fn test_default() -> DsState {
+        DsState::new(Alliance::new_red(1), 4533, DsConfig::default())
+    }
+
     #[inline(always)]
     pub const fn send(&self) -> &RwLock<SendState> {
         &self.send_state
@@ -62,38 +141,320 @@ impl DsState {
     pub const fn tcp(&self) -> &RwLock<TcpState> {
         &self.tcp_state
     }
-}
 
-/// Represents the current Mode that the robot is in. the `Mode` of the robot is considered separately from whether it is enabled or not
-#[derive(Copy, Clone, Debug)]
-pub enum Mode {
-    Autonomous,
-    Teleoperated,
-    Test,
-}
+    #[inline(always)]
+    pub const fn chaos(&self) -> &watch::Sender<Option<ChaosProfile>> {
+        &self.chaos
+    }
+
+    #[inline(always)]
+    pub const fn config(&self) -> &DsConfig {
+        &self.config
+    }
+
+    /// Returns the runtime that tasks for this driver station are spawned on
+    ///
+    /// Panics if no runtime was configured, and this isn't called from within one.
+    pub fn runtime(&self) -> Handle {
+        self.config.runtime.clone().unwrap_or_else(Handle::current)
+    }
+
+    /// Spawns a task for this driver station, on the runtime it was configured with
+    pub fn spawn<F>(&self, fut: F) -> TaskGuard<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        TaskGuard::spawn_on(&self.runtime(), fut)
+    }
+
+    /// Runs `fut` to completion on the runtime that the driver station was configured with, if any
+    ///
+    /// This is used for futures that create sockets, which need to be driven by the runtime they're created on.
+    /// Returns [`DsError::ChannelClosed`] if that runtime is shut down before `fut` completes.
+    pub async fn run<F, T>(&self, fut: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>> + Send + 'static,
+        T: Send + 'static,
+    {
+        match self.config.runtime {
+            Some(ref handle) => match TaskGuard::spawn_on(handle, fut).await {
+                Ok(output) => output,
+                Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+                // The runtime was shut down, such as while the application is exiting
+                Err(_) => Err(DsError::ChannelClosed),
+            },
+            None => fut.await,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn events(&self) -> &broadcast::Sender<DsEvent> {
+        &self.events
+    }
+
+    /// Streams the changes in the state of the robot, from the events emitted after calling this
+    pub fn robot_state_changes(&self) -> impl Stream<Item = RobotStateChange> + use<> {
+        BroadcastStream::new(self.events.subscribe()).filter_map(|event| match event {
+            Ok(DsEvent::RobotStateChanged(change)) => Some(change),
+            _ => None,
+        })
+    }
+
+    #[inline(always)]
+    pub const fn battery(&self) -> &broadcast::Sender<(Instant, f32)> {
+        &self.battery
+    }
+
+    /// Streams the battery voltages received from the robot, from the packets received after calling this
+    pub fn battery_stream(&self) -> impl Stream<Item = (Instant, f32)> + use<> {
+        BroadcastStream::new(self.battery.subscribe()).filter_map(|sample| sample.ok())
+    }
+
+    #[inline(always)]
+    pub const fn tcp_packets(&self) -> &broadcast::Sender<TcpPacket> {
+        &self.tcp_packets
+    }
+
+    #[inline(always)]
+    pub const fn timed_tcp_packets(&self) -> &broadcast::Sender<(PacketTime, TcpPacket)> {
+        &self.timed_tcp_packets
+    }
+
+    /// Passes a packet received from the roboRIO to the TCP consumers and streams
+    pub async fn publish_tcp(&self, packet: TcpPacket, received: PacketTime) {
+        let _ = self.tcp_packets.send(packet.clone());
+        let _ = self.timed_tcp_packets.send((received, packet.clone()));
+        self.tcp_state.write().await.consume(packet, received);
+    }
+
+    /// Streams the TCP packets received from the roboRIO with when they were received, from those received after calling this
+    pub fn timed_tcp_packet_stream(&self) -> impl Stream<Item = (PacketTime, TcpPacket)> + use<> {
+        BroadcastStream::new(self.timed_tcp_packets.subscribe()).filter_map(|packet| packet.ok())
+    }
+
+    #[inline(always)]
+    pub const fn joystick_outputs(&self) -> &watch::Sender<Vec<JoystickOutput>> {
+        &self.joystick_outputs
+    }
+
+    #[inline(always)]
+    pub const fn trace(&self) -> &watch::Sender<Trace> {
+        &self.trace
+    }
+
+    /// Publishes the Trace received from the robot, notifying watchers only if it has changed
+    pub fn set_trace(&self, trace: Trace) {
+        self.trace.send_if_modified(|current| {
+            let changed = *current != trace;
+            *current = trace;
+            changed
+        });
+    }
+
+    #[inline(always)]
+    pub const fn enabled(&self) -> &watch::Sender<bool> {
+        &self.enabled
+    }
 
-impl Mode {
-    /// Decodes the mode of the robot from the given status byte
-    #[inline]
-    pub const fn from_status(status: Status) -> Option<Mode> {
-        if status.contains(Status::TELEOP) {
-            Some(Mode::Teleoperated)
-        } else if status.contains(Status::AUTO) {
-            Some(Mode::Autonomous)
-        } else if status.contains(Status::TEST) {
-            Some(Mode::Test)
-        } else {
-            None
+    #[inline(always)]
+    pub const fn estopped(&self) -> &watch::Sender<bool> {
+        &self.estopped
+    }
+
+    /// Publishes whether `send` is enabled and E-stopped, notifying watchers only of changes
+    ///
+    /// This is called by the send task with every control packet, and straight away by the methods changing them.
+    pub fn publish_control(&self, send: &SendState) {
+        for (sender, value) in [
+            (&self.enabled, send.enabled()),
+            (&self.estopped, send.estopped()),
+        ] {
+            sender.send_if_modified(|current| std::mem::replace(current, value) != value);
+        }
+    }
+
+    #[inline(always)]
+    pub const fn ds_mode(&self) -> &watch::Sender<DsMode> {
+        &self.ds_mode
+    }
+
+    /// Publishes that the driver station has entered or left simulation mode, emitting an event
+    pub fn publish_ds_mode(&self, mode: DsMode) {
+        self.ds_mode.send_replace(mode);
+        self.emit(DsEvent::DsModeChanged(mode));
+    }
+
+    pub fn ticks(&self) -> MutexGuard<'_, TickTracker> {
+        self.ticks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn arrivals(&self) -> MutexGuard<'_, RecvTracker> {
+        self.arrivals.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    #[inline(always)]
+    pub fn tcp_connected(&self) -> bool {
+        self.tcp_connected.load(Ordering::Relaxed)
+    }
+
+    /// Records whether the TCP connection is open, emitting an event if that has changed
+    pub fn set_tcp_connected(&self, connected: bool) {
+        if self.tcp_connected.swap(connected, Ordering::Relaxed) != connected {
+            self.emit(if connected {
+                DsEvent::TcpConnected
+            } else {
+                DsEvent::TcpDisconnected
+            });
+        }
+    }
+
+    /// Broadcasts `event` to all subscribers, if there are any
+    pub fn emit(&self, event: DsEvent) {
+        debug!("{:?}", event);
+        let _ = self.events.send(event);
+    }
+
+    /// Enables the robot, unless an interlock is configured and the driver station wasn't armed beforehand
+    pub async fn enable(&self) -> Result<()> {
+        self.check_reenable().await?;
+        let mut send = self.send_state.write().await;
+        send.check_armed(self.config.interlock, now())?;
+        send.enable();
+        self.publish_control(&send);
+        Ok(())
+    }
+
+    /// Enables the robot until `duration` has passed, with the same interlock as [`enable`](#method.enable)
+    pub async fn enable_for(&self, duration: Duration) -> Result<()> {
+        self.check_reenable().await?;
+        let mut send = self.send_state.write().await;
+        send.check_armed(self.config.interlock, now())?;
+        send.enable_until(now() + duration);
+        self.publish_control(&send);
+        Ok(())
+    }
+
+    /// Returns an error if the disconnect policy doesn't allow enabling the robot while the roboRIO isn't responding
+    pub async fn check_reenable(&self) -> Result<()> {
+        if self.config.disconnect_policy == DisconnectPolicy::RequireReenable
+            && !self.recv_state.read().await.connected()
+        {
+            return Err(DsError::NotConnected);
+        }
+        Ok(())
+    }
+
+    /// Disables the robot, ending any practice match in progress
+    pub async fn disable(&self) {
+        let mut send = self.send_state.write().await;
+        send.disable();
+        self.publish_control(&send);
+        for event in send.take_events() {
+            self.emit(event);
+        }
+    }
+
+    /// E-stops the robot, ending any practice match in progress
+    pub async fn estop(&self) {
+        let mut send = self.send_state.write().await;
+        send.estop(EstopSource::User);
+        self.publish_control(&send);
+        for event in send.take_events() {
+            self.emit(event);
         }
     }
 
-    /// Converts this `Mode` into a `Control` byte that can be modified for encoding the control packet.
+    /// Requests that the robot is E-stopped by the send task, without waiting for any lock
+    pub fn estop_now(&self) {
+        self.estop_requested.store(true, Ordering::SeqCst);
+        self.estop_notify.notify_one();
+    }
+
+    /// Returns whether an E-stop has been requested that the send state hasn't applied yet
     #[inline(always)]
-    const fn to_control(self) -> Control {
-        match self {
-            Mode::Teleoperated => Control::TELEOP,
-            Mode::Autonomous => Control::AUTO,
-            Mode::Test => Control::TEST,
+    pub fn estop_requested(&self) -> bool {
+        self.estop_requested.load(Ordering::SeqCst)
+    }
+
+    /// Waits until [`estop_now`](#method.estop_now) is called, or returns straight away if it was called since this last returned
+    pub async fn estop_requested_notified(&self) {
+        self.estop_notify.notified().await;
+    }
+
+    /// E-stops `send` if [`estop_now`](#method.estop_now) has been called since this was last called
+    pub fn apply_estop_request(&self, send: &mut SendState) {
+        if self.estop_requested.swap(false, Ordering::SeqCst) {
+            warn!("E-stop requested");
+            send.estop(EstopSource::User);
         }
     }
+
+    /// Restarts the seqnums of control packets from 0
+    pub async fn reset_seqnum(&self) {
+        self.send_state.write().await.reset_seqnum();
+        // The roboRIO's echoes jump back too, which shouldn't be counted as losses
+        self.arrivals().reset();
+    }
+
+    /// Queues the DateTime and Timezone tags, setting the roboRIO's clock from the configured clock
+    pub async fn queue_time(&self) {
+        let mut time = self.config.clock.now();
+        if self.config.compensate_clock_latency {
+            time += clock_latency(self.arrivals().trip_time());
+        }
+        let timezone = timezone_tag(self.config.timezone.as_deref());
+
+        let mut send = self.send_state.write().await;
+        send.queue_udp(UdpTag::DateTime(date_time_tag(time)));
+        send.queue_udp(UdpTag::Timezone(timezone));
+    }
+
+    /// Queues the game specific message to be sent to the robot, which must be 3 characters long
+    pub async fn set_game_specific_message(&self, message: &str) -> Result<()> {
+        if message.len() != 3 {
+            return Err(DsError::InvalidGameData(
+                "Message should be 3 characters long".to_string(),
+            ));
+        }
+
+        let _ = self
+            .tcp_state
+            .write()
+            .await
+            .queue_tcp(TcpTag::GameData(GameData {
+                gsm: message.to_string(),
+            }));
+        Ok(())
+    }
+
+    /// Sets the current state of the joystick in `port`, numbering its buttons from 1 as the roboRIO expects
+    pub async fn update_joystick(&self, port: usize, axes: &[f32], buttons: &[bool], povs: &[i16]) {
+        let axes = axes
+            .iter()
+            .enumerate()
+            .map(|(id, &value)| JoystickValue::Axis {
+                id: id as u8,
+                value,
+            });
+        let buttons = buttons
+            .iter()
+            .enumerate()
+            .map(|(id, &pressed)| JoystickValue::Button {
+                id: (id + 1) as u8,
+                pressed,
+            });
+        let povs = povs
+            .iter()
+            .enumerate()
+            .map(|(id, &angle)| JoystickValue::POV {
+                id: id as u8,
+                angle,
+            });
+
+        self.send_state
+            .write()
+            .await
+            .set_joystick(port, axes.chain(buttons).chain(povs).collect());
+    }
 }
@@ -1,68 +1,148 @@
 use crate::Result;
-use crate::TcpPacket;
+use crate::ds::queue::SharedQueue;
+use crate::ds::received::PacketTime;
+use crate::ds::robot::RobotState;
 use crate::ds::state::TcpConsumer;
 use crate::proto::tcp::outbound::TcpTag;
 use crate::proto::udp::inbound::types::*;
-use anyhow::format_err;
-use tokio::sync::mpsc::UnboundedSender;
+use crate::{DsError, TcpPacket};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 /// All the data received from roboRIO UDP status packets that isn't already encoded in the send state
 pub struct RecvState {
     /// The current battery voltage
     battery_voltage: f32,
+    /// The bytes the current battery voltage was decoded from, before any correction
+    battery_raw: [u8; 2],
     /// A bitflags struct that can be used to query the state of various aspects of the RIO
     trace: Trace,
+    /// Whether the RIO is currently responding to control packets
+    connected: bool,
+    /// The address the RIO last answered from
+    rio_address: Option<IpAddr>,
+    /// The state the robot last reported being in
+    robot_state: RobotState,
+    /// When the last status packet was received, kept after the RIO disconnects
+    received: Option<PacketTime>,
 }
 
 impl RecvState {
     pub fn reset(&mut self) {
         self.battery_voltage = 0f32;
+        self.battery_raw = [0; 2];
         self.trace = Trace::empty();
+        self.rio_address = None;
     }
 }
 
 /// All the state associated with TCP communication with the RIO
 pub struct TcpState {
     /// An optional callback that should be notified upon incoming packets being decoded
-    pub tcp_consumer: Option<Box<TcpConsumer>>,
-    /// A channel of packets that should be sent to the roboRIO
-    pending_tcp: Option<UnboundedSender<TcpTag>>,
+    tcp_consumer: Option<Box<TcpConsumer>>,
+    /// Consumers added alongside the main one, each with a flag that is cleared once its handle is dropped
+    tcp_consumers: Vec<(Arc<AtomicBool>, Box<TcpConsumer>)>,
+    /// The queue of packets that should be sent to the roboRIO, closed by the TCP task once it stops
+    pending_tcp: Option<Arc<SharedQueue<TcpTag>>>,
+    /// When the last TCP packet was received, kept after the connection closes
+    received: Option<PacketTime>,
 }
 
 impl TcpState {
     pub const fn new() -> TcpState {
         TcpState {
             tcp_consumer: None,
+            tcp_consumers: Vec::new(),
             pending_tcp: None,
+            received: None,
         }
     }
 
+    /// Queues `tag` to be sent to the roboRIO, which may drop an older tag if the queue is full
     pub fn queue_tcp(&self, tag: TcpTag) -> Result<()> {
-        // pending_tcp is set by the tcp_conn function when it connects.
-        self.pending_tcp
-            .clone()
-            .ok_or_else(|| format_err!("TCP task not spawned."))
-            .and_then(move |tx| tx.send(tag).map_err(|e| e.into()))
-            .map(|_| ())
+        // pending_tcp is set by the tcp_conn function when it connects, and closed however it ends
+        let queue = self.open_queue().ok_or(DsError::NotConnected)?;
+        queue.push(tag);
+        Ok(())
     }
 
-    pub fn set_tcp_tx(&mut self, tx: Option<UnboundedSender<TcpTag>>) {
-        self.pending_tcp = tx;
+    pub fn set_tcp_queue(&mut self, queue: Option<Arc<SharedQueue<TcpTag>>>) {
+        self.pending_tcp = queue;
+    }
+
+    /// Returns how many tags are waiting to be sent over TCP
+    pub fn tcp_queue_len(&self) -> usize {
+        self.open_queue().map_or(0, |queue| queue.len())
+    }
+
+    fn open_queue(&self) -> Option<&Arc<SharedQueue<TcpTag>>> {
+        self.pending_tcp.as_ref().filter(|queue| !queue.is_closed())
     }
 
     pub fn set_tcp_consumer(&mut self, consumer: impl FnMut(TcpPacket) + Send + Sync + 'static) {
         self.tcp_consumer = Some(Box::new(consumer));
     }
+
+    /// Adds a consumer that is called until `active` is cleared
+    pub fn add_tcp_consumer(
+        &mut self,
+        active: Arc<AtomicBool>,
+        consumer: impl FnMut(TcpPacket) + Send + Sync + 'static,
+    ) {
+        self.tcp_consumers.push((active, Box::new(consumer)));
+    }
+
+    #[inline(always)]
+    pub const fn received(&self) -> Option<PacketTime> {
+        self.received
+    }
+
+    /// Passes `packet` to every consumer, dropping those that have been unsubscribed
+    pub fn consume(&mut self, packet: TcpPacket, received: PacketTime) {
+        self.received = Some(received);
+        self.tcp_consumers
+            .retain(|(active, _)| active.load(Ordering::Relaxed));
+        for (_, consumer) in &mut self.tcp_consumers {
+            consumer(packet.clone());
+        }
+        if let Some(ref mut consumer) = self.tcp_consumer {
+            consumer(packet);
+        }
+    }
 }
 
 impl RecvState {
     pub const fn new() -> RecvState {
         RecvState {
             battery_voltage: 0f32,
+            battery_raw: [0; 2],
             trace: Trace::empty(),
+            connected: false,
+            rio_address: None,
+            robot_state: RobotState::Disconnected,
+            received: None,
         }
     }
 
+    #[inline(always)]
+    pub const fn rio_address(&self) -> Option<IpAddr> {
+        self.rio_address
+    }
+
+    pub fn set_rio_address(&mut self, address: Option<IpAddr>) {
+        self.rio_address = address;
+    }
+
+    #[inline(always)]
+    pub const fn connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn set_connected(&mut self, connected: bool) {
+        self.connected = connected;
+    }
+
     #[inline(always)]
     pub const fn battery_voltage(&self) -> f32 {
         self.battery_voltage
@@ -72,6 +152,15 @@ impl RecvState {
         self.battery_voltage = voltage;
     }
 
+    #[inline(always)]
+    pub const fn battery_raw(&self) -> [u8; 2] {
+        self.battery_raw
+    }
+
+    pub fn set_battery_raw(&mut self, raw: [u8; 2]) {
+        self.battery_raw = raw;
+    }
+
     #[inline(always)]
     pub const fn trace(&self) -> Trace {
         self.trace
@@ -80,4 +169,61 @@ impl RecvState {
     pub fn set_trace(&mut self, trace: Trace) {
         self.trace = trace;
     }
+
+    #[inline(always)]
+    pub const fn robot_state(&self) -> RobotState {
+        self.robot_state
+    }
+
+    #[inline(always)]
+    pub const fn received(&self) -> Option<PacketTime> {
+        self.received
+    }
+
+    pub fn set_received(&mut self, received: PacketTime) {
+        self.received = Some(received);
+    }
+
+    /// Records the state the robot is in, returning the previous state if it has changed
+    pub fn set_robot_state(&mut self, state: RobotState) -> Option<RobotState> {
+        let previous = std::mem::replace(&mut self.robot_state, state);
+        (previous != state).then_some(previous)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ds::conn::Clock;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn unsubscribed_consumers_are_dropped() {
+        let mut tcp = TcpState::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = || {
+            let calls = calls.clone();
+            move |_| {
+                calls.fetch_add(1, Ordering::Relaxed);
+            }
+        };
+        let active = Arc::new(AtomicBool::new(true));
+        tcp.add_tcp_consumer(active.clone(), counter());
+        tcp.add_tcp_consumer(active.clone(), counter());
+        tcp.add_tcp_consumer(Arc::new(AtomicBool::new(true)), counter());
+        let received = PacketTime::now(&Clock::new(|| UNIX_EPOCH + Duration::from_millis(1500)));
+
+        tcp.consume(TcpPacket::Dummy, received);
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        active.store(false, Ordering::Relaxed);
+        tcp.consume(TcpPacket::Dummy, received);
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+        assert_eq!(tcp.tcp_consumers.len(), 1);
+        assert_eq!(
+            tcp.received().map(|received| received.timestamp),
+            Some(1500)
+        );
+    }
 }
@@ -1,8 +1,23 @@
-use crate::ds::state::{DsMode, JoystickSupplier};
+use crate::ds::event::{DsEvent, EstopSource, JoystickWarning};
+use crate::ds::now;
+use crate::ds::practice::{MatchPhase, MatchTiming, PracticeMatch};
+use crate::ds::queue::{OverflowPolicy, TagQueue};
+use crate::ds::state::{DsMode, JoystickSupplier, PACKET_PERIOD};
 use crate::proto::udp::outbound::types::{Control, Request};
 use crate::proto::udp::outbound::*;
-use crate::{Alliance, JoystickValue, Joysticks, Mode, Tag, UdpTag};
-use std::f32;
+use crate::util::axis_to_i8;
+use crate::{Alliance, Countdown, DsError, JoystickValue, Joysticks, Mode, Tag, UdpTag};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The number of joystick ports WPILib reads
+pub(crate) const MAX_JOYSTICKS: usize = 6;
+/// The most axes, buttons and POVs WPILib reads from a single joystick
+const MAX_AXES: u8 = 12;
+const MAX_BUTTONS: u8 = 32;
+const MAX_POVS: u8 = 12;
 
 /// State containing all the data relevant to constructing a UDP control packet to the roboRIO
 pub struct SendState {
@@ -14,30 +29,81 @@ pub struct SendState {
     enabled: bool,
     /// Whether the robot is estopped
     estopped: bool,
+    /// Whether the robot is told it is connected to a field management system
+    fms_connected: bool,
+    /// Whether robot code has stopped since the robot was E-stopped
+    code_stopped_since_estop: bool,
+    /// Whether robot code has started again after stopping, allowing the E-stop to be cleared
+    code_restarted_since_estop: bool,
     /// The current alliance of the robot
     pub alliance: Alliance,
+    /// The team number of the robot being driven
+    team_number: u16,
     /// Any UDP tags that are to be sent with the next UDP control packet
-    pending_udp: Vec<UdpTag>,
+    pending_udp: TagQueue<UdpTag>,
     /// An optional source for joystick values that will be encoded and sent with the packet
-    joystick_provider: Option<Box<JoystickSupplier>>,
+    joystick_provider: Option<Arc<JoystickSupplier>>,
+    /// The latest values given for each joystick port, sent when there is no joystick provider
+    joysticks: Vec<Vec<JoystickValue>>,
+    /// How many joystick tags are always sent, with empty slots filling in for missing joysticks
+    joystick_slots: usize,
     /// Pending reboot or code restart requests
     pending_request: Option<Request>,
     dsmode: DsMode,
+    /// The practice match being run, if any
+    practice: Option<PracticeMatch>,
+    /// When the robot should be disabled, if it was enabled for a limited time
+    enabled_until: Option<Instant>,
+    /// When the time remaining given by the application runs out, sent as a Countdown tag with each packet
+    countdown_until: Option<Instant>,
+    /// When the application last showed it was still running
+    watchdog_fed: Option<Instant>,
+    /// When the interlock was last armed, if it hasn't been used since
+    armed_at: Option<Instant>,
+    /// The last values returned by the joystick supplier, reused while it catches up after overrunning
+    last_joysticks: Vec<Vec<JoystickValue>>,
+    /// How many more packets should reuse `last_joysticks` rather than calling the supplier
+    supplier_skips: u32,
+    /// Whether the packets delayed by a slow supplier are sent in a burst, and so should reuse its values
+    catch_up: bool,
+    /// The problems found with the joysticks in the last packet, so each is only reported when it appears
+    joystick_warnings: Vec<(usize, JoystickWarning)>,
+    /// The joystick tags sent with the last packet, in port order
+    sent_joysticks: Vec<Joysticks>,
+    /// Events raised while constructing packets that haven't been emitted yet
+    events: Vec<DsEvent>,
 }
 
 impl SendState {
     #[inline(always)]
-    pub const fn new(alliance: Alliance) -> SendState {
+    pub const fn new(alliance: Alliance, team_number: u16) -> SendState {
         SendState {
             mode: Mode::Autonomous,
             udp_seqnum: 0,
             enabled: false,
             estopped: false,
+            fms_connected: false,
+            code_stopped_since_estop: false,
+            code_restarted_since_estop: false,
             alliance,
-            pending_udp: Vec::new(),
+            team_number,
+            pending_udp: TagQueue::new(16, OverflowPolicy::DropOldest),
             joystick_provider: None,
+            joysticks: Vec::new(),
+            joystick_slots: 0,
             pending_request: None,
             dsmode: DsMode::Normal,
+            practice: None,
+            enabled_until: None,
+            countdown_until: None,
+            watchdog_fed: None,
+            armed_at: None,
+            last_joysticks: Vec::new(),
+            supplier_skips: 0,
+            catch_up: true,
+            joystick_warnings: Vec::new(),
+            sent_joysticks: Vec::new(),
+            events: Vec::new(),
         }
     }
 
@@ -49,70 +115,163 @@ impl SendState {
         self.pending_udp.push(tag);
     }
 
+    pub fn configure_queue(&mut self, capacity: usize, policy: OverflowPolicy) {
+        self.pending_udp.configure(capacity, policy);
+    }
+
+    pub fn pending_udp(&self) -> Vec<UdpTag> {
+        self.pending_udp.iter().cloned().collect()
+    }
+
     #[inline(always)]
-    pub const fn pending_udp(&self) -> &Vec<UdpTag> {
-        &self.pending_udp
+    pub fn udp_queue_len(&self) -> usize {
+        self.pending_udp.len()
+    }
+
+    /// Sets whether packets delayed by a slow joystick supplier are caught up on, rather than dropped
+    pub fn set_catch_up(&mut self, catch_up: bool) {
+        self.catch_up = catch_up;
     }
 
     pub fn set_joystick_supplier(
         &mut self,
         supplier: impl Fn() -> Vec<Vec<JoystickValue>> + Send + Sync + 'static,
     ) {
-        self.joystick_provider = Some(Box::new(supplier))
+        self.joystick_provider = Some(Arc::new(supplier))
+    }
+
+    /// Pads the joysticks sent with each packet to `slots` entries, so that ports keep their index on the roboRIO
+    ///
+    /// Empty slots are sent with every axis and button zeroed, as the official driver station does. At most 6 slots are padded to.
+    pub fn set_joystick_slots(&mut self, slots: usize) {
+        self.joystick_slots = slots.min(MAX_JOYSTICKS);
+    }
+
+    /// Stores the values of the joystick in `port`, to be sent with every packet until they're replaced
+    ///
+    /// Ports below `port` that haven't been given values are sent as empty joysticks.
+    pub fn set_joystick(&mut self, port: usize, values: Vec<JoystickValue>) {
+        if self.joysticks.len() <= port {
+            self.joysticks.resize(port + 1, Vec::new());
+        }
+        self.joysticks[port] = values;
     }
 
     pub fn set_alliance(&mut self, alliance: Alliance) {
         self.alliance = alliance;
     }
 
+    #[inline(always)]
+    pub const fn team_number(&self) -> u16 {
+        self.team_number
+    }
+
+    pub fn set_team_number(&mut self, team_number: u16) {
+        self.team_number = team_number;
+    }
+
+    /// Returns the joystick supplier if the next packet should call it
+    ///
+    /// While the supplier catches up after overrunning, this counts down the packets that reuse its last values and
+    /// returns `None`. The supplier is meant to be called with [`call_supplier`] without holding the lock on this
+    /// state, so a slow supplier doesn't hold up the rest of the API.
+    pub fn due_supplier(&mut self) -> Option<Arc<JoystickSupplier>> {
+        let supplier = self.joystick_provider.as_ref()?;
+        if self.supplier_skips > 0 {
+            // The ticks delayed by an overrun fire back to back, reusing values lets them catch up quickly
+            self.supplier_skips -= 1;
+            return None;
+        }
+        Some(supplier.clone())
+    }
+
+    /// Constructs a control packet from the current state, calling the joystick supplier if it is due
+    ///
+    /// See [`control_with`](#method.control_with).
+    pub fn control(&mut self) -> UdpControlPacket {
+        let supplied = self.due_supplier().map(call_supplier);
+        self.control_with(supplied)
+    }
+
     /// Constructs a control packet from the current state
     ///
-    /// if [self.joystick_provider] is Some, it will be used to construct the joysticks tag
+    /// `supplied` is the result of calling the supplier returned by [`due_supplier`](#method.due_supplier), if any.
+    /// Without it, the supplier's last values are reused, or the values last given for each port are sent if there is no supplier.
     /// if [self.request] is Some, its value will be consumed and sent to the roboRIO
-    pub fn control(&mut self) -> UdpControlPacket {
-        if let Some(ref supplier) = self.joystick_provider {
-            let joysticks = supplier();
-
-            // Joystick tags come one after another, iterate over the outer Vec and queue with each loop
-            for joystick in joysticks {
-                let mut axes = vec![0; 6];
-                let mut buttons = vec![false; 10];
-                let mut povs = vec![-1i16];
-
-                for value in joystick {
-                    // If statements bound check to stop it from crashing
-                    match value {
-                        JoystickValue::Button { id, pressed } => {
-                            if id >= 1 && id <= 10 {
-                                let id = id - 1;
-                                buttons.remove(id as usize);
-                                buttons.insert(id as usize, pressed)
-                            }
-                        }
-                        JoystickValue::Axis { id, value } => {
-                            if id <= 5 {
-                                let value = if (value - 1.0).abs() < f32::EPSILON {
-                                    127i8
-                                } else {
-                                    (value * 128f32) as i8
-                                };
-
-                                axes.remove(id as usize);
-                                axes.insert(id as usize, value);
-                            }
-                        }
-                        JoystickValue::POV { id, angle } => {
-                            if id == 0 {
-                                povs.remove(id as usize);
-                                povs.insert(id as usize, angle);
-                            }
-                        }
-                    }
+    ///
+    /// If the joystick provider panics, it is removed and the robot is disabled.
+    /// If it takes longer than a packet period, its values are reused for the packets it delayed.
+    /// Both are reported through [`take_events`](#method.take_events), along with any joystick values that had to be corrected.
+    pub fn control_with(&mut self, supplied: Option<Supplied>) -> UdpControlPacket {
+        let mut joysticks = match supplied {
+            Some(Supplied {
+                result: Ok(joysticks),
+                elapsed,
+                ..
+            }) => {
+                if elapsed > PACKET_PERIOD {
+                    let skipped = if self.catch_up {
+                        (elapsed.as_nanos() / PACKET_PERIOD.as_nanos()) as u32
+                    } else {
+                        0
+                    };
+                    self.supplier_skips = skipped;
+                    self.events
+                        .push(DsEvent::JoystickSupplierSlow { elapsed, skipped });
+                }
+                self.last_joysticks = joysticks.clone();
+                joysticks
+            }
+            Some(Supplied {
+                supplier,
+                result: Err(payload),
+                ..
+            }) => {
+                // The supplier may have been replaced while it was running, in which case the new one is kept
+                if self
+                    .joystick_provider
+                    .as_ref()
+                    .is_some_and(|current| Arc::ptr_eq(current, &supplier))
+                {
+                    let message = payload
+                        .downcast_ref::<&str>()
+                        .map(|s| s.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "Joystick supplier panicked".to_string());
+                    self.joystick_provider = None;
+                    self.disable();
+                    self.events.push(DsEvent::JoystickSupplierPanicked(message));
                 }
-                self.queue_udp(UdpTag::Joysticks(Joysticks::new(axes, buttons, povs)));
+                Vec::new()
             }
+            None if self.joystick_provider.is_some() => self.last_joysticks.clone(),
+            // Without a supplier, the values last given for each port are sent
+            None => self.joysticks.clone(),
+        };
+        if joysticks.len() < self.joystick_slots {
+            joysticks.resize(self.joystick_slots, Vec::new());
         }
 
+        // Joystick tags come one after another, iterate over the outer Vec and queue with each loop
+        // They're sent straight away rather than queued, so they never crowd out other tags
+        let mut tags: Vec<Box<dyn Tag>> = Vec::new();
+        let mut warnings = Vec::new();
+        self.sent_joysticks.clear();
+        for (port, joystick) in joysticks.iter().enumerate() {
+            let mut joystick_warnings = Vec::new();
+            let tag = encode_joystick(joystick, &mut joystick_warnings);
+            warnings.extend(joystick_warnings.into_iter().map(|warning| (port, warning)));
+            self.sent_joysticks.push(tag.clone());
+            tags.push(Box::new(tag));
+        }
+        for &(port, warning) in &warnings {
+            if !self.joystick_warnings.contains(&(port, warning)) {
+                self.events
+                    .push(DsEvent::InvalidJoystickValue { port, warning });
+            }
+        }
+        self.joystick_warnings = warnings;
+
         let mut control = self.mode.to_control();
 
         if self.enabled {
@@ -123,9 +282,11 @@ impl SendState {
             control |= Control::ESTOP
         }
 
-        let mut tags: Vec<Box<dyn Tag>> = Vec::new();
+        if self.fms_connected {
+            control |= Control::FMS_CONNECTED;
+        }
 
-        for tag in self.pending_udp.clone() {
+        for tag in self.pending_udp.drain() {
             match tag {
                 UdpTag::Timezone(tz) => tags.push(Box::new(tz)),
                 UdpTag::DateTime(dt) => tags.push(Box::new(dt)),
@@ -134,8 +295,6 @@ impl SendState {
             }
         }
 
-        self.pending_udp.clear();
-
         UdpControlPacket {
             seqnum: self.udp_seqnum,
             control,
@@ -145,6 +304,17 @@ impl SendState {
         }
     }
 
+    /// Returns the joystick tags sent with the last control packet, in port order
+    #[inline(always)]
+    pub fn sent_joysticks(&self) -> &[Joysticks] {
+        &self.sent_joysticks
+    }
+
+    /// Returns the events raised while constructing packets or changing the state since this was last called
+    pub fn take_events(&mut self) -> Vec<DsEvent> {
+        std::mem::take(&mut self.events)
+    }
+
     #[inline(always)]
     pub const fn mode(&self) -> Mode {
         self.mode
@@ -171,7 +341,6 @@ impl SendState {
         self.udp_seqnum = 0;
     }
 
-    #[allow(unused)]
     #[inline(always)]
     pub const fn seqnum(&self) -> u16 {
         self.udp_seqnum
@@ -179,10 +348,143 @@ impl SendState {
 
     pub fn enable(&mut self) {
         self.enabled = true;
+        self.enabled_until = None;
+        self.feed_watchdog(now());
+    }
+
+    /// Enables the robot until `deadline`, after which the send task disables it
+    pub fn enable_until(&mut self, deadline: Instant) {
+        self.enabled = true;
+        self.enabled_until = Some(deadline);
+        self.feed_watchdog(now());
+    }
+
+    pub fn arm(&mut self, now: Instant) {
+        self.armed_at = Some(now);
+    }
+
+    /// Uses up the last arm, returning whether it happened no more than `window` before `now`
+    pub fn take_arm(&mut self, now: Instant, window: Duration) -> bool {
+        self.armed_at
+            .take()
+            .is_some_and(|armed| now.saturating_duration_since(armed) <= window)
     }
 
+    /// Returns an error if an `interlock` window is given and the last arm didn't happen within it
+    pub fn check_armed(&mut self, interlock: Option<Duration>, now: Instant) -> crate::Result<()> {
+        match interlock {
+            Some(window) if !self.take_arm(now, window) => Err(DsError::NotArmed),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn feed_watchdog(&mut self, now: Instant) {
+        self.watchdog_fed = Some(now);
+    }
+
+    /// Disables the robot if it is enabled and the watchdog hasn't been fed within `timeout` of `now`
+    ///
+    /// Returns whether the robot was disabled.
+    pub fn expire_watchdog(&mut self, now: Instant, timeout: Duration) -> bool {
+        let starved = self
+            .watchdog_fed
+            .is_none_or(|fed| now.saturating_duration_since(fed) >= timeout);
+        if self.enabled && starved {
+            self.disable();
+            return true;
+        }
+        false
+    }
+
+    /// Disables the robot if it was enabled until a deadline that has passed at `now`
+    pub fn expire_enable(&mut self, now: Instant) {
+        if self.enabled_until.is_some_and(|deadline| now >= deadline) {
+            self.disable();
+        }
+    }
+
+    /// Disables the robot, ending any practice match in progress
+    ///
+    /// Every path that disables the robot comes through here, so ending a match early always queues
+    /// `MatchPhaseChanged(Ended)`.
     pub fn disable(&mut self) {
         self.enabled = false;
+        if self
+            .practice
+            .take()
+            .is_some_and(|practice| practice.phase().is_some())
+        {
+            self.events
+                .push(DsEvent::MatchPhaseChanged(MatchPhase::Ended));
+        }
+        self.enabled_until = None;
+    }
+
+    pub fn start_practice(&mut self, timing: MatchTiming) {
+        self.practice = Some(PracticeMatch::new(timing, now()));
+        self.feed_watchdog(now());
+    }
+
+    /// Returns the phase of the practice match in progress, if any
+    pub fn match_phase(&self) -> Option<MatchPhase> {
+        self.practice.as_ref().and_then(|practice| practice.phase())
+    }
+
+    /// Sequences the practice match in progress, if any, up to `now`
+    ///
+    /// The robot is enabled and disabled as the match moves between phases,
+    /// and a Countdown tag with the time remaining in the current phase is queued. Phase changes are queued as events.
+    pub fn update_practice(&mut self, now: Instant) {
+        let Some(ref mut practice) = self.practice else {
+            return;
+        };
+        let entered = practice.advance(now);
+        let endgame = practice.enter_endgame(now);
+        let (_, remaining) = practice.phase_at(now);
+
+        self.events.extend(entered.map(DsEvent::MatchPhaseChanged));
+        if endgame {
+            self.events.push(DsEvent::Endgame);
+        }
+
+        match entered {
+            Some(MatchPhase::Autonomous) => {
+                self.mode = Mode::Autonomous;
+                self.enabled = true;
+            }
+            Some(MatchPhase::Delay) => self.enabled = false,
+            Some(MatchPhase::Teleop) => {
+                self.mode = Mode::Teleoperated;
+                self.enabled = true;
+            }
+            Some(MatchPhase::Ended) => {
+                // The match has already queued its end, which disabling mustn't queue again
+                self.practice = None;
+                self.disable();
+                return;
+            }
+            None => {}
+        }
+
+        self.queue_udp(UdpTag::Countdown(Countdown::new(remaining.as_secs_f32())));
+    }
+
+    /// Sets when the time remaining reported to robot code runs out, or stops reporting it if `None`
+    pub fn set_countdown(&mut self, until: Option<Instant>) {
+        self.countdown_until = until;
+    }
+
+    /// Queues a Countdown tag with the time remaining at `now`, if one was set
+    ///
+    /// The countdown stops at 0 rather than being cleared, and is left to the practice match while one is in progress.
+    pub fn update_countdown(&mut self, now: Instant) {
+        if self.practice.is_some() {
+            return;
+        }
+        if let Some(until) = self.countdown_until {
+            let remaining = until.saturating_duration_since(now);
+            self.queue_udp(UdpTag::Countdown(Countdown::new(remaining.as_secs_f32())));
+        }
     }
 
     #[inline(always)]
@@ -190,13 +492,455 @@ impl SendState {
         self.enabled
     }
 
-    pub fn estop(&mut self) {
+    /// Disables and E-stops the robot, raising an event with `source` if it wasn't E-stopped already
+    pub fn estop(&mut self, source: EstopSource) {
         self.disable();
-        self.estopped = true;
+        if !self.estopped {
+            self.estopped = true;
+            self.code_stopped_since_estop = false;
+            self.code_restarted_since_estop = false;
+            self.events.push(DsEvent::EStopped(source));
+        }
+    }
+
+    /// Records whether robot code is running, to tell when it has restarted since the robot was E-stopped
+    pub fn observe_code(&mut self, running: bool) {
+        if !self.estopped {
+            return;
+        }
+        if !running {
+            self.code_stopped_since_estop = true;
+        } else if self.code_stopped_since_estop {
+            self.code_restarted_since_estop = true;
+        }
+    }
+
+    /// Clears the E-stop if robot code has restarted since it was raised
+    pub fn clear_estop(&mut self) -> crate::Result<()> {
+        if !self.estopped {
+            return Ok(());
+        }
+        if !self.code_restarted_since_estop {
+            return Err(DsError::EstopLatched);
+        }
+        self.estopped = false;
+        self.events.push(DsEvent::EStopCleared);
+        Ok(())
     }
 
     #[inline(always)]
     pub const fn estopped(&self) -> bool {
         self.estopped
     }
+
+    #[inline(always)]
+    pub const fn fms_connected(&self) -> bool {
+        self.fms_connected
+    }
+
+    pub fn set_fms_connected(&mut self, connected: bool) {
+        self.fms_connected = connected;
+    }
+}
+
+/// The outcome of calling a joystick supplier, to be passed to [`SendState::control_with`]
+pub struct Supplied {
+    supplier: Arc<JoystickSupplier>,
+    result: thread::Result<Vec<Vec<JoystickValue>>>,
+    elapsed: Duration,
+}
+
+/// Calls `supplier`, catching any panic and timing how long it took
+pub fn call_supplier(supplier: Arc<JoystickSupplier>) -> Supplied {
+    let start = Instant::now();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| supplier()));
+    Supplied {
+        supplier,
+        result,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Converts the values of one joystick into its tag, correcting any problems and recording them in `warnings`
+///
+/// The joystick has at least 6 axes, 10 buttons and 1 POV, growing to fit the highest id given up to what WPILib reads.
+fn encode_joystick(values: &[JoystickValue], warnings: &mut Vec<JoystickWarning>) -> Joysticks {
+    let mut axes = vec![0; 6];
+    let mut buttons = vec![false; 10];
+    let mut povs = vec![-1i16];
+    // Bitmasks of the ids already seen, to catch values given twice
+    let (mut seen_axes, mut seen_buttons, mut seen_povs) = (0u32, 0u32, 0u32);
+
+    for &value in values {
+        let duplicate = match value {
+            JoystickValue::Button { id, pressed } => {
+                if !(1..=MAX_BUTTONS).contains(&id) {
+                    warnings.push(JoystickWarning::Unsupported(value));
+                    continue;
+                }
+
+                let id = (id - 1) as usize;
+                if id >= buttons.len() {
+                    buttons.resize(id + 1, false);
+                }
+                buttons[id] = pressed;
+                mark(&mut seen_buttons, id)
+            }
+            JoystickValue::Axis { id, value: raw } => {
+                if id >= MAX_AXES {
+                    warnings.push(JoystickWarning::Unsupported(value));
+                    continue;
+                }
+                if !(-1.0..=1.0).contains(&raw) {
+                    warnings.push(JoystickWarning::OutOfRange(value));
+                }
+
+                let id = id as usize;
+                if id >= axes.len() {
+                    axes.resize(id + 1, 0);
+                }
+                axes[id] = axis_to_i8(raw);
+                mark(&mut seen_axes, id)
+            }
+            JoystickValue::POV { id, angle } => {
+                if id >= MAX_POVS {
+                    warnings.push(JoystickWarning::Unsupported(value));
+                    continue;
+                }
+                let angle = match angle {
+                    -1..360 => angle,
+                    // Full turns are removed, anything else negative is taken to mean not pressed
+                    360.. => {
+                        warnings.push(JoystickWarning::OutOfRange(value));
+                        angle % 360
+                    }
+                    _ => {
+                        warnings.push(JoystickWarning::OutOfRange(value));
+                        -1
+                    }
+                };
+
+                let id = id as usize;
+                if id >= povs.len() {
+                    povs.resize(id + 1, -1);
+                }
+                povs[id] = angle;
+                mark(&mut seen_povs, id)
+            }
+        };
+
+        if duplicate {
+            warnings.push(JoystickWarning::Duplicate(value));
+        }
+    }
+
+    Joysticks::new(axes, buttons, povs)
+}
+
+/// Records `id` in the bitmask `seen`, returning true if it was already there
+fn mark(seen: &mut u32, id: usize) -> bool {
+    let duplicate = *seen & 1 << id != 0;
+    *seen |= 1 << id;
+    duplicate
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn enable_until_expires() {
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        let now = Instant::now();
+
+        send.enable_until(now + Duration::from_secs(1));
+        send.expire_enable(now);
+        assert!(send.enabled());
+        send.expire_enable(now + Duration::from_secs(1));
+        assert!(!send.enabled());
+
+        // A plain enable cancels the deadline
+        send.enable_until(now);
+        send.enable();
+        send.expire_enable(now + Duration::from_secs(1));
+        assert!(send.enabled());
+    }
+
+    #[test]
+    fn countdown_is_queued_until_cleared() {
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        let now = Instant::now();
+
+        send.update_countdown(now);
+        assert_eq!(send.udp_queue_len(), 0);
+
+        send.set_countdown(Some(now + Duration::from_secs(15)));
+        send.update_countdown(now);
+        send.update_countdown(now + Duration::from_secs(20));
+        assert_eq!(send.udp_queue_len(), 2);
+
+        send.set_countdown(None);
+        send.update_countdown(now);
+        assert_eq!(send.udp_queue_len(), 2);
+    }
+
+    #[test]
+    fn estop_clears_only_after_code_restarts() {
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        send.estop(EstopSource::User);
+        send.estop(EstopSource::Robot);
+        assert_eq!(
+            send.take_events(),
+            vec![DsEvent::EStopped(EstopSource::User)]
+        );
+
+        send.observe_code(true);
+        assert!(matches!(send.clear_estop(), Err(DsError::EstopLatched)));
+        send.observe_code(false);
+        assert!(send.clear_estop().is_err());
+        send.observe_code(true);
+        send.clear_estop().unwrap();
+        assert!(!send.estopped());
+        assert!(!send.enabled());
+    }
+
+    #[test]
+    fn ending_practice_is_reported_once() {
+        let ended = |send: &mut SendState| {
+            send.take_events()
+                .into_iter()
+                .filter(|event| *event == DsEvent::MatchPhaseChanged(MatchPhase::Ended))
+                .count()
+        };
+        let ends: [fn(&mut SendState); 3] = [
+            SendState::disable,
+            |send| send.estop(EstopSource::Robot),
+            |send| {
+                send.expire_watchdog(now() + Duration::from_secs(60), Duration::from_secs(1));
+            },
+        ];
+        for end in ends {
+            let mut send = SendState::new(Alliance::new_red(1), 4533);
+            send.start_practice(MatchTiming::default());
+            send.update_practice(now());
+            assert_eq!(ended(&mut send), 0);
+            end(&mut send);
+            assert_eq!(ended(&mut send), 1);
+        }
+
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        send.start_practice(MatchTiming::default());
+        send.update_practice(now() + Duration::from_secs(200));
+        assert_eq!(ended(&mut send), 1);
+        assert_eq!(send.match_phase(), None);
+    }
+
+    #[test]
+    fn fms_connected_bit_is_sent() {
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        assert!(!send.control().control.contains(Control::FMS_CONNECTED));
+        send.set_fms_connected(true);
+        assert!(send.control().control.contains(Control::FMS_CONNECTED));
+    }
+
+    #[test]
+    fn arm_is_used_once() {
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        let window = Duration::from_secs(3);
+        let now = Instant::now();
+
+        assert!(!send.take_arm(now, window));
+        send.arm(now);
+        assert!(send.take_arm(now + window, window));
+        assert!(!send.take_arm(now + window, window));
+        send.arm(now);
+        assert!(!send.take_arm(now + window * 2, window));
+    }
+
+    #[test]
+    fn stored_joysticks_are_sent() {
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        send.set_joystick(
+            1,
+            vec![JoystickValue::Button {
+                id: 1,
+                pressed: true,
+            }],
+        );
+
+        let packet = send.control();
+        assert_eq!(packet.tags.len(), 2);
+        // Stored values are sent again until replaced
+        assert_eq!(send.control().tags.len(), 2);
+    }
+
+    #[test]
+    fn joysticks_grow_to_wpilib_limits() {
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        send.set_joystick(
+            0,
+            vec![
+                JoystickValue::Axis { id: 7, value: 1.0 },
+                JoystickValue::Button {
+                    id: 16,
+                    pressed: true,
+                },
+                // Past the 32 buttons and 12 POVs WPILib reads, so dropped
+                JoystickValue::Button {
+                    id: 40,
+                    pressed: true,
+                },
+                JoystickValue::POV { id: 12, angle: 90 },
+            ],
+        );
+
+        let packet = send.control();
+        let tag = packet.tags[0].construct();
+        // 8 axes, then 16 buttons packed into 2 bytes, then the single default POV
+        assert_eq!(tag[2], 8);
+        assert_eq!(tag[10], 127);
+        assert_eq!(&tag[11..14], &[16, 0x80, 0x00]);
+        assert_eq!(&tag[14..], &[1, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn joysticks_are_padded_to_slots() {
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        send.set_joystick_supplier(|| vec![Vec::new(); 2]);
+        send.set_joystick_slots(4);
+        assert_eq!(send.control().tags.len(), 4);
+
+        // Suppliers returning more joysticks than there are slots aren't cut short
+        send.set_joystick_slots(1);
+        assert_eq!(send.control().tags.len(), 2);
+    }
+
+    #[test]
+    fn invalid_joystick_values_are_reported_once() {
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        let duplicate = JoystickValue::Button {
+            id: 3,
+            pressed: false,
+        };
+        let pov = JoystickValue::POV { id: 0, angle: 450 };
+        send.set_joystick(
+            0,
+            vec![
+                JoystickValue::Button {
+                    id: 3,
+                    pressed: true,
+                },
+                duplicate,
+                pov,
+            ],
+        );
+
+        let tag = send.control().tags[0].construct();
+        // The last value given for a button wins, and whole turns are taken off the POV
+        assert_eq!(&tag[9..12], &[10, 0x00, 0x00]);
+        assert_eq!(&tag[12..], &[1, 0x00, 90]);
+        assert_eq!(
+            send.take_events(),
+            vec![
+                DsEvent::InvalidJoystickValue {
+                    port: 0,
+                    warning: JoystickWarning::Duplicate(duplicate)
+                },
+                DsEvent::InvalidJoystickValue {
+                    port: 0,
+                    warning: JoystickWarning::OutOfRange(pov)
+                },
+            ]
+        );
+
+        send.control();
+        assert!(send.take_events().is_empty());
+    }
+
+    #[test]
+    fn panicking_supplier_disables() {
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        send.set_joystick_supplier(|| panic!("controller unplugged"));
+        send.enable();
+
+        let packet = send.control();
+        assert!(!packet.control.contains(Control::ENABLED));
+        assert!(!send.enabled());
+        assert_eq!(
+            send.take_events(),
+            vec![DsEvent::JoystickSupplierPanicked(
+                "controller unplugged".to_string()
+            )]
+        );
+        // The supplier was removed, so the next packet is built normally
+        send.control();
+        assert!(send.take_events().is_empty());
+    }
+
+    #[test]
+    fn replaced_supplier_survives_old_panic() {
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        send.set_joystick_supplier(|| panic!("controller unplugged"));
+        send.enable();
+
+        // The supplier is replaced while the old one runs outside the lock
+        let supplied = send.due_supplier().map(call_supplier);
+        send.set_joystick_supplier(|| vec![vec![]]);
+        send.control_with(supplied);
+        assert!(send.enabled());
+        assert!(send.take_events().is_empty());
+        assert!(send.due_supplier().is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_supplier_values_are_reused() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        send.set_joystick_supplier({
+            let calls = calls.clone();
+            move || {
+                calls.fetch_add(1, Ordering::SeqCst);
+                vec![vec![]]
+            }
+        });
+
+        // The supplier takes two packet periods to return
+        let supplier = send.due_supplier().unwrap();
+        let start = tokio::time::Instant::now();
+        let result = Ok(supplier());
+        tokio::time::advance(PACKET_PERIOD * 2).await;
+        send.control_with(Some(Supplied {
+            supplier,
+            result,
+            elapsed: start.elapsed(),
+        }));
+        match send.take_events()[..] {
+            [DsEvent::JoystickSupplierSlow { skipped, .. }] => assert_eq!(skipped, 2),
+            ref events => panic!("Unexpected events {:?}", events),
+        }
+
+        // The packets it delayed reuse its values rather than waiting on the supplier again
+        send.control();
+        send.control();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        send.control();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn starved_watchdog_disables() {
+        let mut send = SendState::new(Alliance::new_red(1), 4533);
+        let timeout = Duration::from_millis(500);
+
+        send.enable();
+        let fed = Instant::now();
+        send.feed_watchdog(fed);
+        assert!(!send.expire_watchdog(fed + Duration::from_millis(100), timeout));
+        assert!(send.expire_watchdog(fed + timeout, timeout));
+        assert!(!send.enabled());
+        // Nothing to do once disabled
+        assert!(!send.expire_watchdog(fed + timeout * 2, timeout));
+    }
 }
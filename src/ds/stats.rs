@@ -0,0 +1,309 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::ds::PACKET_PERIOD;
+
+/// How far back packet rates are measured over
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+/// Seqnum gaps larger than this are taken as the roboRIO restarting its count, rather than lost packets
+const MAX_GAP: u16 = 1000;
+/// How many sent packets are remembered to match with their responses, limiting trip times measured to about a second
+const MAX_IN_FLIGHT: usize = 64;
+
+/// How closely the control packet loop is keeping to its 20ms schedule
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TickStats {
+    /// Control packets sent per second, measured over the last second
+    ///
+    /// This should be 50. It is lower while the host application stalls the packet loop, and can briefly be higher while
+    /// delayed packets are caught up on.
+    pub rate: f64,
+    /// How many packets have been sent more than a full period after they were due
+    pub late: u64,
+    /// The furthest behind schedule a packet has been sent
+    pub max_lateness: Duration,
+}
+
+/// Statistics about the control packets exchanged with the roboRIO, as graphed by the official driver station
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NetStats {
+    /// Control packets sent per second, measured over the last second
+    pub sent_rate: f64,
+    /// Status packets received from the roboRIO per second, measured over the last second
+    pub recv_rate: f64,
+    /// The smoothed variation in the time between status packets, which arrive every 20ms on a healthy network
+    ///
+    /// This is calculated as for RTP, see RFC 3550 section 6.4.1.
+    pub jitter: Duration,
+    /// How many status packets have been received
+    pub received: u64,
+    /// How many status packets were lost, going by the gaps in their sequence numbers
+    pub lost: u64,
+    /// The smoothed time between sending a control packet and receiving the roboRIO's response to it
+    ///
+    /// This is `None` until a response has been matched to its packet since the roboRIO connected.
+    pub trip_time: Option<Duration>,
+}
+
+impl NetStats {
+    /// The fraction of status packets that were lost, from 0.0 to 1.0
+    pub fn loss(&self) -> f64 {
+        let expected = self.received + self.lost;
+        if expected == 0 {
+            0.0
+        } else {
+            self.lost as f64 / expected as f64
+        }
+    }
+}
+
+/// The times of the packets seen within the last second
+#[derive(Debug, Default)]
+struct RateWindow(VecDeque<Instant>);
+
+impl RateWindow {
+    fn push(&mut self, now: Instant) {
+        self.0.push_back(now);
+        while self
+            .0
+            .front()
+            .is_some_and(|&seen| now.duration_since(seen) >= RATE_WINDOW)
+        {
+            self.0.pop_front();
+        }
+    }
+
+    fn rate(&self, now: Instant) -> f64 {
+        // Old packets are only pruned as new ones are seen, so they're skipped here in case none have been for a while
+        let recent = self
+            .0
+            .iter()
+            .filter(|&&seen| now.duration_since(seen) < RATE_WINDOW)
+            .count();
+        recent as f64 / RATE_WINDOW.as_secs_f64()
+    }
+}
+
+/// Records when control packets are sent, to measure the rate they're achieving
+#[derive(Debug)]
+pub(crate) struct TickTracker {
+    sent: RateWindow,
+    /// The seqnums of the latest packets and when they were sent, newest last
+    in_flight: VecDeque<(u16, Instant)>,
+    late: u64,
+    max_lateness: Duration,
+}
+
+impl TickTracker {
+    pub fn new() -> TickTracker {
+        TickTracker {
+            sent: RateWindow::default(),
+            in_flight: VecDeque::with_capacity(MAX_IN_FLIGHT),
+            late: 0,
+            max_lateness: Duration::ZERO,
+        }
+    }
+
+    /// Records the packet with `seqnum` sent at `now` for the tick that was due at `due`
+    pub fn tick(&mut self, due: Instant, now: Instant, seqnum: u16) {
+        let lateness = now.saturating_duration_since(due);
+        if lateness > PACKET_PERIOD {
+            self.late += 1;
+        }
+        self.max_lateness = self.max_lateness.max(lateness);
+        self.sent.push(now);
+
+        if self.in_flight.len() == MAX_IN_FLIGHT {
+            self.in_flight.pop_front();
+        }
+        self.in_flight.push_back((seqnum, now));
+    }
+
+    /// Returns when the packet with `seqnum` was sent, if it was recent enough to be remembered
+    pub fn sent_at(&self, seqnum: u16) -> Option<Instant> {
+        // The newest packet wins, in case the seqnum was reset since an older one was sent
+        self.in_flight
+            .iter()
+            .rev()
+            .find(|&&(sent, _)| sent == seqnum)
+            .map(|&(_, at)| at)
+    }
+
+    pub fn stats(&self, now: Instant) -> TickStats {
+        TickStats {
+            rate: self.sent.rate(now),
+            late: self.late,
+            max_lateness: self.max_lateness,
+        }
+    }
+}
+
+/// Records the status packets received from the roboRIO, to measure their rate, jitter and loss
+#[derive(Debug)]
+pub(crate) struct RecvTracker {
+    received: RateWindow,
+    /// The seqnum and arrival time of the last packet
+    last: Option<(u16, Instant)>,
+    /// The seqnum echoed by the latest packet, even one that arrived out of order
+    echoed: Option<u16>,
+    /// The jitter in seconds, kept as a float so that small changes aren't rounded away
+    jitter: f64,
+    /// The smoothed trip time in seconds, if any responses have been matched
+    trip_time: Option<f64>,
+    count: u64,
+    lost: u64,
+}
+
+impl RecvTracker {
+    pub fn new() -> RecvTracker {
+        RecvTracker {
+            received: RateWindow::default(),
+            last: None,
+            echoed: None,
+            jitter: 0.0,
+            trip_time: None,
+            count: 0,
+            lost: 0,
+        }
+    }
+
+    /// Records a status packet with `seqnum` arriving at `now`, in response to a control packet sent at `sent`
+    pub fn packet(&mut self, seqnum: u16, now: Instant, sent: Option<Instant>) {
+        self.received.push(now);
+        self.count += 1;
+        self.echoed = Some(seqnum);
+
+        // Duplicated and reordered packets have a gap of 0 or near u16::MAX, and are left out
+        if let Some((last_seqnum, last_time)) = self.last {
+            let gap = seqnum.wrapping_sub(last_seqnum);
+            if !(1..=MAX_GAP).contains(&gap) {
+                // Any other jump means the seqnums were restarted, and is counted from afresh
+                if (MAX_GAP..=u16::MAX - MAX_GAP).contains(&gap) {
+                    self.last = Some((seqnum, now));
+                }
+                return;
+            }
+            self.lost += u64::from(gap - 1);
+
+            // Packets are compared against the spacing expected for the seqnums between them, so losses aren't jitter
+            let expected = PACKET_PERIOD.as_secs_f64() * f64::from(gap);
+            let deviation = (now.duration_since(last_time).as_secs_f64() - expected).abs();
+            self.jitter += (deviation - self.jitter) / 16.0;
+        }
+        self.last = Some((seqnum, now));
+
+        // Smoothed as TCP smooths its round trip time, see RFC 6298
+        if let Some(sent) = sent {
+            let trip_time = now.saturating_duration_since(sent).as_secs_f64();
+            let smoothed = self.trip_time.get_or_insert(trip_time);
+            *smoothed += (trip_time - *smoothed) / 8.0;
+        }
+    }
+
+    /// Forgets the last packet, so the gap to the first packet after a reconnect isn't counted as losses
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.trip_time = None;
+    }
+
+    /// Returns the seqnum echoed by the latest status packet, if any have been received
+    pub const fn echoed(&self) -> Option<u16> {
+        self.echoed
+    }
+
+    /// Returns the smoothed round trip time, if any responses have been matched since the roboRIO connected
+    pub fn trip_time(&self) -> Option<Duration> {
+        self.trip_time.map(Duration::from_secs_f64)
+    }
+
+    pub fn stats(&self, sent: &TickTracker, now: Instant) -> NetStats {
+        NetStats {
+            sent_rate: sent.sent.rate(now),
+            recv_rate: self.received.rate(now),
+            jitter: Duration::from_secs_f64(self.jitter),
+            received: self.count,
+            lost: self.lost,
+            trip_time: self.trip_time.map(Duration::from_secs_f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rate_covers_the_last_second() {
+        let start = Instant::now();
+        let mut tracker = TickTracker::new();
+        for i in 0..100 {
+            let due = start + PACKET_PERIOD * i;
+            tracker.tick(due, due, i as u16);
+        }
+        let now = start + PACKET_PERIOD * 99;
+        assert_eq!(tracker.stats(now).rate, 50.0);
+        assert_eq!(tracker.stats(now).late, 0);
+
+        // A stall delays one tick by three periods, and the rate falls off while nothing is sent
+        let due = now + PACKET_PERIOD;
+        tracker.tick(due, due + PACKET_PERIOD * 3, 100);
+        let stats = tracker.stats(due + PACKET_PERIOD * 3);
+        assert_eq!(stats.late, 1);
+        assert_eq!(stats.max_lateness, PACKET_PERIOD * 3);
+        assert!(stats.rate < 50.0);
+        assert_eq!(tracker.stats(due + Duration::from_secs(5)).rate, 0.0);
+    }
+
+    #[test]
+    fn seqnum_gaps_are_losses() {
+        let start = Instant::now();
+        let mut tracker = RecvTracker::new();
+        // Packets 3 and 4 are lost, then 5 arrives twice
+        for seqnum in [0u16, 1, 2, 5, 5, 6] {
+            tracker.packet(seqnum, start + PACKET_PERIOD * u32::from(seqnum), None);
+        }
+        let stats = tracker.stats(&TickTracker::new(), start + PACKET_PERIOD * 6);
+        assert_eq!(stats.received, 6);
+        assert_eq!(stats.lost, 2);
+        assert_eq!(stats.loss(), 0.25);
+        assert!(stats.jitter < Duration::from_micros(1));
+
+        // Wrapping around isn't a loss, and neither is the gap across a reconnect
+        let mut tracker = RecvTracker::new();
+        tracker.packet(u16::MAX, start, None);
+        tracker.packet(0, start + PACKET_PERIOD, None);
+        tracker.reset();
+        tracker.packet(500, start + PACKET_PERIOD * 2, None);
+        assert_eq!(tracker.stats(&TickTracker::new(), start).lost, 0);
+
+        // Restarted seqnums are neither losses nor reordered packets
+        tracker.packet(30_000, start + PACKET_PERIOD * 3, None);
+        tracker.packet(3, start + PACKET_PERIOD * 4, None);
+        tracker.packet(5, start + PACKET_PERIOD * 6, None);
+        assert_eq!(tracker.stats(&TickTracker::new(), start).lost, 1);
+        assert_eq!(tracker.echoed(), Some(5));
+    }
+
+    #[test]
+    fn responses_are_matched_to_packets() {
+        let start = Instant::now();
+        let mut ticks = TickTracker::new();
+        let mut arrivals = RecvTracker::new();
+        for seqnum in 0..100u16 {
+            let sent = start + PACKET_PERIOD * u32::from(seqnum);
+            ticks.tick(sent, sent, seqnum);
+        }
+        // Only the latest packets are remembered
+        assert_eq!(ticks.sent_at(0), None);
+
+        let sent = ticks.sent_at(99).unwrap();
+        arrivals.packet(99, sent + Duration::from_millis(8), Some(sent));
+        let stats = arrivals.stats(&ticks, sent);
+        assert_eq!(stats.trip_time, Some(Duration::from_millis(8)));
+
+        arrivals.reset();
+        assert_eq!(arrivals.stats(&ticks, sent).trip_time, None);
+    }
+}
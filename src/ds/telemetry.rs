@@ -0,0 +1,198 @@
+use super::mode::Mode;
+#[cfg(feature = "runtime")]
+use super::state::DsState;
+use super::stats::{NetStats, TickStats};
+use crate::proto::udp::inbound::types::Trace;
+
+/// A snapshot of the state of the driver station and the robot it is connected to
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Telemetry {
+    /// When the snapshot was taken, in milliseconds since the Unix epoch by the driver station's configured clock
+    pub timestamp: u64,
+    /// Whether the roboRIO is responding to control packets
+    pub connected: bool,
+    /// Whether the TCP connection to the roboRIO is open
+    pub tcp_connected: bool,
+    /// Whether the robot is being enabled
+    pub enabled: bool,
+    /// Whether the robot has been E-stopped
+    pub estopped: bool,
+    /// The mode the robot is being told to be in
+    pub mode: Mode,
+    /// The last Trace received from the robot
+    pub trace: Trace,
+    /// The last battery voltage received from the robot
+    pub battery_voltage: f32,
+    /// When the last status packet was received, in milliseconds since the Unix epoch
+    pub udp_received: Option<u64>,
+    /// When the last TCP packet was received, in milliseconds since the Unix epoch
+    pub tcp_received: Option<u64>,
+    pub net: NetStats,
+    pub ticks: TickStats,
+}
+
+#[cfg(feature = "runtime")]
+impl Telemetry {
+    pub(crate) async fn capture(state: &DsState) -> Telemetry {
+        let (enabled, estopped, mode) = {
+            let send = state.send().read().await;
+            (send.enabled(), send.estopped(), send.mode())
+        };
+        let (connected, trace, battery_voltage, udp_received) = {
+            let recv = state.recv().read().await;
+            (
+                recv.connected(),
+                recv.trace(),
+                recv.battery_voltage(),
+                recv.received(),
+            )
+        };
+        let tcp_received = state.tcp().read().await.received();
+        let now = super::now();
+        let ticks = state.ticks();
+        Telemetry {
+            timestamp: state.config().clock.millis(),
+            connected,
+            tcp_connected: state.tcp_connected(),
+            enabled,
+            estopped,
+            mode,
+            trace,
+            battery_voltage,
+            udp_received: udp_received.map(|received| received.timestamp),
+            tcp_received: tcp_received.map(|received| received.timestamp),
+            net: state.arrivals().stats(&ticks, now),
+            ticks: ticks.stats(now),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+pub(crate) mod json {
+    use std::fmt;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::io::{AsyncWrite, AsyncWriteExt};
+    use tokio::sync::mpsc;
+    use tokio::time::MissedTickBehavior;
+
+    use super::Telemetry;
+    use crate::ds::conn::TaskGuard;
+    use crate::ds::state::DsState;
+
+    /// Keeps a telemetry export started by [`DriverStation::export_json`](struct.DriverStation.html#method.export_json)
+    /// running, stopping it when dropped
+    #[must_use = "the export is stopped as soon as its handle is dropped"]
+    pub struct TelemetryExport {
+        _task: TaskGuard<()>,
+    }
+
+    impl fmt::Debug for TelemetryExport {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("TelemetryExport").finish_non_exhaustive()
+        }
+    }
+
+    /// Serializes a snapshot of `state` as a line of JSON
+    async fn line(state: &DsState) -> String {
+        let mut line = serde_json::to_string(&Telemetry::capture(state).await)
+            .expect("telemetry is always serializable");
+        line.push('\n');
+        line
+    }
+
+    fn interval(period: Duration) -> tokio::time::Interval {
+        let mut interval = tokio::time::interval(period);
+        // A slow writer shouldn't be followed by a burst of stale snapshots
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        interval
+    }
+
+    pub(crate) fn to_writer<W>(
+        state: Arc<DsState>,
+        mut writer: W,
+        period: Duration,
+    ) -> TelemetryExport
+    where
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let task = TaskGuard::spawn_on(&state.runtime(), async move {
+            let mut interval = interval(period);
+            loop {
+                interval.tick().await;
+                let line = line(&state).await;
+                if writer.write_all(line.as_bytes()).await.is_err() || writer.flush().await.is_err()
+                {
+                    warn!("Telemetry export stopped, the writer failed");
+                    break;
+                }
+            }
+        });
+        TelemetryExport { _task: task }
+    }
+
+    pub(crate) fn to_channel(
+        state: Arc<DsState>,
+        tx: mpsc::Sender<String>,
+        period: Duration,
+    ) -> TelemetryExport {
+        let task = TaskGuard::spawn_on(&state.runtime(), async move {
+            let mut interval = interval(period);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        if tx.send(line(&state).await).await.is_err() {
+                            break;
+                        }
+                    }
+                    _ = tx.closed() => break,
+                }
+            }
+        });
+        TelemetryExport { _task: task }
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn snapshots_are_sent_as_json_lines() {
+        let state = Arc::new(DsState::test_default());
+        let (tx, mut rx) = mpsc::channel(1);
+        let export = json::to_channel(state, tx, Duration::from_millis(10));
+
+        let line = rx.recv().await.unwrap();
+        assert!(line.ends_with('\n'));
+        let telemetry: Telemetry = serde_json::from_str(&line).unwrap();
+        assert!(!telemetry.connected);
+        assert!(!telemetry.enabled);
+
+        // Dropping the handle stops the export, closing the channel
+        drop(export);
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_timed_by_the_configured_clock() {
+        use crate::Alliance;
+        use crate::ds::builder::DsConfig;
+        use crate::ds::conn::Clock;
+        use std::time::UNIX_EPOCH;
+
+        let config = DsConfig {
+            clock: Clock::new(|| UNIX_EPOCH + Duration::from_secs(1_000)),
+            ..DsConfig::default()
+        };
+        let state = DsState::new(Alliance::new_red(1), 4533, config);
+        assert_eq!(Telemetry::capture(&state).await.timestamp, 1_000_000);
+    }
+}
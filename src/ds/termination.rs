@@ -0,0 +1,68 @@
+use std::io;
+use std::sync::Arc;
+
+use super::PACKET_PERIOD;
+use super::state::DsState;
+use crate::Result;
+
+/// What is done to the robot when the process is asked to terminate
+///
+/// See [`DriverStationBuilder::stop_on_termination`](struct.DriverStationBuilder.html#method.stop_on_termination).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TerminationAction {
+    Disable,
+    /// E-stops the robot, which then can't be enabled until its code is restarted
+    Estop,
+}
+
+/// How many control packets are sent after the robot is stopped, in case the first are lost
+const FINAL_PACKETS: u32 = 3;
+
+/// tokio task stopping the robot and exiting the process once it is asked to terminate
+pub(crate) async fn stop_on_termination(
+    state: Arc<DsState>,
+    action: TerminationAction,
+) -> Result<()> {
+    let code = termination().await?;
+    info!("Terminating, stopping the robot first");
+
+    match action {
+        TerminationAction::Disable => state.disable().await,
+        TerminationAction::Estop => state.estop().await,
+    }
+    tokio::time::sleep(PACKET_PERIOD * FINAL_PACKETS).await;
+
+    std::process::exit(code)
+}
+
+/// Waits for SIGINT or SIGTERM, returning the exit code conventionally used for the signal
+#[cfg(unix)]
+async fn termination() -> io::Result<i32> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    Ok(tokio::select! {
+        _ = interrupt.recv() => 130,
+        _ = terminate.recv() => 143,
+    })
+}
+
+/// Waits for Ctrl-C, Ctrl-Break, or the console being closed
+#[cfg(windows)]
+async fn termination() -> io::Result<i32> {
+    use tokio::signal::windows;
+
+    let mut ctrl_c = windows::ctrl_c()?;
+    let mut ctrl_break = windows::ctrl_break()?;
+    let mut ctrl_close = windows::ctrl_close()?;
+    let mut ctrl_shutdown = windows::ctrl_shutdown()?;
+    tokio::select! {
+        _ = ctrl_c.recv() => {}
+        _ = ctrl_break.recv() => {}
+        _ = ctrl_close.recv() => {}
+        _ = ctrl_shutdown.recv() => {}
+    }
+    // STATUS_CONTROL_C_EXIT, as when the default handler terminates the process
+    Ok(0xC000013Au32 as i32)
+}
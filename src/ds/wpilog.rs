@@ -0,0 +1,313 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio::time::MissedTickBehavior;
+
+use super::conn::TaskGuard;
+use super::state::{DsState, Mode};
+use super::telemetry::Telemetry;
+use crate::Result;
+
+/// The version of the format that is written, 1.0
+const VERSION: u16 = 0x0100;
+
+/// The entries that telemetry is written to, with their types, in the order of their IDs from 1
+const ENTRIES: [(&str, &str); 16] = [
+    // Written by DataLogManager on the robot too, which AdvantageScope uses to line logs up
+    ("systemTime", "int64"),
+    ("DS/Connected", "boolean"),
+    ("DS/TcpConnected", "boolean"),
+    ("DS/Enabled", "boolean"),
+    ("DS/Estopped", "boolean"),
+    ("DS/Mode", "string"),
+    ("DS/Trace", "int64"),
+    ("DS/BatteryVoltage", "double"),
+    ("DS/SentRate", "double"),
+    ("DS/RecvRate", "double"),
+    ("DS/JitterMs", "double"),
+    ("DS/Received", "int64"),
+    ("DS/Lost", "int64"),
+    ("DS/TripTimeMs", "double"),
+    ("DS/TickRate", "double"),
+    ("DS/LateTicks", "int64"),
+];
+
+/// Describes where and how often telemetry is logged by
+/// [`DriverStation::log_wpilog`](struct.DriverStation.html#method.log_wpilog)
+///
+/// Each session is written to its own file in WPILib's `.wpilog` data log format, which AdvantageScope and the other
+/// WPILib log tools can open. Each field of a [`Telemetry`](struct.Telemetry.html) snapshot is an entry under `DS/`,
+/// written only when it changes.
+///
+/// Records are timestamped in microseconds since the Unix epoch, by the same clock the roboRIO's date is synchronized
+/// to. The current time is also written to the `systemTime` entry, as DataLogManager does on the robot, so the two
+/// logs can be lined up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WpiLog {
+    /// The directory log files are created in, which is created if it doesn't exist
+    pub dir: PathBuf,
+    /// How often telemetry is checked for changes
+    pub period: Duration,
+}
+
+impl WpiLog {
+    /// Logs to `dir` every 20ms, the rate control packets are sent at
+    pub fn new(dir: impl Into<PathBuf>) -> WpiLog {
+        WpiLog {
+            dir: dir.into(),
+            period: Duration::from_millis(20),
+        }
+    }
+}
+
+/// Keeps a logger started by [`DriverStation::log_wpilog`](struct.DriverStation.html#method.log_wpilog) running,
+/// stopping it when dropped
+#[must_use = "logging is stopped as soon as its handle is dropped"]
+pub struct WpiLogHandle {
+    path: PathBuf,
+    _task: TaskGuard<()>,
+}
+
+impl WpiLogHandle {
+    /// Returns the path of the session's log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl fmt::Debug for WpiLogHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WpiLogHandle")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The value of an entry
+#[derive(Debug, PartialEq)]
+enum Value {
+    Boolean(bool),
+    Int64(i64),
+    Double(f64),
+    String(&'static str),
+}
+
+impl Value {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match *self {
+            Value::Boolean(value) => buf.push(value as u8),
+            Value::Int64(value) => buf.extend_from_slice(&value.to_le_bytes()),
+            Value::Double(value) => buf.extend_from_slice(&value.to_le_bytes()),
+            Value::String(value) => buf.extend_from_slice(value.as_bytes()),
+        }
+    }
+}
+
+/// Returns the values of each entry after `systemTime`, in the order of `ENTRIES`
+///
+/// The trip time is `None` until it has been measured, and its entry isn't written until then.
+fn values(telemetry: &Telemetry) -> [Option<Value>; ENTRIES.len() - 1] {
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let mode = match telemetry.mode {
+        Mode::Autonomous => "autonomous",
+        Mode::Teleoperated => "teleoperated",
+        Mode::Test => "test",
+    };
+    [
+        Some(Value::Boolean(telemetry.connected)),
+        Some(Value::Boolean(telemetry.tcp_connected)),
+        Some(Value::Boolean(telemetry.enabled)),
+        Some(Value::Boolean(telemetry.estopped)),
+        Some(Value::String(mode)),
+        Some(Value::Int64(telemetry.trace.bits().into())),
+        Some(Value::Double(telemetry.battery_voltage.into())),
+        Some(Value::Double(telemetry.net.sent_rate)),
+        Some(Value::Double(telemetry.net.recv_rate)),
+        Some(Value::Double(millis(telemetry.net.jitter))),
+        Some(Value::Int64(telemetry.net.received as i64)),
+        Some(Value::Int64(telemetry.net.lost as i64)),
+        telemetry
+            .net
+            .trip_time
+            .map(|trip| Value::Double(millis(trip))),
+        Some(Value::Double(telemetry.ticks.rate)),
+        Some(Value::Int64(telemetry.ticks.late as i64)),
+    ]
+}
+
+/// Returns how many bytes are needed to hold `value`, with at least one
+fn width(value: u64) -> usize {
+    (8 - value.leading_zeros() as usize / 8).max(1)
+}
+
+/// Encodes the records of a log, keeping the last value written to each entry
+struct Encoder {
+    last: [Option<Value>; ENTRIES.len() - 1],
+}
+
+impl Encoder {
+    /// Starts a log, with its header and the control records starting each entry
+    fn start(timestamp: u64, buf: &mut Vec<u8>) -> Encoder {
+        buf.extend_from_slice(b"WPILOG");
+        buf.extend_from_slice(&VERSION.to_le_bytes());
+        let extra = concat!("ds-rs ", env!("CARGO_PKG_VERSION"));
+        buf.extend_from_slice(&(extra.len() as u32).to_le_bytes());
+        buf.extend_from_slice(extra.as_bytes());
+
+        for (id, (name, ty)) in (1u32..).zip(ENTRIES) {
+            let mut payload = vec![0];
+            payload.extend_from_slice(&id.to_le_bytes());
+            for field in [name, ty, ""] {
+                payload.extend_from_slice(&(field.len() as u32).to_le_bytes());
+                payload.extend_from_slice(field.as_bytes());
+            }
+            record(buf, 0, timestamp, &payload);
+        }
+        Encoder {
+            last: Default::default(),
+        }
+    }
+
+    /// Writes `telemetry` at `timestamp`, skipping the entries whose values haven't changed
+    fn write(&mut self, timestamp: u64, telemetry: &Telemetry, buf: &mut Vec<u8>) {
+        let mut payload = Vec::with_capacity(16);
+        Value::Int64(timestamp as i64).encode(&mut payload);
+        record(buf, 1, timestamp, &payload);
+
+        for ((id, last), value) in (2u32..).zip(&mut self.last).zip(values(telemetry)) {
+            if let Some(value) = value
+                && last.as_ref() != Some(&value)
+            {
+                payload.clear();
+                value.encode(&mut payload);
+                record(buf, id, timestamp, &payload);
+                *last = Some(value);
+            }
+        }
+    }
+}
+
+/// Writes a record of `payload` to `entry`, with each field of its header as narrow as it can be
+fn record(buf: &mut Vec<u8>, entry: u32, timestamp: u64, payload: &[u8]) {
+    let (entry_width, size_width) = (width(entry.into()), width(payload.len() as u64));
+    let timestamp_width = width(timestamp);
+    buf.push(
+        (entry_width - 1) as u8
+            | ((size_width - 1) as u8) << 2
+            | ((timestamp_width - 1) as u8) << 4,
+    );
+    buf.extend_from_slice(&entry.to_le_bytes()[..entry_width]);
+    buf.extend_from_slice(&(payload.len() as u32).to_le_bytes()[..size_width]);
+    buf.extend_from_slice(&timestamp.to_le_bytes()[..timestamp_width]);
+    buf.extend_from_slice(payload);
+}
+
+/// Returns `time` in microseconds since the Unix epoch
+fn micros(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_micros() as u64)
+}
+
+pub(crate) async fn start(state: Arc<DsState>, log: WpiLog) -> Result<WpiLogHandle> {
+    fs::create_dir_all(&log.dir).await?;
+    let name = chrono::Utc::now()
+        .format("ds-%Y%m%d-%H%M%S.wpilog")
+        .to_string();
+    let path = log.dir.join(name);
+    let mut file = File::create(&path).await?;
+    let mut buf = Vec::new();
+    let mut encoder = Encoder::start(micros(state.config().clock.now()), &mut buf);
+    file.write_all(&buf).await?;
+
+    let task = TaskGuard::spawn(async move {
+        let mut interval = tokio::time::interval(log.period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            let telemetry = Telemetry::capture(&state).await;
+            buf.clear();
+            encoder.write(micros(state.config().clock.now()), &telemetry, &mut buf);
+            let written = async {
+                file.write_all(&buf).await?;
+                file.flush().await
+            };
+            if let Err(e) = written.await {
+                warn!("wpilog logging stopped: {}", e);
+                break;
+            }
+        }
+    });
+    Ok(WpiLogHandle { path, _task: task })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Splits the records after the header into their entry, timestamp and payload
+    fn records(mut bytes: &[u8]) -> Vec<(u32, u64, Vec<u8>)> {
+        let read = |bytes: &mut &[u8], width: usize| {
+            let mut value = [0; 8];
+            value[..width].copy_from_slice(&bytes[..width]);
+            *bytes = &bytes[width..];
+            u64::from_le_bytes(value)
+        };
+        let mut records = Vec::new();
+        while let Some((&header, rest)) = bytes.split_first() {
+            bytes = rest;
+            let entry = read(&mut bytes, (header & 0x3) as usize + 1) as u32;
+            let size = read(&mut bytes, ((header >> 2) & 0x3) as usize + 1) as usize;
+            let timestamp = read(&mut bytes, ((header >> 4) & 0x7) as usize + 1);
+            records.push((entry, timestamp, bytes[..size].to_vec()));
+            bytes = &bytes[size..];
+        }
+        records
+    }
+
+    #[tokio::test]
+    async fn only_changes_are_written() {
+        let state = DsState::test_default();
+        let mut telemetry = Telemetry::capture(&state).await;
+        let mut buf = Vec::new();
+        let mut encoder = Encoder::start(1_000, &mut buf);
+
+        let extra = concat!("ds-rs ", env!("CARGO_PKG_VERSION"));
+        assert_eq!(&buf[..6], b"WPILOG");
+        assert_eq!(&buf[6..8], &[0x00, 0x01]);
+        let header = 12 + extra.len();
+        let starts = records(&buf[header..]);
+        assert_eq!(starts.len(), ENTRIES.len());
+        assert!(
+            starts
+                .iter()
+                .all(|(entry, ts, _)| *entry == 0 && *ts == 1_000)
+        );
+        let name = &starts[7].2;
+        assert_eq!(name[0], 0);
+        assert_eq!(&name[1..5], &8u32.to_le_bytes());
+        assert_eq!(&name[9..26], b"DS/BatteryVoltage");
+
+        buf.clear();
+        encoder.write(2_000, &telemetry, &mut buf);
+        // Every entry but the trip time, which hasn't been measured
+        assert_eq!(records(&buf).len(), ENTRIES.len() - 1);
+
+        telemetry.battery_voltage = 12.5;
+        buf.clear();
+        encoder.write(0x1_0000_0000, &telemetry, &mut buf);
+        let written = records(&buf);
+        assert_eq!(written.len(), 2);
+        assert_eq!(
+            written[0],
+            (1, 0x1_0000_0000, 0x1_0000_0000i64.to_le_bytes().to_vec())
+        );
+        assert_eq!(
+            written[1],
+            (8, 0x1_0000_0000, 12.5f64.to_le_bytes().to_vec())
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[cfg(feature = "runtime")]
+use tokio::sync::mpsc::error::SendError;
+
+/// The error type for all fallible operations in this crate
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DsError {
+    /// An I/O error occurred on one of the sockets used to communicate with the roboRIO
+    Io(io::Error),
+    /// The given port couldn't be bound because another socket is using it
+    ///
+    /// This is usually the official driver station, or another instance of this one.
+    PortInUse(u16),
+    /// A packet received from the roboRIO was malformed
+    Decode(String),
+    /// The provided game data was rejected before being sent
+    InvalidGameData(String),
+    /// The team number is too large to be mapped to a roboRIO address
+    InvalidTeamNumber(u16),
+    /// The given string doesn't name an alliance station, such as "Red 1"
+    InvalidAlliance(String),
+    /// The operation requires a connection to the roboRIO that isn't currently established
+    NotConnected,
+    /// The robot can't be enabled because the interlock wasn't armed
+    NotArmed,
+    /// The E-stop can't be cleared because robot code hasn't restarted since the robot was E-stopped
+    EstopLatched,
+    /// One of the background tasks managing the connection has stopped
+    ChannelClosed,
+    /// An input backend couldn't read from its devices
+    Input(String),
+    /// A session recording couldn't be written to its database
+    Database(String),
+}
+
+impl fmt::Display for DsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DsError::Io(e) => write!(f, "I/O error: {}", e),
+            DsError::PortInUse(port) => write!(
+                f,
+                "Port {} is already in use, is the official driver station or another instance running?",
+                port
+            ),
+            DsError::Decode(reason) => write!(f, "Failed to decode packet: {}", reason),
+            DsError::InvalidGameData(reason) => write!(f, "Invalid game data: {}", reason),
+            DsError::InvalidTeamNumber(team) => write!(f, "Invalid team number: {}", team),
+            DsError::InvalidAlliance(station) => {
+                write!(f, "Invalid alliance station: {:?}", station)
+            }
+            DsError::NotConnected => write!(f, "Not connected to the roboRIO"),
+            DsError::NotArmed => write!(f, "The driver station must be armed before enabling"),
+            DsError::EstopLatched => write!(
+                f,
+                "Robot code must restart before the E-stop can be cleared"
+            ),
+            DsError::ChannelClosed => write!(f, "Connection task is no longer running"),
+            DsError::Input(reason) => write!(f, "Input backend failed: {}", reason),
+            DsError::Database(reason) => write!(f, "Database error: {}", reason),
+        }
+    }
+}
+
+impl Error for DsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DsError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DsError {
+    fn from(e: io::Error) -> Self {
+        DsError::Io(e)
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl<T> From<SendError<T>> for DsError {
+    fn from(_: SendError<T>) -> Self {
+        DsError::ChannelClosed
+    }
+}
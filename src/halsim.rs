@@ -0,0 +1,283 @@
+//! Driving a desktop simulation over WPILib's HALSim WebSocket extension
+//!
+//! Robot programs simulated with the `halsim_ws_server` extension serve their HAL on a WebSocket, by default at
+//! `ws://localhost:3300/wpilibws`. With
+//! [`DriverStationBuilder::halsim`](../struct.DriverStationBuilder.html#method.halsim), the driver station connects to
+//! it and sets the simulated driver station state and joysticks directly, each time a control packet is sent. The
+//! outputs and rumble that robot code sets for each joystick are read back, reaching
+//! [`DsMonitor::joystick_outputs`](../struct.DsMonitor.html#method.joystick_outputs) as they would from a roboRIO.
+//!
+//! Only the values that have changed since they were last sent are sent again. The simulation's own driver station
+//! state isn't read back, as the driver station is what sets it.
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use tokio::net::TcpStream;
+use tokio::time::MissedTickBehavior;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::ds::PACKET_PERIOD;
+use crate::ds::state::{DsState, MAX_JOYSTICKS};
+use crate::{DsError, JoystickOutput, Joysticks, Mode, Result};
+
+/// The port that the HALSim WebSocket extension serves on by default
+pub const HALSIM_PORT: u16 = 3300;
+
+/// The path that the HALSim WebSocket extension serves on
+const PATH: &str = "/wpilibws";
+
+/// How long to wait before connecting again, once the simulation hasn't been found or the connection has closed
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Builds the message setting the simulated driver station to the state that would be sent to the roboRIO
+async fn driver_station(state: &DsState) -> Value {
+    let send = state.send().read().await;
+    let alliance = send.alliance;
+    let color = if alliance.is_red() { "red" } else { "blue" };
+    json!({
+        "type": "DriverStation",
+        "device": "",
+        "data": {
+            ">new_data": true,
+            ">ds": true,
+            ">enabled": send.enabled(),
+            ">autonomous": send.mode() == Mode::Autonomous,
+            ">test": send.mode() == Mode::Test,
+            ">estop": send.estopped(),
+            ">fms": send.fms_connected(),
+            ">station": format!("{}{}", color, alliance.position()),
+        },
+    })
+}
+
+/// Builds the message setting the simulated joystick in `port` to the values of `joystick`
+fn joystick(port: usize, joystick: &Joysticks) -> Value {
+    // The inverse of util::axis_to_i8
+    let axes: Vec<f64> = joystick
+        .axes()
+        .iter()
+        .map(|&axis| f64::from(axis) / if axis < 0 { 128.0 } else { 127.0 })
+        .collect();
+    json!({
+        "type": "Joystick",
+        "device": port.to_string(),
+        "data": {
+            ">axes": axes,
+            ">buttons": joystick.buttons(),
+            ">povs": joystick.povs(),
+        },
+    })
+}
+
+/// Applies the outputs and rumble in a Joystick message from the simulation to `outputs`, returning whether it changed
+fn apply_outputs(message: &Value, outputs: &mut Vec<JoystickOutput>) -> bool {
+    if message["type"] != "Joystick" {
+        return false;
+    }
+    let Some(port) = message["device"]
+        .as_str()
+        .and_then(|device| device.parse::<usize>().ok())
+        .filter(|&port| port < MAX_JOYSTICKS)
+    else {
+        return false;
+    };
+    let data = &message["data"];
+    let rumble = |key: &str| {
+        data[key]
+            .as_f64()
+            .map(|rumble| (rumble.clamp(0.0, 1.0) * f64::from(u16::MAX)).round() as u16)
+    };
+    let (bits, left, right) = (
+        data["<outputs"].as_i64(),
+        rumble("<rumble_left"),
+        rumble("<rumble_right"),
+    );
+    if bits.is_none() && left.is_none() && right.is_none() {
+        return false;
+    }
+
+    if outputs.len() <= port {
+        outputs.resize(port + 1, JoystickOutput::default());
+    }
+    let output = &mut outputs[port];
+    let previous = *output;
+    if let Some(bits) = bits {
+        output.outputs = bits as u32;
+    }
+    if let Some(left) = left {
+        output.left_rumble = left;
+    }
+    if let Some(right) = right {
+        output.right_rumble = right;
+    }
+    *output != previous
+}
+
+/// Sends the state of the driver station to the simulation each packet period, until the connection closes
+async fn session(state: &DsState, stream: TcpStream, addr: SocketAddr) -> Result<()> {
+    let url = format!("ws://{}{}", addr, PATH);
+    let (mut socket, _) = tokio_tungstenite::client_async(url, stream)
+        .await
+        .map_err(|e| DsError::Io(io::Error::other(e)))?;
+    info!("Connected to HALSim at {}", addr);
+
+    // The last message sent for the driver station, then each joystick port
+    let mut sent: Vec<Option<Value>> = Vec::new();
+    let mut interval = tokio::time::interval(PACKET_PERIOD);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let mut messages = vec![driver_station(state).await];
+                messages.extend(
+                    state.send().read().await.sent_joysticks().iter().enumerate().map(|(port, tag)| joystick(port, tag)),
+                );
+                if sent.len() < messages.len() {
+                    sent.resize(messages.len(), None);
+                }
+                for (message, last) in messages.into_iter().zip(&mut sent) {
+                    if last.as_ref() != Some(&message) {
+                        let text = message.to_string();
+                        *last = Some(message);
+                        if socket.send(Message::Text(text)).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+            message = socket.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let Ok(message) = serde_json::from_str::<Value>(&text) else {
+                        debug!("Ignoring malformed HALSim message: {}", text);
+                        continue;
+                    };
+                    state
+                        .joystick_outputs()
+                        .send_if_modified(|outputs| apply_outputs(&message, outputs));
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    debug!("HALSim connection to {} failed: {}", addr, e);
+                    return Ok(());
+                }
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+/// tokio task driving the simulation at `addr`, reconnecting whenever it is lost
+pub(crate) async fn halsim_conn(state: Arc<DsState>, addr: SocketAddr) -> Result<()> {
+    loop {
+        match TcpStream::connect(addr).await {
+            Ok(stream) => {
+                if let Err(e) = session(&state, stream, addr).await {
+                    debug!("Couldn't connect to HALSim at {}: {}", addr, e);
+                } else {
+                    info!("HALSim connection to {} closed", addr);
+                }
+                // Nothing is left to stop the controllers rumbling once the simulation has gone
+                state.joystick_outputs().send_replace(Vec::new());
+            }
+            Err(e) => debug!("Couldn't connect to HALSim at {}: {}", addr, e),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ds::TaskGuard;
+    use crate::ds::builder::DsConfig;
+    use crate::{Alliance, JoystickValue};
+    use std::net::Ipv4Addr;
+    use tokio::net::TcpListener;
+
+    async fn next<S>(socket: &mut S) -> Value
+    where
+        S: futures_util::Stream<Item = tokio_tungstenite::tungstenite::Result<Message>> + Unpin,
+    {
+        match socket.next().await {
+            Some(Ok(Message::Text(text))) => serde_json::from_str(&text).unwrap(),
+            other => panic!("Unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn outputs_are_read_from_joystick_messages() {
+        let mut outputs = Vec::new();
+        let message = json!({
+            "type": "Joystick",
+            "device": "1",
+            "data": { "<outputs": 5, "<rumble_left": 1.0 },
+        });
+        assert!(apply_outputs(&message, &mut outputs));
+        assert_eq!(
+            outputs,
+            [
+                JoystickOutput::default(),
+                JoystickOutput {
+                    outputs: 5,
+                    left_rumble: u16::MAX,
+                    right_rumble: 0,
+                },
+            ]
+        );
+        assert!(!apply_outputs(&message, &mut outputs));
+
+        let other = json!({ "type": "DriverStation", "device": "", "data": { ">enabled": true } });
+        assert!(!apply_outputs(&other, &mut outputs));
+
+        // Only the ports the roboRIO has are kept
+        let out_of_range =
+            json!({ "type": "Joystick", "device": "4000000000", "data": { "<outputs": 1 } });
+        assert!(!apply_outputs(&out_of_range, &mut outputs));
+        assert_eq!(outputs.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn state_is_sent_to_the_simulation() {
+        let state = Arc::new(DsState::new(
+            Alliance::new_blue(2),
+            4533,
+            DsConfig::default(),
+        ));
+        {
+            let mut send = state.send().write().await;
+            send.set_joystick(0, vec![JoystickValue::Axis { id: 0, value: -1.0 }]);
+            let _ = send.control();
+        }
+        let mut outputs = state.joystick_outputs().subscribe();
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _task = TaskGuard::spawn(halsim_conn(state.clone(), addr));
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let ds = next(&mut socket).await;
+        assert_eq!(ds["type"], "DriverStation");
+        assert_eq!(ds["data"][">enabled"], false);
+        assert_eq!(ds["data"][">station"], "blue2");
+        let stick = next(&mut socket).await;
+        assert_eq!(stick["device"], "0");
+        assert_eq!(stick["data"][">axes"][0], -1.0);
+
+        state.send().write().await.enable();
+        let ds = next(&mut socket).await;
+        assert_eq!(ds["data"][">enabled"], true);
+
+        let rumble = json!({ "type": "Joystick", "device": "0", "data": { "<rumble_right": 0.5 } });
+        socket
+            .send(Message::Text(rumble.to_string()))
+            .await
+            .unwrap();
+        outputs.changed().await.unwrap();
+        assert_eq!(outputs.borrow()[0].right_rumble, 32768);
+    }
+}
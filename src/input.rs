@@ -0,0 +1,114 @@
+//! Backends that read input devices and provide their values to a [`DriverStation`](../struct.DriverStation.html)
+//!
+//! Each backend is enabled by the cargo feature of the same name, except for [`KeyboardJoystick`](struct.KeyboardJoystick.html),
+//! which is fed key events by the application and is always available. The `hotkeys` feature also provides
+//! [`SafetyHotkeys`](struct.SafetyHotkeys.html), which binds the official driver station's E-stop and disable keys.
+
+mod axis;
+#[cfg(feature = "gilrs")]
+mod gilrs;
+#[cfg(feature = "hotkeys")]
+mod hotkeys;
+mod keyboard;
+mod manager;
+#[cfg(feature = "sdl2")]
+mod sdl2;
+
+pub use self::axis::AxisFilter;
+#[cfg(feature = "gilrs")]
+pub use self::gilrs::GilrsBackend;
+#[cfg(feature = "hotkeys")]
+pub use self::hotkeys::SafetyHotkeys;
+pub use self::keyboard::{Key, KeyboardJoystick};
+pub use self::manager::{JoystickEvent, JoystickManager, JoystickSlot};
+#[cfg(feature = "sdl2")]
+pub use self::sdl2::Sdl2Backend;
+
+use std::sync::{Arc, Mutex};
+
+#[cfg(any(feature = "gilrs", feature = "sdl2"))]
+use tokio::sync::watch;
+
+#[cfg(any(feature = "gilrs", feature = "sdl2"))]
+use crate::JoystickOutput;
+use crate::JoystickValue;
+
+/// The latest joystick values read by a backend, shared with the joystick supplier given to the driver station
+#[derive(Clone, Default)]
+pub(crate) struct SharedJoysticks(Arc<Mutex<Vec<Vec<JoystickValue>>>>);
+
+impl SharedJoysticks {
+    pub fn set(&self, joysticks: Vec<Vec<JoystickValue>>) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = joysticks;
+    }
+
+    pub fn get(&self) -> Vec<Vec<JoystickValue>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Returns a joystick supplier that yields the latest values
+    pub fn supplier(&self) -> impl Fn() -> Vec<Vec<JoystickValue>> + Send + Sync + 'static {
+        let joysticks = self.clone();
+        move || joysticks.get()
+    }
+}
+
+/// The joystick outputs of the driver station a backend is attached to, polled by the backend's thread
+#[cfg(any(feature = "gilrs", feature = "sdl2"))]
+#[derive(Clone, Default)]
+pub(crate) struct SharedOutputs(Arc<Mutex<Option<watch::Receiver<Vec<JoystickOutput>>>>>);
+
+#[cfg(any(feature = "gilrs", feature = "sdl2"))]
+impl SharedOutputs {
+    /// Starts following the outputs received by a driver station, replacing any it was following before
+    pub fn attach(&self, mut outputs: watch::Receiver<Vec<JoystickOutput>>) {
+        // The outputs already received should be applied too, not just those that arrive later
+        outputs.mark_changed();
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = Some(outputs);
+    }
+
+    /// Returns the latest outputs if they have changed since this was last called
+    pub fn changed(&self) -> Option<Vec<JoystickOutput>> {
+        let mut outputs = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let outputs = outputs.as_mut()?;
+        match outputs.has_changed() {
+            Ok(true) => Some(outputs.borrow_and_update().clone()),
+            // A driver station that has shut down leaves nothing rumbling
+            Err(_) => Some(Vec::new()),
+            Ok(false) => None,
+        }
+    }
+}
+
+/// Converts the pressed directions of a D-pad into a POV angle in degrees, or -1 if it is centered
+pub(crate) fn pov_angle(up: bool, right: bool, down: bool, left: bool) -> i16 {
+    let x = right as i8 - left as i8;
+    let y = up as i8 - down as i8;
+    match (x, y) {
+        (0, 1) => 0,
+        (1, 1) => 45,
+        (1, 0) => 90,
+        (1, -1) => 135,
+        (0, -1) => 180,
+        (-1, -1) => 225,
+        (-1, 0) => 270,
+        (-1, 1) => 315,
+        _ => -1,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pov_angles() {
+        assert_eq!(pov_angle(false, false, false, false), -1);
+        assert_eq!(pov_angle(true, false, false, false), 0);
+        assert_eq!(pov_angle(true, true, false, false), 45);
+        assert_eq!(pov_angle(false, false, true, true), 225);
+        // Opposing directions cancel out
+        assert_eq!(pov_angle(true, false, true, false), -1);
+        assert_eq!(pov_angle(true, true, false, true), 0);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::JoystickValue;
+
+/// Shapes the raw value of an axis before it is sent to the roboRIO
+///
+/// Axes are quantized to a byte when they're sent, so shaping them in robot code loses precision near the center of
+/// the stick. Filters are applied in a fixed order: trigger mapping, inversion, deadzone, then the response curve.
+///
+/// ```
+/// use ds::input::AxisFilter;
+///
+/// let filter = AxisFilter::new().deadzone(0.1).curve(2.0);
+/// assert_eq!(filter.apply(0.05), 0.0);
+/// assert_eq!(filter.apply(-1.0), -1.0);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AxisFilter {
+    deadzone: f32,
+    exponent: f32,
+    inverted: bool,
+    trigger: bool,
+}
+
+impl AxisFilter {
+    /// Creates a filter that passes values through unchanged
+    pub const fn new() -> AxisFilter {
+        AxisFilter {
+            deadzone: 0.0,
+            exponent: 1.0,
+            inverted: false,
+            trigger: false,
+        }
+    }
+
+    /// Zeroes values closer to the center than `threshold`, rescaling the rest so the output still starts from 0
+    pub const fn deadzone(mut self, threshold: f32) -> AxisFilter {
+        self.deadzone = threshold.clamp(0.0, 0.99);
+        self
+    }
+
+    /// Raises the magnitude of values to `exponent`, keeping their sign
+    ///
+    /// Exponents above 1 give finer control near the center of the stick.
+    pub const fn curve(mut self, exponent: f32) -> AxisFilter {
+        self.exponent = exponent;
+        self
+    }
+
+    /// Flips the direction of the axis
+    pub const fn inverted(mut self) -> AxisFilter {
+        self.inverted = !self.inverted;
+        self
+    }
+
+    /// Maps an axis that rests at -1.0, as some drivers report triggers, onto `0.0..=1.0`
+    pub const fn trigger(mut self) -> AxisFilter {
+        self.trigger = true;
+        self
+    }
+
+    /// Returns `value` with the filter applied
+    pub fn apply(&self, value: f32) -> f32 {
+        let mut value = value.clamp(-1.0, 1.0);
+        if self.trigger {
+            value = (value + 1.0) / 2.0;
+        }
+        if self.inverted {
+            value = -value;
+        }
+
+        let magnitude = value.abs();
+        if magnitude < self.deadzone {
+            return 0.0;
+        }
+        let magnitude = (magnitude - self.deadzone) / (1.0 - self.deadzone);
+
+        magnitude.powf(self.exponent).copysign(value)
+    }
+
+    /// Applies the filter to the axis `id` of a joystick, leaving its other values alone
+    pub fn apply_to(&self, joystick: &mut [JoystickValue], id: u8) {
+        for value in joystick {
+            if let JoystickValue::Axis { id: axis, value } = value
+                && *axis == id
+            {
+                *value = self.apply(*value);
+            }
+        }
+    }
+}
+
+impl Default for AxisFilter {
+    fn default() -> Self {
+        AxisFilter::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filters_compose() {
+        let filter = AxisFilter::new().deadzone(0.5).curve(2.0).inverted();
+        assert_eq!(filter.apply(0.4), 0.0);
+        assert_eq!(filter.apply(0.75), -0.25);
+        assert_eq!(filter.apply(-1.5), 1.0);
+
+        let trigger = AxisFilter::new().trigger();
+        assert_eq!(trigger.apply(-1.0), 0.0);
+        assert_eq!(trigger.apply(1.0), 1.0);
+
+        let mut joystick = vec![
+            JoystickValue::Axis { id: 0, value: 0.75 },
+            JoystickValue::Axis { id: 1, value: 0.75 },
+        ];
+        filter.apply_to(&mut joystick, 1);
+        assert_eq!(joystick[0], JoystickValue::Axis { id: 0, value: 0.75 });
+        assert_eq!(
+            joystick[1],
+            JoystickValue::Axis {
+                id: 1,
+                value: -0.25
+            }
+        );
+    }
+}
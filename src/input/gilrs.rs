@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use gilrs::ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder};
+use gilrs::{Axis, Button, Event, EventType, Gamepad, GamepadId, Gilrs};
+
+use super::{JoystickManager, SharedJoysticks, SharedOutputs, pov_angle};
+use crate::{DriverStation, DsError, JoystickOutput, JoystickValue, Result};
+
+/// How long the backend thread waits for gamepad events before checking whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The buttons of a gamepad in the order WPILib numbers them for an Xbox controller
+const BUTTONS: [Button; 10] = [
+    Button::South,
+    Button::East,
+    Button::West,
+    Button::North,
+    Button::LeftTrigger,
+    Button::RightTrigger,
+    Button::Select,
+    Button::Start,
+    Button::LeftThumb,
+    Button::RightThumb,
+];
+
+/// Reads gamepads with [gilrs](https://docs.rs/gilrs), assigning each to a joystick slot as it connects
+///
+/// Gamepads are read on a background thread, and mapped onto the layout WPILib expects of an Xbox controller.
+/// Slots are assigned by a [`JoystickManager`](struct.JoystickManager.html), so gamepads keep their slot across reconnects.
+/// Once attached, rumble set by robot code is played on gamepads that support force feedback.
+///
+/// ```no_run
+/// # async fn run(ds: &mut ds::DriverStation) -> ds::Result<()> {
+/// let gamepads = ds::input::GilrsBackend::spawn()?;
+/// gamepads.attach(ds).await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct GilrsBackend {
+    joysticks: SharedJoysticks,
+    manager: JoystickManager,
+    outputs: SharedOutputs,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl GilrsBackend {
+    /// Starts reading gamepads on a background thread
+    ///
+    /// Returns an error if gamepads can't be read on this system.
+    pub fn spawn() -> Result<GilrsBackend> {
+        let joysticks = SharedJoysticks::default();
+        let manager = JoystickManager::new();
+        let outputs = SharedOutputs::default();
+        let running = Arc::new(AtomicBool::new(true));
+        let (init_tx, init_rx) = mpsc::channel();
+
+        // Gilrs isn't Send on every platform, so it has to be created on the thread that uses it
+        let thread = {
+            let joysticks = joysticks.clone();
+            let manager = manager.clone();
+            let outputs = outputs.clone();
+            let running = running.clone();
+            thread::spawn(move || match Gilrs::new() {
+                Ok(gilrs) => {
+                    let _ = init_tx.send(Ok(()));
+                    run(gilrs, joysticks, manager, outputs, running);
+                }
+                Err(e) => {
+                    let _ = init_tx.send(Err(DsError::Input(e.to_string())));
+                }
+            })
+        };
+
+        init_rx
+            .recv()
+            .map_err(|_| DsError::Input("Gamepad thread stopped unexpectedly".to_string()))??;
+
+        Ok(GilrsBackend {
+            joysticks,
+            manager,
+            outputs,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns the manager assigning gamepads to joystick slots
+    pub fn manager(&self) -> &JoystickManager {
+        &self.manager
+    }
+
+    /// Returns a joystick supplier that yields the latest gamepad values
+    pub fn supplier(&self) -> impl Fn() -> Vec<Vec<JoystickValue>> + Send + Sync + 'static {
+        self.joysticks.supplier()
+    }
+
+    /// Sends the values of the gamepads to the roboRIO with every control packet from `ds`, and plays the rumble
+    /// robot code sets on them
+    pub async fn attach(&self, ds: &mut DriverStation) {
+        ds.set_joystick_supplier(self.supplier()).await;
+        self.outputs.attach(ds.joystick_outputs());
+    }
+}
+
+impl Drop for GilrsBackend {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Reads gamepads until `running` is cleared, publishing their values to `joysticks`
+fn run(
+    mut gilrs: Gilrs,
+    joysticks: SharedJoysticks,
+    manager: JoystickManager,
+    outputs: SharedOutputs,
+    running: Arc<AtomicBool>,
+) {
+    let mut gamepads: HashMap<usize, GamepadId> = HashMap::new();
+    let mut latest: Vec<JoystickOutput> = Vec::new();
+    // The rumble playing on each gamepad, which stops when its effect is dropped
+    let mut rumbles: HashMap<usize, (JoystickOutput, Effect)> = HashMap::new();
+    let connected: Vec<GamepadId> = gilrs.gamepads().map(|(id, _)| id).collect();
+    for id in connected {
+        connect(&gilrs, &manager, &mut gamepads, id);
+    }
+
+    while running.load(Ordering::Relaxed) {
+        let mut next = gilrs.next_event_blocking(Some(POLL_INTERVAL));
+        while let Some(Event { id, event, .. }) = next {
+            match event {
+                EventType::Connected => connect(&gilrs, &manager, &mut gamepads, id),
+                EventType::Disconnected => {
+                    gamepads.remove(&usize::from(id));
+                    rumbles.remove(&usize::from(id));
+                    manager.disconnect(usize::from(id));
+                }
+                _ => {}
+            }
+            next = gilrs.next_event();
+        }
+
+        if let Some(outputs) = outputs.changed() {
+            latest = outputs;
+        }
+        let instances = manager.instances();
+        for (slot, instance) in instances.iter().enumerate() {
+            if let Some(&id) = instance.and_then(|instance| gamepads.get(&instance)) {
+                let output = latest.get(slot).copied().unwrap_or_default();
+                rumble(&mut gilrs, &mut rumbles, id, output);
+            }
+        }
+
+        joysticks.set(
+            instances
+                .into_iter()
+                .map(|instance| {
+                    instance
+                        .and_then(|instance| gamepads.get(&instance))
+                        .and_then(|&id| gilrs.connected_gamepad(id))
+                        .map(|gamepad| read(&gamepad))
+                        .unwrap_or_default()
+                })
+                .collect(),
+        );
+    }
+}
+
+/// Gives the gamepad `id` a slot, identifying it across reconnects by its model
+fn connect(
+    gilrs: &Gilrs,
+    manager: &JoystickManager,
+    gamepads: &mut HashMap<usize, GamepadId>,
+    id: GamepadId,
+) {
+    let gamepad = gilrs.gamepad(id);
+    let uuid: String = gamepad.uuid().iter().map(|b| format!("{b:02x}")).collect();
+    manager.connect(usize::from(id), &uuid, gamepad.name());
+    gamepads.insert(usize::from(id), id);
+}
+
+/// Plays the rumble in `output` on the gamepad `id`, if it isn't already
+fn rumble(
+    gilrs: &mut Gilrs,
+    rumbles: &mut HashMap<usize, (JoystickOutput, Effect)>,
+    id: GamepadId,
+    output: JoystickOutput,
+) {
+    let current = rumbles.get(&usize::from(id)).map(|(current, _)| *current);
+    let idle = output.left_rumble == 0 && output.right_rumble == 0;
+    if current.unwrap_or_default() == output || !gilrs.gamepad(id).is_ff_supported() {
+        return;
+    }
+    if idle {
+        rumbles.remove(&usize::from(id));
+        return;
+    }
+
+    // Effects can't be changed once created, so each new strength replaces the last effect
+    let motor = |kind| BaseEffect {
+        kind,
+        ..Default::default()
+    };
+    let effect = EffectBuilder::new()
+        .add_effect(motor(BaseEffectType::Strong {
+            magnitude: output.left_rumble,
+        }))
+        .add_effect(motor(BaseEffectType::Weak {
+            magnitude: output.right_rumble,
+        }))
+        .gamepads(&[id])
+        .finish(gilrs);
+    if let Ok(effect) = effect
+        && effect.play().is_ok()
+    {
+        rumbles.insert(usize::from(id), (output, effect));
+    }
+}
+
+/// Reads the current state of `gamepad` in the layout WPILib expects of an Xbox controller
+fn read(gamepad: &Gamepad<'_>) -> Vec<JoystickValue> {
+    // Analog triggers are reported as buttons by some drivers, and as axes by others
+    let trigger = |button, axis| {
+        gamepad
+            .button_data(button)
+            .map(|data| data.value())
+            .unwrap_or_else(|| gamepad.value(axis).max(0.0))
+    };
+    let axes = [
+        gamepad.value(Axis::LeftStickX),
+        // WPILib considers pushing a stick forward to be negative
+        -gamepad.value(Axis::LeftStickY),
+        trigger(Button::LeftTrigger2, Axis::LeftZ),
+        trigger(Button::RightTrigger2, Axis::RightZ),
+        gamepad.value(Axis::RightStickX),
+        -gamepad.value(Axis::RightStickY),
+    ];
+
+    let mut values: Vec<JoystickValue> = axes
+        .iter()
+        .enumerate()
+        .map(|(id, &value)| JoystickValue::Axis {
+            id: id as u8,
+            value,
+        })
+        .collect();
+    values.extend(
+        BUTTONS
+            .iter()
+            .enumerate()
+            .map(|(id, &button)| JoystickValue::Button {
+                id: id as u8 + 1,
+                pressed: gamepad.is_pressed(button),
+            }),
+    );
+    values.push(JoystickValue::POV {
+        id: 0,
+        angle: pov_angle(
+            gamepad.is_pressed(Button::DPadUp),
+            gamepad.is_pressed(Button::DPadRight),
+            gamepad.is_pressed(Button::DPadDown),
+            gamepad.is_pressed(Button::DPadLeft),
+        ),
+    });
+    values
+}
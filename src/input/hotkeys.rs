@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use global_hotkey::hotkey::{Code, HotKey};
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use tokio::runtime::Handle;
+
+use crate::ds::state::DsState;
+use crate::{DriverStation, DsError, Result};
+
+/// How long the hotkey thread waits for key presses before checking whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Registers the safety bindings of the official driver station as system wide hotkeys
+///
+/// Like the official driver station, Space E-stops the robot and Enter disables it, even while another application
+/// is focused. The bindings are released when this is dropped.
+///
+/// Key presses are read from [global-hotkey](https://docs.rs/global-hotkey)'s shared event channel, so this shouldn't
+/// be combined with other hotkeys registered through it by the same application. On macOS, it must be created on the
+/// main thread, which has to run an event loop for hotkeys to be delivered.
+///
+/// ```no_run
+/// # async fn run(ds: &ds::DriverStation) -> ds::Result<()> {
+/// let _hotkeys = ds::input::SafetyHotkeys::register(ds)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SafetyHotkeys {
+    manager: GlobalHotKeyManager,
+    hotkeys: [HotKey; 2],
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SafetyHotkeys {
+    /// Registers the hotkeys, applying them to `ds`
+    ///
+    /// Returns an error if the hotkeys couldn't be registered, such as when another application already has them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime, unless the driver station was built with a
+    /// [runtime handle](../struct.DriverStationBuilder.html#method.runtime).
+    pub fn register(ds: &DriverStation) -> Result<SafetyHotkeys> {
+        let estop = HotKey::new(None, Code::Space);
+        let disable = HotKey::new(None, Code::Enter);
+
+        let manager = GlobalHotKeyManager::new().map_err(|e| DsError::Input(e.to_string()))?;
+        manager
+            .register_all(&[estop, disable])
+            .map_err(|e| DsError::Input(e.to_string()))?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let state = ds.state().clone();
+            let handle = state.runtime();
+            let running = running.clone();
+            thread::spawn(move || run(state, handle, estop.id(), disable.id(), running))
+        };
+
+        Ok(SafetyHotkeys {
+            manager,
+            hotkeys: [estop, disable],
+            running,
+            thread: Some(thread),
+        })
+    }
+}
+
+fn run(state: Arc<DsState>, handle: Handle, estop: u32, disable: u32, running: Arc<AtomicBool>) {
+    let events = GlobalHotKeyEvent::receiver();
+    while running.load(Ordering::Relaxed) {
+        let Ok(event) = events.recv_timeout(POLL_INTERVAL) else {
+            continue;
+        };
+        if event.state() != HotKeyState::Pressed {
+            continue;
+        }
+
+        if event.id() == estop {
+            warn!("E-stopped by hotkey");
+            state.estop_now();
+        } else if event.id() == disable {
+            handle.block_on(state.disable());
+        }
+    }
+}
+
+impl Drop for SafetyHotkeys {
+    fn drop(&mut self) {
+        let _ = self.manager.unregister_all(&self.hotkeys);
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+
+use super::pov_angle;
+use crate::JoystickValue;
+
+/// A key that can drive a [`KeyboardJoystick`]
+///
+/// Letters are matched without regard to case.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Key {
+    fn normalize(self) -> Key {
+        match self {
+            Key::Char(c) => Key::Char(c.to_ascii_lowercase()),
+            key => key,
+        }
+    }
+}
+
+/// A virtual joystick driven by the keyboard, laid out like the keyboard joystick of the WPILib simulation GUI
+///
+/// W and S move axis 1 and A and D move axis 0, with W pushing the stick forward to -1.0 as on a real joystick.
+/// The arrow keys drive POV 0, and the button keys (Z, X, C and V unless configured otherwise) are buttons 1 onwards.
+///
+/// The joystick doesn't read the keyboard itself, so key events from whatever windowing or terminal library is in use
+/// should be passed to [`set`](#method.set).
+///
+/// ```
+/// use ds::input::{Key, KeyboardJoystick};
+///
+/// let mut keyboard = KeyboardJoystick::new();
+/// keyboard.set(Key::Char('w'), true);
+/// keyboard.set(Key::Up, true);
+/// let values = keyboard.values();
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyboardJoystick {
+    buttons: Vec<Key>,
+    pressed: HashSet<Key>,
+}
+
+impl KeyboardJoystick {
+    /// Creates a keyboard joystick with Z, X, C and V as buttons 1 through 4
+    pub fn new() -> KeyboardJoystick {
+        KeyboardJoystick::with_buttons(['z', 'x', 'c', 'v'].map(Key::Char))
+    }
+
+    /// Creates a keyboard joystick with the given keys as buttons, numbered from 1 in order
+    pub fn with_buttons(buttons: impl IntoIterator<Item = Key>) -> KeyboardJoystick {
+        KeyboardJoystick {
+            buttons: buttons.into_iter().map(Key::normalize).collect(),
+            pressed: HashSet::new(),
+        }
+    }
+
+    /// Records whether `key` is currently held down
+    pub fn set(&mut self, key: Key, pressed: bool) {
+        let key = key.normalize();
+        if pressed {
+            self.pressed.insert(key);
+        } else {
+            self.pressed.remove(&key);
+        }
+    }
+
+    /// Releases every key, such as when the window reading the keyboard loses focus
+    pub fn clear(&mut self) {
+        self.pressed.clear();
+    }
+
+    /// Returns the values of the joystick for the keys currently held
+    pub fn values(&self) -> Vec<JoystickValue> {
+        let held = |key| self.pressed.contains(&key) as i8 as f32;
+        let axis =
+            |negative: char, positive: char| held(Key::Char(positive)) - held(Key::Char(negative));
+
+        let mut values = vec![
+            JoystickValue::Axis {
+                id: 0,
+                value: axis('a', 'd'),
+            },
+            JoystickValue::Axis {
+                id: 1,
+                value: axis('w', 's'),
+            },
+        ];
+        values.extend(
+            self.buttons
+                .iter()
+                .enumerate()
+                .map(|(id, key)| JoystickValue::Button {
+                    id: id as u8 + 1,
+                    pressed: self.pressed.contains(key),
+                }),
+        );
+        values.push(JoystickValue::POV {
+            id: 0,
+            angle: pov_angle(
+                self.pressed.contains(&Key::Up),
+                self.pressed.contains(&Key::Right),
+                self.pressed.contains(&Key::Down),
+                self.pressed.contains(&Key::Left),
+            ),
+        });
+        values
+    }
+}
+
+impl Default for KeyboardJoystick {
+    fn default() -> Self {
+        KeyboardJoystick::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keys_map_to_values() {
+        let mut keyboard = KeyboardJoystick::new();
+        keyboard.set(Key::Char('W'), true);
+        keyboard.set(Key::Char('d'), true);
+        keyboard.set(Key::Char('x'), true);
+        keyboard.set(Key::Left, true);
+
+        let values = keyboard.values();
+        assert_eq!(values[0], JoystickValue::Axis { id: 0, value: 1.0 });
+        assert_eq!(values[1], JoystickValue::Axis { id: 1, value: -1.0 });
+        assert_eq!(
+            values[3],
+            JoystickValue::Button {
+                id: 2,
+                pressed: true
+            }
+        );
+        assert_eq!(values[6], JoystickValue::POV { id: 0, angle: 270 });
+
+        keyboard.set(Key::Char('w'), false);
+        assert_eq!(
+            keyboard.values()[1],
+            JoystickValue::Axis { id: 1, value: 0.0 }
+        );
+    }
+}
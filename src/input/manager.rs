@@ -0,0 +1,232 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tokio::sync::broadcast;
+
+use crate::ds::state::MAX_JOYSTICKS;
+
+/// Events emitted by a [`JoystickManager`] as devices are plugged in and unplugged
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JoystickEvent {
+    /// A device was plugged in and placed in `slot`
+    Connected { slot: usize, name: String },
+    /// The device in `slot` was unplugged
+    ///
+    /// The slot is left empty rather than shifting the devices after it, and the device is put back in it if it returns.
+    Disconnected { slot: usize, name: String },
+}
+
+/// A device known to the manager, connected or not
+#[derive(Debug, Clone)]
+struct Device {
+    /// Identifies the model of the device across reconnects, such as its USB product
+    id: String,
+    name: String,
+    /// The handle the backend currently knows the device by, if it is plugged in
+    instance: Option<usize>,
+    locked: bool,
+}
+
+/// The device assigned to a joystick slot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoystickSlot {
+    /// The name reported by the device
+    pub name: String,
+    /// Whether the device is currently plugged in
+    pub connected: bool,
+    /// Whether the slot is reserved for this device, even while it is unplugged
+    pub locked: bool,
+}
+
+/// Assigns the devices read by an input backend to the joystick slots sent to the roboRIO
+///
+/// Like the joystick tab of the official driver station, devices keep their slot when unplugged and replugged, so the
+/// ports robot code binds to don't shift mid-practice. An unplugged device's slot can be taken by a new device unless it
+/// has been [locked](#method.lock), and slots can be rearranged with [`swap`](#method.swap).
+///
+/// The manager is shared with the backend's thread, so clones all refer to the same slots.
+#[derive(Clone)]
+pub struct JoystickManager {
+    slots: Arc<Mutex<Vec<Option<Device>>>>,
+    events: broadcast::Sender<JoystickEvent>,
+}
+
+impl JoystickManager {
+    pub(crate) fn new() -> JoystickManager {
+        let (events, _) = broadcast::channel(16);
+        JoystickManager {
+            slots: Arc::default(),
+            events,
+        }
+    }
+
+    fn lock_slots(&self) -> MutexGuard<'_, Vec<Option<Device>>> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Subscribes to devices being plugged in and unplugged
+    pub fn events(&self) -> broadcast::Receiver<JoystickEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns the device assigned to each slot, or `None` for empty slots
+    pub fn slots(&self) -> Vec<Option<JoystickSlot>> {
+        self.lock_slots()
+            .iter()
+            .map(|slot| {
+                slot.as_ref().map(|device| JoystickSlot {
+                    name: device.name.clone(),
+                    connected: device.instance.is_some(),
+                    locked: device.locked,
+                })
+            })
+            .collect()
+    }
+
+    /// Reserves `slot` for the device in it, so no other device takes it while that one is unplugged
+    ///
+    /// Returns false if the slot is empty.
+    pub fn lock(&self, slot: usize) -> bool {
+        match self.lock_slots().get_mut(slot) {
+            Some(Some(device)) => {
+                device.locked = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Releases a slot reserved with [`lock`](#method.lock)
+    pub fn unlock(&self, slot: usize) {
+        if let Some(Some(device)) = self.lock_slots().get_mut(slot) {
+            device.locked = false;
+        }
+    }
+
+    /// Exchanges the devices assigned to two slots, either of which may be empty
+    ///
+    /// Returns false without changing anything if either slot is past the joysticks the roboRIO accepts.
+    pub fn swap(&self, a: usize, b: usize) -> bool {
+        if a.max(b) >= MAX_JOYSTICKS {
+            return false;
+        }
+
+        let mut slots = self.lock_slots();
+        let len = a.max(b) + 1;
+        if slots.len() < len {
+            slots.resize(len, None);
+        }
+        slots.swap(a, b);
+        true
+    }
+
+    /// Places a newly plugged in device, returning its slot
+    ///
+    /// The device is put back in the slot it last had if it is still free, and otherwise takes the first slot that
+    /// is empty or held by an unplugged, unlocked device.
+    pub(crate) fn connect(&self, instance: usize, id: &str, name: &str) -> usize {
+        let mut slots = self.lock_slots();
+        if let Some(slot) = slots.iter().position(|slot| {
+            slot.as_ref()
+                .is_some_and(|device| device.instance == Some(instance))
+        }) {
+            return slot;
+        }
+
+        let returning = slots.iter().position(|slot| {
+            slot.as_ref()
+                .is_some_and(|device| device.instance.is_none() && device.id == id)
+        });
+        let slot = returning
+            .or_else(|| {
+                slots.iter().position(|slot| {
+                    slot.as_ref()
+                        .is_none_or(|device| device.instance.is_none() && !device.locked)
+                })
+            })
+            .unwrap_or_else(|| {
+                slots.push(None);
+                slots.len() - 1
+            });
+
+        let locked =
+            returning.is_some_and(|slot| slots[slot].as_ref().is_some_and(|device| device.locked));
+        slots[slot] = Some(Device {
+            id: id.to_string(),
+            name: name.to_string(),
+            instance: Some(instance),
+            locked,
+        });
+
+        let _ = self.events.send(JoystickEvent::Connected {
+            slot,
+            name: name.to_string(),
+        });
+        slot
+    }
+
+    /// Marks a device as unplugged, leaving it assigned to its slot
+    pub(crate) fn disconnect(&self, instance: usize) {
+        let mut slots = self.lock_slots();
+        let found = slots.iter_mut().enumerate().find_map(|(slot, device)| {
+            device
+                .as_mut()
+                .filter(|device| device.instance == Some(instance))
+                .map(|device| (slot, device))
+        });
+
+        if let Some((slot, device)) = found {
+            device.instance = None;
+            let _ = self.events.send(JoystickEvent::Disconnected {
+                slot,
+                name: device.name.clone(),
+            });
+        }
+    }
+
+    /// Returns the backend handle of the device plugged into each slot
+    pub(crate) fn instances(&self) -> Vec<Option<usize>> {
+        self.lock_slots()
+            .iter()
+            .map(|slot| slot.as_ref().and_then(|device| device.instance))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn devices_return_to_their_slot() {
+        let manager = JoystickManager::new();
+        assert_eq!(manager.connect(10, "xbox", "Xbox Controller"), 0);
+        assert_eq!(manager.connect(11, "stick", "Flight Stick"), 1);
+
+        manager.disconnect(10);
+        assert_eq!(manager.instances(), vec![None, Some(11)]);
+        // Reconnecting gives the device a new handle, but it is recognised by its id
+        assert_eq!(manager.connect(12, "xbox", "Xbox Controller"), 0);
+    }
+
+    #[test]
+    fn locked_slots_are_reserved() {
+        let manager = JoystickManager::new();
+        manager.connect(10, "xbox", "Xbox Controller");
+        manager.connect(11, "stick", "Flight Stick");
+        assert!(manager.lock(0));
+        manager.disconnect(10);
+        manager.disconnect(11);
+
+        // The unlocked slot is free for a new device, the locked one isn't
+        assert_eq!(manager.connect(12, "box", "Button Box"), 1);
+        assert_eq!(manager.connect(13, "xbox", "Xbox Controller"), 0);
+        assert!(manager.slots()[0].as_ref().unwrap().locked);
+
+        assert!(manager.swap(0, 2));
+        assert_eq!(manager.instances(), vec![None, Some(12), Some(13)]);
+        assert!(!manager.swap(0, MAX_JOYSTICKS));
+        assert!(!manager.swap(usize::MAX, 1));
+        assert_eq!(manager.instances(), vec![None, Some(12), Some(13)]);
+    }
+}
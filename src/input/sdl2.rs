@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use sdl2::JoystickSubsystem;
+use sdl2::joystick::{HatState, Joystick};
+
+use super::{JoystickManager, SharedJoysticks, SharedOutputs, pov_angle};
+use crate::{DriverStation, DsError, JoystickOutput, JoystickValue, Result};
+
+/// How often the backend thread reads joysticks and checks for new ones
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long SDL plays each rumble request for, which is renewed halfway through while robot code keeps it on
+const RUMBLE_DURATION: Duration = Duration::from_secs(2);
+
+/// Reads joysticks with [SDL2](https://docs.rs/sdl2), assigning each to a joystick slot as it connects
+///
+/// Unlike [`GilrsBackend`](struct.GilrsBackend.html), devices aren't mapped onto a gamepad layout. Every axis, button
+/// and hat is sent in the order SDL reports them, which suits button boxes and flight sticks with many axes.
+/// Slots are assigned by a [`JoystickManager`](struct.JoystickManager.html), so joysticks keep their slot across reconnects.
+/// Once attached, rumble set by robot code is played on joysticks that support it.
+///
+/// ```no_run
+/// # async fn run(ds: &mut ds::DriverStation) -> ds::Result<()> {
+/// let joysticks = ds::input::Sdl2Backend::spawn()?;
+/// joysticks.attach(ds).await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Sdl2Backend {
+    joysticks: SharedJoysticks,
+    manager: JoystickManager,
+    outputs: SharedOutputs,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Sdl2Backend {
+    /// Starts reading joysticks on a background thread
+    ///
+    /// Returns an error if SDL can't be initialized on this system.
+    pub fn spawn() -> Result<Sdl2Backend> {
+        let joysticks = SharedJoysticks::default();
+        let manager = JoystickManager::new();
+        let outputs = SharedOutputs::default();
+        let running = Arc::new(AtomicBool::new(true));
+        let (init_tx, init_rx) = mpsc::channel();
+
+        // SDL has to be used from the thread it was initialized on
+        let thread = {
+            let joysticks = joysticks.clone();
+            let manager = manager.clone();
+            let outputs = outputs.clone();
+            let running = running.clone();
+            thread::spawn(move || match sdl2::init().and_then(|sdl| sdl.joystick()) {
+                Ok(subsystem) => {
+                    let _ = init_tx.send(Ok(()));
+                    run(subsystem, joysticks, manager, outputs, running);
+                }
+                Err(e) => {
+                    let _ = init_tx.send(Err(DsError::Input(e)));
+                }
+            })
+        };
+
+        init_rx
+            .recv()
+            .map_err(|_| DsError::Input("Joystick thread stopped unexpectedly".to_string()))??;
+
+        Ok(Sdl2Backend {
+            joysticks,
+            manager,
+            outputs,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns the manager assigning joysticks to joystick slots
+    pub fn manager(&self) -> &JoystickManager {
+        &self.manager
+    }
+
+    /// Returns a joystick supplier that yields the latest joystick values
+    pub fn supplier(&self) -> impl Fn() -> Vec<Vec<JoystickValue>> + Send + Sync + 'static {
+        self.joysticks.supplier()
+    }
+
+    /// Sends the values of the joysticks to the roboRIO with every control packet from `ds`, and plays the rumble
+    /// robot code sets on them
+    pub async fn attach(&self, ds: &mut DriverStation) {
+        ds.set_joystick_supplier(self.supplier()).await;
+        self.outputs.attach(ds.joystick_outputs());
+    }
+}
+
+impl Drop for Sdl2Backend {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Reads joysticks until `running` is cleared, publishing their values to `joysticks`
+fn run(
+    subsystem: JoystickSubsystem,
+    joysticks: SharedJoysticks,
+    manager: JoystickManager,
+    outputs: SharedOutputs,
+    running: Arc<AtomicBool>,
+) {
+    let mut open: HashMap<usize, Joystick> = HashMap::new();
+    let mut latest: Vec<JoystickOutput> = Vec::new();
+    // The rumble last requested of each joystick, and when
+    let mut rumbles: HashMap<usize, (JoystickOutput, Instant)> = HashMap::new();
+
+    while running.load(Ordering::Relaxed) {
+        subsystem.update();
+
+        open.retain(|&instance, joystick| {
+            let attached = joystick.attached();
+            if !attached {
+                manager.disconnect(instance);
+                rumbles.remove(&instance);
+            }
+            attached
+        });
+
+        // Device indices shift as joysticks come and go, so new ones are found by their instance id
+        for index in 0..subsystem.num_joysticks().unwrap_or(0) {
+            let Ok(joystick) = subsystem.open(index) else {
+                continue;
+            };
+            let instance = joystick.instance_id() as usize;
+            if open.contains_key(&instance) {
+                continue;
+            }
+
+            manager.connect(instance, &joystick.guid().string(), &joystick.name());
+            open.insert(instance, joystick);
+        }
+
+        if let Some(outputs) = outputs.changed() {
+            latest = outputs;
+        }
+        let instances = manager.instances();
+        for (slot, instance) in instances.iter().enumerate() {
+            if let Some(instance) = *instance
+                && let Some(joystick) = open.get_mut(&instance)
+            {
+                let output = latest.get(slot).copied().unwrap_or_default();
+                rumble(joystick, &mut rumbles, instance, output);
+            }
+        }
+
+        joysticks.set(
+            instances
+                .into_iter()
+                .map(|instance| {
+                    instance
+                        .and_then(|instance| open.get(&instance))
+                        .map(read)
+                        .unwrap_or_default()
+                })
+                .collect(),
+        );
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Requests the rumble in `output` from `joystick`, renewing it before SDL stops playing it
+fn rumble(
+    joystick: &mut Joystick,
+    rumbles: &mut HashMap<usize, (JoystickOutput, Instant)>,
+    instance: usize,
+    output: JoystickOutput,
+) {
+    let idle = output.left_rumble == 0 && output.right_rumble == 0;
+    let due = match rumbles.get(&instance) {
+        Some(&(current, at)) => current != output || (!idle && at.elapsed() >= RUMBLE_DURATION / 2),
+        None => !idle,
+    };
+    if !due {
+        return;
+    }
+
+    let _ = joystick.set_rumble(
+        output.left_rumble,
+        output.right_rumble,
+        RUMBLE_DURATION.as_millis() as u32,
+    );
+    rumbles.insert(instance, (output, Instant::now()));
+}
+
+/// Reads every axis, button and hat of `joystick`
+fn read(joystick: &Joystick) -> Vec<JoystickValue> {
+    let axes = (0..joystick.num_axes()).map(|id| JoystickValue::Axis {
+        id: id as u8,
+        value: joystick
+            .axis(id)
+            .map_or(0.0, |value| value as f32 / 32767.0),
+    });
+    let buttons = (0..joystick.num_buttons()).map(|id| JoystickValue::Button {
+        id: id as u8 + 1,
+        pressed: joystick.button(id).unwrap_or(false),
+    });
+    let povs = (0..joystick.num_hats()).map(|id| JoystickValue::POV {
+        id: id as u8,
+        angle: joystick.hat(id).map_or(-1, hat_angle),
+    });
+
+    axes.chain(buttons).chain(povs).collect()
+}
+
+/// Converts the state of a hat into a POV angle
+fn hat_angle(hat: HatState) -> i16 {
+    let hat = hat.to_raw();
+    pov_angle(
+        hat & 0x01 != 0,
+        hat & 0x02 != 0,
+        hat & 0x04 != 0,
+        hat & 0x08 != 0,
+    )
+}
@@ -7,6 +7,22 @@
 //! The core trait for use of the crate is the [`DriverStation`](struct.DriverStation.html) crate. This crate
 //! provides an API for connecting and controlling to the roboRIO in an FRC robot. It also allows for users to
 //! provide joystick input using arbitrary APIs, and to consume any incoming TCP packets.
+//!
+//! With the `tracing` feature enabled, the connection tasks report what they are doing through [`tracing`](https://docs.rs/tracing),
+//! with a span for each connection and events as the connection to the roboRIO changes.
+//! The `hexdump` feature additionally logs every frame sent to and received from the roboRIO at TRACE level, under
+//! the `ds::hexdump` target, with the boundaries of each tag marked.
+//!
+//! The driver station itself is behind the default `runtime` feature, which pulls in tokio. With default features
+//! disabled only the protocol types are built, such as [`UdpResponsePacket`](struct.UdpResponsePacket.html),
+//! [`TcpPacket`](enum.TcpPacket.html) and [`Telemetry`](struct.Telemetry.html), and the crate compiles for
+//! `wasm32-unknown-unknown`. This lets a browser dashboard decode packets relayed to it, or deserialize telemetry
+//! with the `serde` feature, using the same structs as the driver station.
+//!
+//! The driver station keeps time with tokio's clock, so tests run on a runtime with paused time, such as
+//! `#[tokio::test(start_paused = true)]`, skip through enable timeouts, the watchdog, reconnection and backoff rather
+//! than waiting for them. The exceptions are [`sender_thread`](struct.DriverStationBuilder.html#method.sender_thread)
+//! and [`high_resolution_timer`](struct.DriverStationBuilder.html#method.high_resolution_timer), which keep real time.
 
 #![doc(html_root_url = "https://docs.rs/ds/1.0.1")]
 #![forbid(unsafe_code)]
@@ -15,16 +31,55 @@
 #[macro_use]
 extern crate bitflags;
 
+#[cfg(feature = "runtime")]
+#[macro_use]
+mod log;
+
+#[cfg(feature = "runtime")]
+pub mod blocking;
 mod ds;
+mod error;
 mod ext;
+#[cfg(feature = "halsim")]
+pub mod halsim;
+#[cfg(feature = "runtime")]
+pub mod input;
+#[cfg(feature = "runtime")]
+pub mod netconsole;
 mod proto;
-pub(crate) mod util;
+#[cfg(feature = "runtime")]
+pub mod riolog;
+#[cfg(feature = "egui")]
+pub mod ui;
+pub mod util;
 
-pub use self::ds::state::{DsMode, Mode};
-pub use self::ds::{DriverStation, JoystickValue};
+#[cfg(feature = "grpc")]
+pub use self::ds::GrpcServer;
+#[cfg(feature = "http")]
+pub use self::ds::HttpServer;
+#[cfg(feature = "signals")]
+pub use self::ds::TerminationAction;
+#[cfg(feature = "websocket")]
+pub use self::ds::WebSocketServer;
+#[cfg(feature = "runtime")]
+pub use self::ds::{
+    BackoffPolicy, BatteryLevel, BatteryThresholds, ChaosProfile, CsvLog, CsvLogHandle,
+    DisconnectPolicy, DriverStation, DriverStationBuilder, DsController, DsEvent, DsMonitor,
+    DsPool, EstopSource, JoystickWarning, MatchPhase, MatchTiming, OverflowPolicy, PacketTime,
+    TcpConsumerHandle, WpiLog, WpiLogHandle,
+};
+#[cfg(feature = "json")]
+pub use self::ds::{Command, TelemetryExport};
+pub use self::ds::{
+    DsMode, JoystickValue, Mode, NetStats, RobotState, RobotStateChange, Telemetry, TickStats,
+};
+#[cfg(feature = "sqlite")]
+pub use self::ds::{SqliteLog, SqliteLogHandle};
+pub use self::error::DsError;
 pub use self::proto::tcp::inbound::*;
 pub use self::proto::tcp::outbound::*;
-pub use self::proto::udp::inbound::types::Trace;
+pub use self::proto::udp::inbound::UdpResponsePacket;
+pub use self::proto::udp::inbound::types::{JoystickOutput, Status, Trace};
 pub use self::proto::udp::outbound::types::*;
 
-pub type Result<T> = std::result::Result<T, anyhow::Error>;
+pub type Result<T> = std::result::Result<T, DsError>;
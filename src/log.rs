@@ -0,0 +1,39 @@
+//! Diagnostics from the connection tasks
+//!
+//! With the `tracing` feature these are emitted as [`tracing`](https://docs.rs/tracing) events, which applications
+//! can route, filter and persist with a subscriber. Without it they are discarded.
+
+#[cfg(feature = "tracing")]
+macro_rules! debug {
+    ($($arg:tt)+) => { ::tracing::debug!($($arg)+) };
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! info {
+    ($($arg:tt)+) => { ::tracing::info!($($arg)+) };
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! warn {
+    ($($arg:tt)+) => { ::tracing::warn!($($arg)+) };
+}
+
+// The arguments are still type checked, so that the two configurations can't drift apart
+#[cfg(not(feature = "tracing"))]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! info {
+    ($($arg:tt)+) => { debug!($($arg)+) };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! warn {
+    ($($arg:tt)+) => { debug!($($arg)+) };
+}
@@ -0,0 +1,86 @@
+//! Console output from the robot over NetConsole
+//!
+//! Some robot programs and older roboRIO images send their output as UDP datagrams to port 6666, rather than over
+//! the driver station's TCP connection. With
+//! [`DriverStationBuilder::netconsole`](../struct.DriverStationBuilder.html#method.netconsole), these datagrams are
+//! decoded and merged into the console, reaching the TCP consumers and streams as [`Stdout`](../struct.Stdout.html)
+//! messages from [`ConsoleSource::NetConsole`](../enum.ConsoleSource.html#variant.NetConsole).
+
+use std::sync::Arc;
+
+use tokio::net::UdpSocket;
+
+use crate::ds::PacketTime;
+use crate::ds::state::DsState;
+use crate::{ConsoleSource, Result, Stdout, TcpPacket};
+
+/// The UDP port that the roboRIO sends NetConsole output to
+pub const NETCONSOLE_PORT: u16 = 6666;
+
+/// Decodes a NetConsole datagram into a console message
+///
+/// NetConsole sends plain text without a timestamp or seqnum, so both are 0. Bytes that aren't valid UTF-8 are
+/// replaced rather than rejected, as prints can be cut off partway through a character.
+pub fn decode(datagram: &[u8]) -> Stdout {
+    Stdout {
+        timestamp: 0.0,
+        message: String::from_utf8_lossy(datagram).into_owned(),
+        seqnum: 0,
+        source: ConsoleSource::NetConsole,
+    }
+}
+
+/// tokio task forwarding the NetConsole output received on `sock` to the console
+pub(crate) async fn netconsole_conn(state: Arc<DsState>, sock: UdpSocket) -> Result<()> {
+    // NetConsole output is usually a line at a time, but a datagram can be up to 64KiB
+    let mut buf = vec![0; u16::MAX as usize];
+    loop {
+        let (n, addr) = sock.recv_from(&mut buf).await?;
+        // Output from other robots on the network is ignored once the roboRIO has been found, as with status packets
+        if state.config().filter_source
+            && let Some(rio) = state.recv().read().await.rio_address()
+            && rio != addr.ip().to_canonical()
+        {
+            continue;
+        }
+
+        let received = PacketTime::now(&state.config().clock);
+        state
+            .publish_tcp(TcpPacket::Stdout(decode(&buf[..n])), received)
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ds::TaskGuard;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn datagrams_are_decoded_as_stdout() {
+        let stdout = decode(b"Robot program starting\n\xff");
+        assert_eq!(stdout.message, "Robot program starting\n\u{fffd}");
+        assert_eq!(stdout.source, ConsoleSource::NetConsole);
+    }
+
+    #[tokio::test]
+    async fn output_is_merged_into_the_console() {
+        let state = Arc::new(DsState::test_default());
+        let mut packets = state.tcp_packets().subscribe();
+        let sock = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = sock.local_addr().unwrap();
+        let _task = TaskGuard::spawn(netconsole_conn(state.clone(), sock));
+
+        let robot = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        robot.send_to(b"hello\n", addr).await.unwrap();
+        match packets.recv().await.unwrap() {
+            TcpPacket::Stdout(stdout) => {
+                assert_eq!(stdout.message, "hello\n");
+                assert_eq!(stdout.source, ConsoleSource::NetConsole);
+            }
+            other => panic!("Unexpected packet {:?}", other),
+        }
+        assert!(state.tcp().read().await.received().is_some());
+    }
+}
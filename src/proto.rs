@@ -1,2 +1,4 @@
+#[cfg(feature = "hexdump")]
+pub(crate) mod hexdump;
 pub mod tcp;
 pub mod udp;
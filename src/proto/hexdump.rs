@@ -0,0 +1,121 @@
+//! Hexdumps of the frames sent to and received from the roboRIO
+//!
+//! Each frame is logged at TRACE level under the `ds::hexdump` target, with ` | ` marking where each tag starts, so
+//! that new tags can be picked out without an external capture tool.
+
+use std::fmt::Write;
+
+/// A kind of frame, which decides how its tags are found
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Frame {
+    /// A control packet sent to the roboRIO
+    UdpOut,
+    /// A status packet received from the roboRIO
+    UdpIn,
+    /// A TCP frame sent to the roboRIO
+    TcpOut,
+    /// A TCP frame received from the roboRIO
+    TcpIn,
+}
+
+impl Frame {
+    /// Returns the length of the header before the first tag, and the width of each tag's length prefix
+    const fn layout(self) -> (usize, usize) {
+        match self {
+            // seqnum, comm version, control, request, alliance
+            Frame::UdpOut => (6, 1),
+            // seqnum, comm version, status, trace, battery, need date
+            Frame::UdpIn => (8, 1),
+            // Every TCP frame is a single tag
+            Frame::TcpOut | Frame::TcpIn => (0, 2),
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Frame::UdpOut => "UDP out",
+            Frame::UdpIn => "UDP in",
+            Frame::TcpOut => "TCP out",
+            Frame::TcpIn => "TCP in",
+        }
+    }
+}
+
+/// Logs a hexdump of `bytes` at TRACE level
+pub(crate) fn log(frame: Frame, bytes: &[u8]) {
+    tracing::trace!(
+        target: "ds::hexdump",
+        "{} ({} bytes): {}",
+        frame.name(),
+        bytes.len(),
+        annotate(frame, bytes)
+    );
+}
+
+/// Formats `bytes` as hex, with ` | ` before the start of each tag
+///
+/// A tag whose length runs past the end of the frame is marked as truncated.
+pub(crate) fn annotate(frame: Frame, bytes: &[u8]) -> String {
+    let (header, width) = frame.layout();
+    let mut out = String::with_capacity(bytes.len() * 3 + 16);
+    let header = header.min(bytes.len());
+    hex(&mut out, &bytes[..header]);
+
+    let mut rest = &bytes[header..];
+    while !rest.is_empty() {
+        if !out.is_empty() {
+            out.push_str(" | ");
+        }
+        let len = match *rest {
+            [len, ..] if width == 1 => Some(1 + usize::from(len)),
+            [hi, lo, ..] => Some(2 + usize::from(u16::from_be_bytes([hi, lo]))),
+            _ => None,
+        };
+        match len {
+            Some(len) if len <= rest.len() => {
+                hex(&mut out, &rest[..len]);
+                rest = &rest[len..];
+            }
+            _ => {
+                out.push_str("(truncated) ");
+                hex(&mut out, rest);
+                rest = &[];
+            }
+        }
+    }
+    out
+}
+
+fn hex(out: &mut String, bytes: &[u8]) {
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        let _ = write!(out, "{:02x}", byte);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tag_boundaries_are_marked() {
+        let control = [
+            0x00, 0x01, 0x01, 0x04, 0x00, 0x00, 0x02, 0x0e, 0x01, 0x01, 0x07,
+        ];
+        assert_eq!(
+            annotate(Frame::UdpOut, &control),
+            "00 01 01 04 00 00 | 02 0e 01 | 01 07"
+        );
+
+        let tcp = [0x00, 0x04, 0x0e, b'L', b'R', b'L'];
+        assert_eq!(annotate(Frame::TcpOut, &tcp), "00 04 0e 4c 52 4c");
+
+        let truncated = [0x00, 0x01, 0x01, 0x00, 0x30, 0x0c, 0x80, 0x00, 0x05, 0x01];
+        assert_eq!(
+            annotate(Frame::UdpIn, &truncated),
+            "00 01 01 00 30 0c 80 00 | (truncated) 05 01"
+        );
+    }
+}
@@ -1,71 +1,129 @@
-use crate::ext::BufExt;
+#[cfg(feature = "hexdump")]
+use crate::proto::hexdump::{self, Frame};
+#[cfg(feature = "runtime")]
 use crate::proto::tcp::outbound::{OutgoingTcpTag, TcpTag};
-use crate::{Stdout, TcpPacket};
-use bytes::{Buf, BytesMut};
-use std::io;
+#[cfg(feature = "runtime")]
+use crate::{DsError, TcpPacket};
+#[cfg(feature = "runtime")]
+use bytes::BytesMut;
+#[cfg(feature = "runtime")]
 use tokio_util::codec::{Decoder, Encoder};
 
 pub mod inbound;
 pub mod outbound;
 
 /// The tokio codec for TCP traffic to and from the roboRIO
+#[cfg(feature = "runtime")]
 pub struct DsTcpCodec;
 
+#[cfg(feature = "runtime")]
 impl Encoder<TcpTag> for DsTcpCodec {
-    type Error = anyhow::Error;
+    type Error = DsError;
 
     fn encode(&mut self, item: TcpTag, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        #[cfg(feature = "hexdump")]
+        let start = dst.len();
         match item {
-            TcpTag::GameData(gd) => {
-                dst.extend(gd.construct().iter());
-            }
-            TcpTag::MatchInfo(mi) => dst.extend(mi.construct().iter()),
+            TcpTag::GameData(gd) => gd.construct_into(dst),
+            TcpTag::MatchInfo(mi) => mi.construct_into(dst),
         }
+        #[cfg(feature = "hexdump")]
+        hexdump::log(Frame::TcpOut, &dst[start..]);
         Ok(())
     }
 }
 
+#[cfg(feature = "runtime")]
 impl Decoder for DsTcpCodec {
     type Item = TcpPacket;
-    type Error = anyhow::Error;
+    type Error = DsError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let mut buf = src.clone().freeze();
+        #[cfg(feature = "hexdump")]
+        let frame = src.clone();
+        let packet = TcpPacket::decode_frame(src)?;
+        #[cfg(feature = "hexdump")]
+        if packet.is_some() {
+            hexdump::log(Frame::TcpIn, &frame[..frame.len() - src.len()]);
+        }
+        Ok(packet)
+    }
+}
 
-        fn inner(buf: &mut impl Buf) -> crate::Result<(TcpPacket, usize)> {
-            let len = buf.read_u16_be()?;
+#[cfg(all(test, feature = "runtime"))]
+mod test {
+    use super::*;
 
-            let id = buf.read_u8()?;
-            match id {
-                0x0c => Ok((
-                    TcpPacket::Stdout(Stdout::decode(buf, len as usize - 1)?),
-                    len as usize + 2,
-                )),
-                _ => {
-                    for _ in 0..(len - 1) {
-                        let _ = buf.read_u8()?;
-                    }
-                    Ok((TcpPacket::Dummy, len as usize + 2))
-                }
-            }
+    #[test]
+    fn incomplete_frames_wait_for_data() {
+        let mut src = BytesMut::from(&[0x00, 0x0a, 0x0c, 0x00][..]);
+        assert!(DsTcpCodec.decode(&mut src).unwrap().is_none());
+        assert_eq!(src.len(), 4);
+    }
+
+    #[test]
+    fn malformed_frames_are_skipped() {
+        let mut src = BytesMut::new();
+        // Zero length frame
+        src.extend_from_slice(&[0x00, 0x00]);
+        // Stdout frame too short to contain a timestamp and seqnum
+        src.extend_from_slice(&[0x00, 0x02, 0x0c, 0x00]);
+        // A good frame after them
+        src.extend_from_slice(&[0x00, 0x07, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        assert!(matches!(
+            DsTcpCodec.decode(&mut src).unwrap(),
+            Some(TcpPacket::Dummy)
+        ));
+        assert!(matches!(
+            DsTcpCodec.decode(&mut src).unwrap(),
+            Some(TcpPacket::Dummy)
+        ));
+        assert!(matches!(
+            DsTcpCodec.decode(&mut src).unwrap(),
+            Some(TcpPacket::Stdout(_))
+        ));
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced() {
+        let frame = [
+            0x00, 0x09, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, b'h', 0xff,
+        ];
+        match DsTcpCodec.decode(&mut BytesMut::from(&frame[..])).unwrap() {
+            Some(TcpPacket::Stdout(stdout)) => assert_eq!(stdout.message, "h\u{fffd}"),
+            other => panic!("Unexpected packet {:?}", other),
         }
+    }
 
-        use anyhow::bail;
-        match inner(&mut buf) {
-            Ok((packet, n)) => {
-                src.advance(n);
-                Ok(Some(packet))
+    #[test]
+    fn decodes_stdout() {
+        let frame = [
+            0x00, 0x09, 0x0c, 0x3f, 0x80, 0x00, 0x00, 0x00, 0x02, b'h', b'i',
+        ];
+        let mut src = BytesMut::from(&frame[..]);
+        match DsTcpCodec.decode(&mut src).unwrap() {
+            Some(TcpPacket::Stdout(stdout)) => {
+                assert_eq!(stdout.timestamp, 1.0);
+                assert_eq!(stdout.seqnum, 2);
+                assert_eq!(stdout.message, "hi");
             }
-            Err(e) => match e.downcast::<io::Error>() {
-                Ok(e) => {
-                    if e.kind() == io::ErrorKind::UnexpectedEof {
-                        Ok(None)
-                    } else {
-                        Err(e.into())
-                    }
-                }
-                _ => bail!("Failed to decode TCP packet"),
-            },
+            other => panic!("Unexpected packet {:?}", other),
         }
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn tags_are_appended_to_the_buffer() {
+        let mut dst = BytesMut::from(&[0xaa][..]);
+        DsTcpCodec
+            .encode(
+                TcpTag::GameData(outbound::GameData {
+                    gsm: "LRL".to_string(),
+                }),
+                &mut dst,
+            )
+            .unwrap();
+        assert_eq!(dst.as_ref(), &[0xaa, 0x00, 0x04, 0x0e, b'L', b'R', b'L']);
     }
 }
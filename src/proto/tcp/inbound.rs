@@ -1,44 +1,91 @@
+use crate::DsError;
 use crate::Result as CResult;
 use crate::ext::BufExt;
-use bytes::Buf;
+use bytes::{Buf, BytesMut};
 use std::io::{Error, ErrorKind};
-use std::str;
 
 /// Enum containing possible incoming TCP packets from the roboRIO
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum TcpPacket {
     /// Contains a message from the robot code's standard output
     Stdout(Stdout),
     Dummy,
 }
 
+impl TcpPacket {
+    /// Decodes the length-prefixed frame at the start of `src`, removing it from the buffer
+    ///
+    /// Returns `Ok(None)` without consuming anything if `src` doesn't hold a complete frame yet, so that bytes
+    /// relayed from the TCP stream can be appended as they arrive. Frames other than those from the robot's standard
+    /// output are skipped, and decoded as `Dummy`. Malformed frames, including those too short to hold a tag, are
+    /// skipped the same way, so that one bad frame can't end the stream.
+    pub fn decode_frame(src: &mut BytesMut) -> CResult<Option<TcpPacket>> {
+        if src.len() < 2 {
+            return Ok(None);
+        }
+        let len = usize::from(u16::from_be_bytes([src[0], src[1]]));
+        if src.len() < len + 2 {
+            return Ok(None);
+        }
+        let mut frame = src.split_to(len + 2).freeze();
+        frame.advance(2);
+
+        let packet = match frame.read_u8() {
+            Ok(0x0c) => {
+                Stdout::decode(&mut frame, len - 1).map_or(TcpPacket::Dummy, TcpPacket::Stdout)
+            }
+            _ => TcpPacket::Dummy,
+        };
+        Ok(Some(packet))
+    }
+}
+
 pub(crate) trait IncomingTcpPacket: Sized {
     fn decode(buf: &mut impl Buf) -> CResult<Self>;
 }
 
 /// Contains data outputted to standard output from robot code. Can be consumed by API users to
 /// display code logs
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Stdout {
     pub timestamp: f32,
     pub message: String,
     pub seqnum: u16,
+    /// Where the message was received from
+    pub source: ConsoleSource,
+}
+
+/// Where a console message from the robot was received from
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum ConsoleSource {
+    /// The driver station's TCP connection to the roboRIO
+    #[default]
+    Tcp,
+    /// NetConsole datagrams sent to UDP port 6666, see [`netconsole`](netconsole/index.html)
+    NetConsole,
+    /// The riolog console on TCP port 1741, see [`riolog`](riolog/index.html)
+    Riolog,
 }
 
 impl Stdout {
     pub fn decode(buf: &mut impl Buf, len: usize) -> CResult<Self> {
         let timestamp = buf.read_f32_be()?;
         let seqnum = buf.read_u16_be()?;
-        let mut v = vec![0; len - 6];
+        let message_len = len
+            .checked_sub(6)
+            .ok_or_else(|| DsError::Decode(format!("Stdout packet too short ({} bytes)", len)))?;
+        let mut v = vec![0; message_len];
         if buf.remaining() < v.len() {
             return Err(Error::new(ErrorKind::UnexpectedEof, "Not enough data").into());
         }
         buf.copy_to_slice(&mut v[..]);
-        let message = str::from_utf8(&v[..])?;
         Ok(Stdout {
             timestamp,
-            message: message.to_string(),
+            // A message cut off partway through a character still has the rest worth showing
+            message: String::from_utf8_lossy(&v).into_owned(),
             seqnum,
+            source: ConsoleSource::Tcp,
         })
     }
 }
@@ -1,6 +1,7 @@
-use bytes::{BufMut, Bytes, BytesMut};
+use bytes::{BufMut, BytesMut};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TcpTag {
     MatchInfo(MatchInfo),
     GameData(GameData),
@@ -9,32 +10,28 @@ pub enum TcpTag {
 pub(crate) trait OutgoingTcpTag {
     fn id(&self) -> u8;
 
-    fn data(&self) -> Bytes;
+    fn write_data(&self, buf: &mut BytesMut);
 
-    fn construct(&self) -> Bytes {
-        let id = self.id();
-        let data = self.data();
-        let data_len = data.len();
-        let payload_len = 1 + data_len;
+    /// Writes the tag with its length prefix to the end of `buf`
+    fn construct_into(&self, buf: &mut BytesMut) {
+        let start = buf.len();
+        // The length is filled in once the data has been written
+        buf.put_u16(0);
+        buf.put_u8(self.id());
+        self.write_data(buf);
 
         // Check size
+        let payload_len = buf.len() - start - 2;
         assert!(
             payload_len <= u16::MAX as usize,
             "Payload too large for u16 length"
         );
-
-        let total_len = 2 + payload_len; // Total buffer size = Length field + Payload
-        let mut buf = BytesMut::with_capacity(total_len);
-
-        buf.put_u16(payload_len as u16);
-        buf.put_u8(id);
-        buf.extend_from_slice(&data);
-
-        buf.freeze()
+        buf[start..start + 2].copy_from_slice(&(payload_len as u16).to_be_bytes());
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MatchInfo {
     competition: String,
     match_type: MatchType,
@@ -46,16 +43,15 @@ impl OutgoingTcpTag for MatchInfo {
         0x07
     }
 
-    fn data(&self) -> Bytes {
-        let mut buf = BytesMut::with_capacity(1 + self.competition.len() + 1);
+    fn write_data(&self, buf: &mut BytesMut) {
         buf.put_u8(self.competition.len() as u8);
         buf.put_slice(self.competition.as_bytes());
         buf.put_u8(self.match_type as u8);
-        buf.freeze()
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GameData {
     pub gsm: String,
 }
@@ -65,16 +61,15 @@ impl OutgoingTcpTag for GameData {
         0x0e
     }
 
-    fn data(&self) -> Bytes {
-        let mut buf = BytesMut::with_capacity(self.gsm.len());
+    fn write_data(&self, buf: &mut BytesMut) {
         buf.put_slice(self.gsm.as_bytes());
-        buf.freeze()
     }
 }
 
 #[repr(u8)]
 #[derive(Debug, Copy, Clone)]
 #[allow(unused)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MatchType {
     MatchTest = 0,
     Practice = 1,
@@ -1,38 +1,57 @@
+#[cfg(feature = "runtime")]
+use crate::DsError;
+#[cfg(feature = "hexdump")]
+use crate::proto::hexdump::{self, Frame};
+#[cfg(feature = "runtime")]
 use crate::proto::udp::inbound::UdpResponsePacket;
+#[cfg(feature = "runtime")]
 use crate::proto::udp::outbound::UdpControlPacket;
+#[cfg(feature = "runtime")]
 use bytes::BytesMut;
+#[cfg(feature = "runtime")]
 use tokio_util::codec::{Decoder, Encoder};
 
 pub mod inbound;
 pub mod outbound;
 
 /// The tokio codec for UDP traffic to and from the roboRIO
+#[cfg(feature = "runtime")]
 pub struct DsUdpCodec;
 
+#[cfg(feature = "runtime")]
 impl Decoder for DsUdpCodec {
     type Item = UdpResponsePacket;
-    type Error = anyhow::Error;
+    type Error = DsError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        // UdpFramed keeps decoding from the same datagram until None is returned,
+        // so an exhausted datagram must not be reported as an error.
+        if src.is_empty() {
+            return Ok(None);
+        }
+        #[cfg(feature = "hexdump")]
+        hexdump::log(Frame::UdpIn, src);
+
         match UdpResponsePacket::decode(src) {
-            Ok(packet) => {
-                Ok(Some(packet))
-            }
+            Ok(packet) => Ok(Some(packet)),
             // In other Decoder implementations, the error is checked and if it was due
             // to a lack of data, Ok(None) is returned.
-            // This implementation does it differently due to a bug in UDPFramed assuming there's nothing left
-            // to decode if None is ever returned.
-            Err(e) => Err(e),
+            // A datagram is never split across reads, so a short read means the packet is malformed,
+            // and the rest of it is discarded.
+            Err(e) => {
+                src.clear();
+                Err(e)
+            }
         }
     }
 }
 
+#[cfg(feature = "runtime")]
 impl Encoder<UdpControlPacket> for DsUdpCodec {
-    type Error = anyhow::Error;
+    type Error = DsError;
 
     fn encode(&mut self, item: UdpControlPacket, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        println!("a");
-        dst.extend(item.encode());
+        item.encode_into(dst);
 
         Ok(())
     }
@@ -9,53 +9,50 @@ use crate::ext::BufExt;
 use bytes::Buf;
 
 /// Response packet sent by the RIO over UDP every ~20ms.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct UdpResponsePacket {
     pub seqnum: u16,
     pub status: Status,
     pub trace: Trace,
     pub battery: f32,
+    /// The high and low bytes that `battery` was decoded from, as sent by the roboRIO
+    pub battery_raw: [u8; 2],
     pub need_date: bool,
+    /// The outputs set for each joystick, in port order
+    pub joystick_outputs: Vec<JoystickOutput>,
 }
 
 impl UdpResponsePacket {
     /// Attempts to decode a valid response packet from the given buffer
     /// Will return Err() if any of the reads fail.
+    ///
+    /// Unknown bits in the status and trace bytes are discarded rather than rejected,
+    /// so that newer roboRIO images don't break decoding.
     pub fn decode(buf: &mut impl Buf) -> Result<UdpResponsePacket> {
         let seqnum = buf.read_u16_be()?;
         let _comm_version = buf.read_u8()?;
-        let status = Status::from_bits(buf.read_u8()?).unwrap();
-        let trace = Trace::from_bits(buf.read_u8()?).unwrap();
-        let battery = {
-            let high = buf.read_u8()?;
-            let low = buf.read_u8()?;
-            f32::from(high) + f32::from(low) / 256f32
-        };
+        let status = Status::from_bits_truncate(buf.read_u8()?);
+        let trace = Trace::from_bits_truncate(buf.read_u8()?);
+        let battery_raw = [buf.read_u8()?, buf.read_u8()?];
+        let battery = f32::from(battery_raw[0]) + f32::from(battery_raw[1]) / 256f32;
         let need_date = buf.read_u8()? == 1;
-        while let Ok(tag_id) = buf.read_u8() {
-            match tag_id {
-                0x01 => {
-                    types::JoystickOutput::chomp(buf)?;
-                }
-                0x04 => {
-                    types::DiskInfo::chomp(buf)?;
-                }
-                0x05 => {
-                    types::CPUInfo::chomp(buf)?;
-                }
-                0x06 => {
-                    types::RAMInfo::chomp(buf)?;
-                }
-                0x08 => {
-                    types::PDPLog::chomp(buf)?;
-                }
-                0x09 => {
-                    types::Unknown::chomp(buf)?;
-                }
-                0x0e => {
-                    types::CANMetrics::chomp(buf)?;
-                }
-                _ => {}
+        let mut joystick_outputs = Vec::new();
+        // Each tag is framed as its size, then its id and data, which the size counts
+        while let Ok(size) = buf.read_u8() {
+            let size = usize::from(size);
+            if size == 0 {
+                continue;
+            }
+            // A tag running past the end of the packet can't be read, but the rest of the packet still can
+            if buf.remaining() < size {
+                break;
+            }
+            let mut tag = buf.copy_to_bytes(size);
+            // Disk, CPU, RAM, PDP, CAN and the other tags aren't used yet, and are skipped by their size
+            if tag.get_u8() == 0x01
+                && let Ok(output) = types::JoystickOutput::chomp(&mut tag)
+            {
+                joystick_outputs.push(output);
             }
         }
 
@@ -65,7 +62,93 @@ impl UdpResponsePacket {
                 status,
                 trace,
                 battery,
+                battery_raw,
                 need_date,
+                joystick_outputs,
             })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::XorShift;
+
+    #[test]
+    fn unknown_status_bits_are_ignored() {
+        let mut buf = &[0x00, 0x01, 0x01, 0b0110_0100, 0b1110_0001, 0x0c, 0x80, 0x00][..];
+        let packet = UdpResponsePacket::decode(&mut buf).unwrap();
+        assert_eq!(packet.seqnum, 1);
+        assert_eq!(packet.status, Status::ENABLED);
+        assert_eq!(packet.trace, Trace::ROBOT_CODE | Trace::DISABLED);
+        assert_eq!(packet.battery, 12.5);
+        assert_eq!(packet.battery_raw, [0x0c, 0x80]);
+        assert!(!packet.need_date);
+    }
+
+    #[test]
+    fn joystick_outputs_are_decoded_in_order() {
+        let mut buf = &[
+            0x00, 0x01, 0x01, 0x00, 0x30, 0x0c, 0x80, 0x00,
+            // Joystick 0
+            0x09, 0x01, 0x00, 0x00, 0x00, 0x05, 0xff, 0xff, 0x00, 0x00,
+            // Joystick 1
+            0x09, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00,
+        ][..];
+        let packet = UdpResponsePacket::decode(&mut buf).unwrap();
+        assert_eq!(
+            packet.joystick_outputs,
+            vec![
+                JoystickOutput {
+                    outputs: 5,
+                    left_rumble: 0xffff,
+                    right_rumble: 0
+                },
+                JoystickOutput {
+                    outputs: 0,
+                    left_rumble: 0,
+                    right_rumble: 0x8000
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_tags_are_skipped_by_size() {
+        let mut buf = &[
+            0x00, 0x01, 0x01, 0x00, 0x30, 0x0c, 0x80, 0x00,
+            // An unknown tag, then a CPU tag shorter than any roboRIO image sends
+            0x03, 0x7f, 0x01, 0x02, 0x02, 0x05, 0x00,
+            0x09, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+            // A tag running past the end of the packet
+            0x09, 0x01, 0x00,
+        ][..];
+        let packet = UdpResponsePacket::decode(&mut buf).unwrap();
+        assert_eq!(
+            packet.joystick_outputs,
+            vec![JoystickOutput {
+                outputs: 1,
+                left_rumble: 0,
+                right_rumble: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn truncated_packets_are_errors() {
+        let full = [0x00, 0x01, 0x01, 0x00, 0x30, 0x0c, 0x80, 0x01];
+        for len in 0..full.len() {
+            let mut buf = &full[..len];
+            assert!(UdpResponsePacket::decode(&mut buf).is_err());
+        }
+    }
+
+    #[test]
+    fn random_input_never_panics() {
+        let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+        for len in 0..512 {
+            let data: Vec<u8> = (0..len).map(|_| rng.next_u64() as u8).collect();
+            let _ = UdpResponsePacket::decode(&mut &data[..]);
+        }
+    }
+}
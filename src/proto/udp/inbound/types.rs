@@ -3,34 +3,32 @@ use crate::ext::BufExt;
 use crate::util::InboundTag;
 use bytes::Buf;
 
-macro_rules! gen_stub_tags {
-    ($($struct_name:ident : $num_bytes:expr),*) => {
-        $(
-        pub(crate) struct $struct_name {
-            _data: [u8; $num_bytes]
-        }
-
-        impl InboundTag for $struct_name {
-            fn chomp(buf: &mut impl Buf) -> Result<Self> {
-                let mut _data = [0; $num_bytes];
-
-                for i in 0..$num_bytes {
-                    _data[i] = buf.read_u8()?;
-                }
+/// The outputs and rumble that robot code has set for a joystick, sent back by the roboRIO
+///
+/// The roboRIO sends one of these tags for each joystick, in port order.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct JoystickOutput {
+    /// The HID outputs set by robot code, one bit per output
+    pub outputs: u32,
+    /// The strength of the left, low frequency rumble motor
+    pub left_rumble: u16,
+    /// The strength of the right, high frequency rumble motor
+    pub right_rumble: u16,
+}
 
-                Ok($struct_name { _data })
-            }
-        }
-        )*
+impl InboundTag for JoystickOutput {
+    fn chomp(buf: &mut impl Buf) -> Result<Self> {
+        Ok(JoystickOutput {
+            outputs: buf.read_u32_be()?,
+            left_rumble: buf.read_u16_be()?,
+            right_rumble: buf.read_u16_be()?,
+        })
     }
 }
 
-// UDP tags should be eaten to ensure the pipe doesn't get clogged, but for now proper structs aren't implemented.
-gen_stub_tags!(PDPLog : 25, JoystickOutput : 8, DiskInfo : 4, CPUInfo : 20, RAMInfo : 8, Unknown : 9, CANMetrics : 14);
-
-
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Status: u8 {
         const ESTOP = 0b1000_0000;
         const BROWNOUT = 0b0001_0000;
@@ -58,6 +56,7 @@ impl Status {
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Trace: u8 {
         const ROBOT_CODE = 0b0010_0000;
         const IS_ROBORIO = 0b0001_0000;
@@ -1,6 +1,8 @@
 pub mod types;
 
 use self::types::*;
+#[cfg(feature = "hexdump")]
+use crate::proto::hexdump::{self, Frame};
 use bytes::{BufMut, Bytes, BytesMut};
 
 /// UDP control packet to send to the roboRIO
@@ -15,7 +17,19 @@ pub struct UdpControlPacket {
 impl UdpControlPacket {
     /// Encodes the current state of the packet into a vec to send to the roboRIO
     pub fn encode(&self) -> Bytes {
-        let mut buf = BytesMut::with_capacity(2 + 1 + 1 + 1 + 1);
+        let mut buf = BytesMut::new();
+        self.encode_into(&mut buf);
+        buf.freeze()
+    }
+
+    /// Encodes the current state of the packet to the end of `buf`
+    ///
+    /// Packets are sent 50 times a second, so reusing one buffer saves an allocation each tick. Once the bytes split
+    /// off from the buffer have been dropped, its memory is reclaimed the next time it grows.
+    pub fn encode_into(&self, buf: &mut BytesMut) {
+        #[cfg(feature = "hexdump")]
+        let start = buf.len();
+        buf.reserve(2 + 1 + 1 + 1 + 1);
         buf.put_u16(self.seqnum);
         buf.put_u8(0x01);
         buf.put_u8(self.control.bits());
@@ -27,10 +41,10 @@ impl UdpControlPacket {
         buf.put_u8(self.alliance.0);
 
         for tag in self.tags.iter() {
-            buf.extend(tag.construct());
+            tag.construct_into(buf);
         }
-
-        buf.freeze()
+        #[cfg(feature = "hexdump")]
+        hexdump::log(Frame::UdpOut, &buf[start..]);
 
         // let mut buf = vec![];
         // buf.write_u16::<BigEndian>(self.seqnum).unwrap();
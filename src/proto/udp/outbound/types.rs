@@ -1,10 +1,14 @@
-use bytes::{BufMut, Bytes, BytesMut};
+use std::fmt;
+use std::str::FromStr;
 
-// Assuming this helper still exists and returns Vec<u8> or preferably Bytes
-use crate::util::to_u8_vec;
+use bytes::{BufMut, BytesMut};
+
+use crate::DsError;
+use crate::util::put_buttons;
 
 /// Enum wrapping possible outgoing UDP tags
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UdpTag {
     /// Tag sent to inform user code of the time left in the current mode
     Countdown(Countdown),
@@ -21,36 +25,38 @@ pub(crate) trait Tag: Send {
     /// Returns the unique ID byte for this tag type.
     fn id(&self) -> u8;
 
-    /// Returns the serialized data payload for this tag as immutable Bytes.
-    fn data(&self) -> Bytes;
+    /// Writes the serialized data payload for this tag to the end of `buf`.
+    fn write_data(&self, buf: &mut BytesMut);
 
-    /// Constructs the final tag bytes including the length prefix and ID.
+    /// Writes the final tag bytes including the length prefix and ID to the end of `buf`.
     /// Format: Length (u8) | ID (u8) | Data (...)
-    fn construct(&self) -> Bytes {
-        let id_byte = self.id();
-        let data_bytes = self.data();
-        let data_len = data_bytes.len();
-
-        let payload_len = 1 + data_len;
-
+    fn construct_into(&self, buf: &mut BytesMut) {
+        let start = buf.len();
+        // The length is filled in once the data has been written
+        buf.put_u8(0);
+        buf.put_u8(self.id());
+        self.write_data(buf);
+
+        let payload_len = buf.len() - start - 1;
         assert!(
             payload_len <= u8::MAX as usize,
             "Tag payload too large for u8 length field"
         );
+        buf[start] = payload_len as u8;
+    }
 
-        let total_len = 1 + payload_len;
-        let mut buf = BytesMut::with_capacity(total_len);
-
-        buf.put_u8(payload_len as u8);
-        buf.put_u8(id_byte);
-        buf.put(data_bytes);
-
-        buf.freeze()
+    /// Returns the final tag bytes in a buffer of their own
+    #[cfg(test)]
+    fn construct(&self) -> BytesMut {
+        let mut buf = BytesMut::new();
+        self.construct_into(&mut buf);
+        buf
     }
 }
 
 /// Tag containing the time remaining in the current mode
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Countdown {
     seconds_remaining: f32,
 }
@@ -70,15 +76,14 @@ impl Tag for Countdown {
         0x07
     }
 
-    fn data(&self) -> Bytes {
-        let mut buf = BytesMut::with_capacity(4);
+    fn write_data(&self, buf: &mut BytesMut) {
         buf.put_f32(self.seconds_remaining);
-        buf.freeze()
     }
 }
 
 /// Tag containing values from joysticks
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Joysticks {
     axes: Vec<i8>,
     buttons: Vec<bool>,
@@ -94,6 +99,21 @@ impl Joysticks {
             povs,
         }
     }
+
+    #[inline(always)]
+    pub(crate) fn axes(&self) -> &[i8] {
+        &self.axes
+    }
+
+    #[inline(always)]
+    pub(crate) fn buttons(&self) -> &[bool] {
+        &self.buttons
+    }
+
+    #[inline(always)]
+    pub(crate) fn povs(&self) -> &[i16] {
+        &self.povs
+    }
 }
 
 impl Tag for Joysticks {
@@ -102,16 +122,7 @@ impl Tag for Joysticks {
         0x0c
     }
 
-    fn data(&self) -> Bytes {
-        let buttons_packed = to_u8_vec(&self.buttons);
-        let capacity = 1 // axes count
-                     + self.axes.len() // Each i8 is 1 byte
-                     + 1 // button count
-                     + buttons_packed.len()
-                     + 1 // pov count
-                     + (self.povs.len() * 2); // Each i16 is 2 bytes
-        let mut buf = BytesMut::with_capacity(capacity);
-
+    fn write_data(&self, buf: &mut BytesMut) {
         assert!(
             self.axes.len() <= u8::MAX as usize,
             "Too many axes for u8 count"
@@ -127,7 +138,7 @@ impl Tag for Joysticks {
             "Too many buttons for u8 count"
         );
         buf.put_u8(self.buttons.len() as u8);
-        buf.extend_from_slice(&buttons_packed);
+        put_buttons(buf, &self.buttons);
 
         assert!(
             self.povs.len() <= u8::MAX as usize,
@@ -137,13 +148,12 @@ impl Tag for Joysticks {
         for pov in &self.povs {
             buf.put_i16(*pov);
         }
-
-        buf.freeze()
     }
 }
 
 /// Tag containing the current date and time in UTC
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DateTime {
     micros: u32,
     second: u8,
@@ -183,8 +193,7 @@ impl Tag for DateTime {
         0x0f
     }
 
-    fn data(&self) -> Bytes {
-        let mut buf = BytesMut::with_capacity(4 + 1 + 1 + 1 + 1 + 1 + 1);
+    fn write_data(&self, buf: &mut BytesMut) {
         buf.put_u32(self.micros);
         buf.put_u8(self.second);
         buf.put_u8(self.minute);
@@ -192,12 +201,12 @@ impl Tag for DateTime {
         buf.put_u8(self.day);
         buf.put_u8(self.month);
         buf.put_u8(self.year);
-        buf.freeze()
     }
 }
 
 /// Tag containing the current timezone of the RIO
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Timezone {
     tz: String,
 }
@@ -215,12 +224,11 @@ impl Tag for Timezone {
         0x10
     }
 
-    fn data(&self) -> Bytes {
-        Bytes::from(self.tz.clone())
+    fn write_data(&self, buf: &mut BytesMut) {
+        buf.put_slice(self.tz.as_bytes());
     }
 }
 
-
 bitflags! {
     /// bitflag struct for the Control value of the packet
     pub struct Control: u8 {
@@ -244,7 +252,11 @@ bitflags! {
 }
 
 /// Struct abstracting the byte value for alliance colour and position
+///
+/// The byte can be set directly, so isn't guaranteed to name a valid station. [`AllianceStation`](enum.AllianceStation.html)
+/// can only hold valid stations, and converts into an `Alliance` wherever one is needed.
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Alliance(pub u8);
 
 impl Alliance {
@@ -283,6 +295,173 @@ impl Alliance {
     pub const fn position(self) -> u8 {
         (self.0 % 3) + 1
     }
+
+    /// Creates a new `Alliance` for the given colour and position
+    #[inline(always)]
+    pub const fn new(color: AllianceColor, position: u8) -> Alliance {
+        match color {
+            AllianceColor::Red => Alliance::new_red(position),
+            AllianceColor::Blue => Alliance::new_blue(position),
+        }
+    }
+
+    /// Returns the colour of the alliance `self` is on
+    #[inline(always)]
+    pub const fn color(self) -> AllianceColor {
+        if self.is_red() {
+            AllianceColor::Red
+        } else {
+            AllianceColor::Blue
+        }
+    }
+
+    /// Returns the colour and position of the alliance station for `self`
+    #[inline(always)]
+    pub const fn station(self) -> (AllianceColor, u8) {
+        (self.color(), self.position())
+    }
+}
+
+impl From<AllianceStation> for Alliance {
+    fn from(station: AllianceStation) -> Alliance {
+        Alliance(station as u8)
+    }
+}
+
+impl From<Alliance> for (AllianceColor, u8) {
+    fn from(alliance: Alliance) -> (AllianceColor, u8) {
+        alliance.station()
+    }
+}
+
+/// Formats the alliance station as the official driver station and FMS name it, such as "Red 1"
+impl fmt::Display for Alliance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.color(), self.position())
+    }
+}
+
+/// Parses an alliance station such as "Red 1" or "blue3", or the short form "R1" or "B3"
+impl FromStr for Alliance {
+    type Err = DsError;
+
+    fn from_str(s: &str) -> Result<Alliance, DsError> {
+        let invalid = || DsError::InvalidAlliance(s.to_string());
+        let trimmed = s.trim();
+        let split = trimmed.len().checked_sub(1).ok_or_else(invalid)?;
+        let (color, position) = trimmed.split_at_checked(split).ok_or_else(invalid)?;
+
+        let color = color.trim_end().parse().map_err(|_| invalid())?;
+        let position = match position {
+            "1" => 1,
+            "2" => 2,
+            "3" => 3,
+            _ => return Err(invalid()),
+        };
+        Ok(Alliance::new(color, position))
+    }
+}
+
+/// The colour of an alliance
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AllianceColor {
+    Red,
+    Blue,
+}
+
+impl fmt::Display for AllianceColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AllianceColor::Red => "Red",
+            AllianceColor::Blue => "Blue",
+        })
+    }
+}
+
+/// Parses "Red" or "Blue", or "R" or "B", ignoring case
+impl FromStr for AllianceColor {
+    type Err = DsError;
+
+    fn from_str(s: &str) -> Result<AllianceColor, DsError> {
+        match s.to_ascii_lowercase().as_str() {
+            "red" | "r" => Ok(AllianceColor::Red),
+            "blue" | "b" => Ok(AllianceColor::Blue),
+            _ => Err(DsError::InvalidAlliance(s.to_string())),
+        }
+    }
+}
+
+/// One of the six alliance stations, encoded as the byte sent in control packets
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum AllianceStation {
+    Red1 = 0,
+    Red2 = 1,
+    Red3 = 2,
+    Blue1 = 3,
+    Blue2 = 4,
+    Blue3 = 5,
+}
+
+impl AllianceStation {
+    /// Returns the colour of the alliance this station is on
+    #[inline(always)]
+    pub const fn color(self) -> AllianceColor {
+        Alliance(self as u8).color()
+    }
+
+    /// Returns the position of this station within its alliance, from 1 to 3
+    #[inline(always)]
+    pub const fn position(self) -> u8 {
+        Alliance(self as u8).position()
+    }
+}
+
+impl TryFrom<u8> for AllianceStation {
+    type Error = DsError;
+
+    fn try_from(byte: u8) -> Result<AllianceStation, DsError> {
+        match byte {
+            0 => Ok(AllianceStation::Red1),
+            1 => Ok(AllianceStation::Red2),
+            2 => Ok(AllianceStation::Red3),
+            3 => Ok(AllianceStation::Blue1),
+            4 => Ok(AllianceStation::Blue2),
+            5 => Ok(AllianceStation::Blue3),
+            _ => Err(DsError::InvalidAlliance(format!("{:#04x}", byte))),
+        }
+    }
+}
+
+/// Fails if the byte of `alliance` doesn't name a valid station
+impl TryFrom<Alliance> for AllianceStation {
+    type Error = DsError;
+
+    fn try_from(alliance: Alliance) -> Result<AllianceStation, DsError> {
+        AllianceStation::try_from(alliance.0)
+    }
+}
+
+impl From<AllianceStation> for u8 {
+    fn from(station: AllianceStation) -> u8 {
+        station as u8
+    }
+}
+
+impl fmt::Display for AllianceStation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Alliance::from(*self).fmt(f)
+    }
+}
+
+impl FromStr for AllianceStation {
+    type Err = DsError;
+
+    fn from_str(s: &str) -> Result<AllianceStation, DsError> {
+        s.parse::<Alliance>().and_then(AllianceStation::try_from)
+    }
 }
 
 #[cfg(test)]
@@ -307,7 +486,7 @@ mod test {
         assert_eq!(
             buf.as_ref(),
             &[
-                0x0D, 0x0c, 0x03, 0x80, 0x00, 0x7F, 0x09, 0x05, 0x01, 0x02, 0x00, 0x00, 0x46, 0x50
+                0x0D, 0x0c, 0x03, 0x80, 0x00, 0x7F, 0x09, 0x01, 0x05, 0x02, 0x00, 0x00, 0x46, 0x50
             ]
         );
     }
@@ -324,6 +503,40 @@ mod test {
         );
     }
 
+    #[test]
+    fn alliance_round_trips_through_strings() {
+        let alliance: Alliance = "Blue 3".parse().unwrap();
+        assert_eq!(alliance.station(), (AllianceColor::Blue, 3));
+        assert_eq!(alliance.to_string(), "Blue 3");
+
+        assert_eq!("r2".parse::<Alliance>().unwrap().to_string(), "Red 2");
+        assert_eq!(
+            "Red1".parse::<Alliance>().unwrap().station(),
+            (AllianceColor::Red, 1)
+        );
+        assert!("Red 4".parse::<Alliance>().is_err());
+        assert!("Green 1".parse::<Alliance>().is_err());
+        assert!("".parse::<Alliance>().is_err());
+    }
+
+    #[test]
+    fn alliance_station_matches_alliance_bytes() {
+        assert_eq!(
+            Alliance::from(AllianceStation::Red1).0,
+            Alliance::new_red(1).0
+        );
+        assert_eq!(
+            Alliance::from(AllianceStation::Blue3).0,
+            Alliance::new_blue(3).0
+        );
+        assert_eq!(
+            AllianceStation::try_from(Alliance::new_blue(2)).unwrap(),
+            AllianceStation::Blue2
+        );
+        assert!(AllianceStation::try_from(Alliance(6)).is_err());
+        assert_eq!(AllianceStation::Red3.to_string(), "Red 3");
+    }
+
     #[test]
     fn verify_timezone_format() {
         let tz = Timezone::new("UTC");
@@ -0,0 +1,107 @@
+//! Console output from the robot over riolog
+//!
+//! The roboRIO also serves its console on TCP port 1741, which VS Code's riolog viewer reads. Its frames are the
+//! same as the console messages on the driver station's own TCP connection. With
+//! [`DriverStationBuilder::riolog`](../struct.DriverStationBuilder.html#method.riolog), the console is read from this
+//! port instead, reaching the TCP consumers and streams as [`Stdout`](../struct.Stdout.html) messages from
+//! [`ConsoleSource::Riolog`](../enum.ConsoleSource.html#variant.Riolog).
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio_stream::StreamExt;
+use tokio_util::codec::FramedRead;
+
+use crate::ds::PacketTime;
+use crate::ds::connect_tcp;
+use crate::ds::state::DsState;
+use crate::proto::tcp::DsTcpCodec;
+use crate::{ConsoleSource, Result, TcpPacket};
+
+/// The TCP port that the roboRIO serves riolog on
+pub const RIOLOG_PORT: u16 = 1741;
+
+/// How long to wait before connecting again, once the roboRIO hasn't been found or the connection has closed
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Marks a console message as received over riolog
+fn from_riolog(packet: TcpPacket) -> TcpPacket {
+    match packet {
+        TcpPacket::Stdout(mut stdout) => {
+            stdout.source = ConsoleSource::Riolog;
+            TcpPacket::Stdout(stdout)
+        }
+        packet => packet,
+    }
+}
+
+/// tokio task reading the console from riolog on `port` of the roboRIO that was found, reconnecting whenever it is lost
+pub(crate) async fn riolog_conn(state: Arc<DsState>, port: u16) -> Result<()> {
+    loop {
+        let rio = state.recv().read().await.rio_address();
+        if let Some(ip) = rio {
+            match connect_tcp(state.config(), SocketAddr::new(ip, port)).await {
+                Ok(conn) => {
+                    info!("Connected to riolog at {}", ip);
+                    let mut frames = FramedRead::new(conn, DsTcpCodec);
+                    while let Some(packet) = frames.next().await {
+                        match packet {
+                            Ok(packet) => {
+                                let received = PacketTime::now(&state.config().clock);
+                                state.publish_tcp(from_riolog(packet), received).await;
+                            }
+                            Err(e) => {
+                                warn!("Error decoding riolog: {}", e);
+                                break;
+                            }
+                        }
+                    }
+                    info!("riolog connection to {} closed", ip);
+                }
+                Err(e) => debug!("Couldn't connect to riolog at {}: {}", ip, e),
+            }
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ds::TaskGuard;
+    use std::net::Ipv4Addr;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    #[tokio::test(start_paused = true)]
+    async fn reconnects_once_the_roborio_is_found() {
+        let state = Arc::new(DsState::test_default());
+        let mut packets = state.tcp_packets().subscribe();
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let _task = TaskGuard::spawn(riolog_conn(state.clone(), port));
+
+        tokio::time::sleep(RECONNECT_DELAY * 3).await;
+        state
+            .recv()
+            .write()
+            .await
+            .set_rio_address(Some(Ipv4Addr::LOCALHOST.into()));
+
+        for message in [&b"one"[..], b"two"] {
+            let (mut conn, _) = listener.accept().await.unwrap();
+            let mut frame = vec![0x00, 0x07 + message.len() as u8, 0x0c, 0, 0, 0, 0, 0, 1];
+            frame.extend_from_slice(message);
+            conn.write_all(&frame).await.unwrap();
+
+            match packets.recv().await.unwrap() {
+                TcpPacket::Stdout(stdout) => {
+                    assert_eq!(stdout.message.as_bytes(), message);
+                    assert_eq!(stdout.source, ConsoleSource::Riolog);
+                }
+                other => panic!("Unexpected packet {:?}", other),
+            }
+        }
+    }
+}
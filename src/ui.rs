@@ -0,0 +1,214 @@
+//! Widgets for embedding a driver station in an [`egui`](https://docs.rs/egui) application
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use egui::{Button, Color32, Response, RichText, Ui, Widget};
+use tokio::sync::{mpsc, watch};
+use tokio::time::MissedTickBehavior;
+
+use crate::ds::TaskGuard;
+use crate::ds::state::DsState;
+use crate::{DriverStation, Mode, Result, Telemetry, Trace};
+
+/// How often the state shown by a panel is refreshed
+const REFRESH_PERIOD: Duration = Duration::from_millis(50);
+
+const MODES: [(Mode, &str); 3] = [
+    (Mode::Teleoperated, "Teleoperated"),
+    (Mode::Autonomous, "Autonomous"),
+    (Mode::Test, "Test"),
+];
+
+/// A panel with the controls and status of a driver station, like the operation tab of the official one
+///
+/// The panel shows whether the robot is communicating and running code, its battery voltage, and the mode it is in,
+/// with buttons to enable, disable and E-stop it. It is drawn with [`show`](#method.show), or added as a widget.
+///
+/// egui draws synchronously, so the panel keeps a snapshot of the driver station that a background task refreshes,
+/// and passes commands to that task to be applied. The task is stopped when the panel is dropped.
+pub struct DsPanel {
+    telemetry: watch::Receiver<Telemetry>,
+    actions: mpsc::UnboundedSender<Action>,
+    errors: mpsc::UnboundedReceiver<String>,
+    /// The error returned by the last command, shown until another is sent
+    error: Option<String>,
+    _task: TaskGuard<()>,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Action {
+    Enable,
+    Disable,
+    Estop,
+    SetMode(Mode),
+}
+
+impl Action {
+    async fn apply(self, state: &DsState) -> Result<()> {
+        match self {
+            Action::Enable => state.enable().await?,
+            Action::Disable => state.disable().await,
+            Action::Estop => state.estop_now(),
+            Action::SetMode(mode) => state.send().write().await.set_mode(mode),
+        }
+        Ok(())
+    }
+}
+
+impl DsPanel {
+    /// Creates a panel controlling `ds`
+    ///
+    /// The panel remains usable after `ds` is dropped, though its commands no longer reach the robot.
+    pub async fn new(ds: &DriverStation) -> DsPanel {
+        DsPanel::from_state(ds.state().clone()).await
+    }
+
+    async fn from_state(state: Arc<DsState>) -> DsPanel {
+        let (telemetry_tx, telemetry) = watch::channel(Telemetry::capture(&state).await);
+        let (actions, mut actions_rx) = mpsc::unbounded_channel::<Action>();
+        let (errors_tx, errors) = mpsc::unbounded_channel();
+
+        let task = TaskGuard::spawn_on(&state.runtime(), async move {
+            let mut refresh = tokio::time::interval(REFRESH_PERIOD);
+            refresh.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                tokio::select! {
+                    _ = refresh.tick() => {}
+                    action = actions_rx.recv() => match action {
+                        Some(action) => {
+                            if let Err(e) = action.apply(&state).await {
+                                let _ = errors_tx.send(e.to_string());
+                            }
+                        }
+                        None => break,
+                    },
+                }
+                // Refreshed after commands too, so that their effect is shown on the next frame
+                telemetry_tx.send_replace(Telemetry::capture(&state).await);
+            }
+        });
+
+        DsPanel {
+            telemetry,
+            actions,
+            errors,
+            error: None,
+            _task: task,
+        }
+    }
+
+    fn send(&mut self, action: Action) {
+        self.error = None;
+        let _ = self.actions.send(action);
+    }
+
+    /// Draws the panel, returning the response for the area it covers
+    ///
+    /// A repaint is requested so that the panel keeps up with the robot, even without any input.
+    pub fn show(&mut self, ui: &mut Ui) -> Response {
+        while let Ok(error) = self.errors.try_recv() {
+            self.error = Some(error);
+        }
+        let telemetry = *self.telemetry.borrow();
+        ui.ctx().request_repaint_after(REFRESH_PERIOD);
+
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                indicator(ui, telemetry.connected, "Communications");
+                indicator(
+                    ui,
+                    telemetry.trace.contains(Trace::ROBOT_CODE),
+                    "Robot code",
+                );
+            });
+            ui.label(
+                RichText::new(format!("{:.2} V", telemetry.battery_voltage))
+                    .size(24.0)
+                    .strong(),
+            );
+
+            let mut mode = telemetry.mode;
+            ui.horizontal(|ui| {
+                for (value, label) in MODES {
+                    ui.selectable_value(&mut mode, value, label);
+                }
+            });
+            if mode != telemetry.mode {
+                self.send(Action::SetMode(mode));
+            }
+
+            ui.horizontal(|ui| {
+                let enable = Button::new(RichText::new("Enable").color(Color32::GREEN))
+                    .selected(telemetry.enabled);
+                if ui.add_enabled(!telemetry.estopped, enable).clicked() {
+                    self.send(Action::Enable);
+                }
+                let disable = Button::new(RichText::new("Disable").color(Color32::RED))
+                    .selected(!telemetry.enabled);
+                if ui.add(disable).clicked() {
+                    self.send(Action::Disable);
+                }
+            });
+            let estop = Button::new(RichText::new("E-Stop").strong().color(Color32::WHITE))
+                .fill(Color32::DARK_RED);
+            if ui.add(estop).clicked() {
+                self.send(Action::Estop);
+            }
+
+            if telemetry.estopped {
+                ui.colored_label(Color32::RED, "E-stopped");
+            }
+            if let Some(ref error) = self.error {
+                ui.colored_label(Color32::RED, error);
+            }
+        })
+        .response
+    }
+}
+
+impl Widget for &mut DsPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        self.show(ui)
+    }
+}
+
+impl fmt::Debug for DsPanel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DsPanel").finish_non_exhaustive()
+    }
+}
+
+/// Draws a label with a green or red light in front of it
+fn indicator(ui: &mut Ui, on: bool, label: &str) {
+    let color = if on { Color32::GREEN } else { Color32::RED };
+    ui.colored_label(color, "●");
+    ui.label(label);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn commands_are_applied_to_the_driver_station() {
+        let state = Arc::new(DsState::test_default());
+        let mut panel = DsPanel::from_state(state.clone()).await;
+
+        let ctx = egui::Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| ui.add(&mut panel));
+        });
+
+        panel.send(Action::SetMode(Mode::Autonomous));
+        panel.send(Action::Enable);
+        tokio::time::sleep(REFRESH_PERIOD).await;
+        assert_eq!(panel.telemetry.borrow().mode, Mode::Autonomous);
+        assert!(panel.telemetry.borrow().enabled);
+
+        panel.send(Action::Estop);
+        tokio::time::sleep(REFRESH_PERIOD).await;
+        assert!(state.estop_requested());
+    }
+}
@@ -1,52 +1,142 @@
-use bytes::Buf;
+//! Helpers for mapping team numbers to the addresses used on FRC networks, and for encoding joystick values
 
-/// Translates boolean button values into bytes expected by the roboRIO.
+use bytes::{Buf, BufMut};
+use std::net::Ipv4Addr;
+
+use crate::DsError;
+
+/// Writes boolean button values to `buf` as the bytes expected by the roboRIO.
 /// Encoding: LSB 0 (first bool = bit 0 of the byte).
 /// Byte Order: First chunk of 8 booleans corresponds to the *last* byte in the output.
-pub(crate) fn to_u8_vec(vec_in: &[bool]) -> Vec<u8> {
-    // Calculate needed bytes, rounding up
-    let num_bytes = vec_in.len().div_ceil(8);
-    let mut result = Vec::with_capacity(num_bytes);
-
-    // Iterate over input in chunks of 8
-    for chunk_start in (0..vec_in.len()).step_by(8) {
-        let mut byte: u8 = 0;
-        // Build one byte, processing up to 8 bits
-        for bit_pos in 0..8 {
-            let bool_idx = chunk_start + bit_pos;
-
-            // Safely handle slices not perfectly divisible by 8
-            if let Some(&value) = vec_in.get(bool_idx) {
-                if value {
-                    // Set bit 'bit_pos' if true (LSB-first)
-                    byte |= 1 << bit_pos;
-                }
-            } else {
-                break; // Reached end of input slice
-            }
-        }
-        result.push(byte);
+pub(crate) fn put_buttons(buf: &mut impl BufMut, buttons: &[bool]) {
+    // Chunks are written last to first, so the first chunk ends up as the last byte
+    for chunk in buttons.chunks(8).rev() {
+        let byte = chunk
+            .iter()
+            .enumerate()
+            .fold(0u8, |byte, (bit_pos, &pressed)| {
+                byte | ((pressed as u8) << bit_pos)
+            });
+        buf.put_u8(byte);
+    }
+}
+
+/// The highest team number that has a 10.TE.AM.x address
+pub const MAX_TEAM_NUMBER: u16 = 25599;
+
+/// Returns the static address of the roboRIO for the given team, 10.TE.AM.2
+///
+/// Returns an error if the team number is too large to be encoded in an address.
+pub fn team_ip(team: u16) -> crate::Result<Ipv4Addr> {
+    if team > MAX_TEAM_NUMBER {
+        return Err(DsError::InvalidTeamNumber(team));
     }
 
-    // Reverse byte order (first chunk processed becomes last byte)
-    result.reverse();
+    Ok(Ipv4Addr::new(10, (team / 100) as u8, (team % 100) as u8, 2))
+}
 
-    result
+/// Parses the team number out of an address in a team's 10.TE.AM.x subnet
+///
+/// Returns `None` if `ip` doesn't belong to a team subnet.
+pub fn team_from_ip(ip: Ipv4Addr) -> Option<u16> {
+    match ip.octets() {
+        [10, te, am, _] if am < 100 => Some(u16::from(te) * 100 + u16::from(am)),
+        _ => None,
+    }
 }
 
-/// Converts the given team number into a String containing the IP of the roboRIO
-/// Assumes the roboRIO will exist at 10.TE.AM.2
-/// Optimized version using integer arithmetic.
-pub(crate) fn ip_from_team_number(team: u16) -> String {
-    if team < 100 {
-        // Format as 10.0.AM.2 where AM is the team number
-        format!("10.0.{}.2", team)
-    } else {
-        // Covers 3, 4, and 5 digit numbers (team >= 100 and team < 100000)
-        let te = team / 100; // Integer division gives the "TE" part
-        let am = team % 100; // Modulo gives the "AM" part
-        format!("10.{}.{}.2", te, am)
+/// Converts an axis value in `-1.0..=1.0` into the byte sent to the roboRIO
+///
+/// Like the official driver station, negative values are scaled by 128 and positive values by 127, so that both
+/// -1.0 and 1.0 reach the ends of the range. Values outside the range are clamped, and NaN is sent as 0.
+pub fn axis_to_i8(value: f32) -> i8 {
+    if value.is_nan() {
+        return 0;
     }
+
+    let value = value.clamp(-1.0, 1.0);
+    let scale = if value < 0.0 { 128.0 } else { 127.0 };
+    (value * scale).round() as i8
+}
+
+/// Converts an axis byte received by the roboRIO back into a value in `-1.0..=1.0`, as WPILib does
+pub fn axis_from_i8(value: i8) -> f32 {
+    let scale = if value < 0 { 128.0 } else { 127.0 };
+    value as f32 / scale
+}
+
+/// A small xorshift64 random number generator
+///
+/// Used where randomness only needs to be cheap and reproducible, not secure.
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> XorShift {
+        // xorshift gets stuck at 0
+        XorShift(seed.max(1))
+    }
+
+    /// Seeds the generator from the system clock
+    pub fn from_time() -> XorShift {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        XorShift::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a random number in `0.0..1.0`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// The address of a roboRIO connected over USB
+pub(crate) const USB_TARGET: &str = "172.22.11.2";
+
+/// How the addresses that the roboRIO is looked for at are chosen, kept so they can be derived again for a new team
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TargetSpec {
+    /// The static address, 10.TE.AM.2
+    Static,
+    /// The mDNS hostname, `roboRIO-TEAM-frc.local`
+    Mdns,
+    /// The USB, mDNS, and static addresses, as the official driver station probes
+    Auto,
+    /// Addresses given explicitly, which don't depend on the team number
+    Custom(Vec<String>),
+}
+
+impl TargetSpec {
+    /// Returns the addresses that the roboRIO of the given team is looked for at, in order of preference
+    ///
+    /// Returns an error if the team number is too large to be mapped to an address, even if the addresses don't use it.
+    pub(crate) fn targets(&self, team: u16) -> crate::Result<Vec<String>> {
+        let ip = team_ip(team)?.to_string();
+
+        Ok(match self {
+            TargetSpec::Static => vec![ip],
+            TargetSpec::Mdns => vec![mdns_hostname_from_team_number(team)],
+            TargetSpec::Auto => vec![
+                USB_TARGET.to_string(),
+                mdns_hostname_from_team_number(team),
+                ip,
+            ],
+            TargetSpec::Custom(targets) => targets.clone(),
+        })
+    }
+}
+
+/// Returns the mDNS hostname advertised by the roboRIO of the given team
+pub(crate) fn mdns_hostname_from_team_number(team: u16) -> String {
+    format!("roboRIO-{}-frc.local", team)
 }
 
 pub(crate) trait InboundTag {
@@ -54,3 +144,54 @@ pub(crate) trait InboundTag {
     where
         Self: Sized;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn team_ip_round_trips() {
+        assert_eq!(team_ip(4533).unwrap(), Ipv4Addr::new(10, 45, 33, 2));
+        assert_eq!(team_ip(254).unwrap(), Ipv4Addr::new(10, 2, 54, 2));
+        assert_eq!(team_ip(7).unwrap(), Ipv4Addr::new(10, 0, 7, 2));
+
+        for team in [1, 42, 254, 4533, MAX_TEAM_NUMBER] {
+            assert_eq!(team_from_ip(team_ip(team).unwrap()), Some(team));
+        }
+    }
+
+    #[test]
+    fn targets_use_the_team_ip() {
+        assert_eq!(TargetSpec::Static.targets(4533).unwrap(), ["10.45.33.2"]);
+        assert_eq!(
+            TargetSpec::Auto.targets(254).unwrap(),
+            ["172.22.11.2", "roboRIO-254-frc.local", "10.2.54.2"]
+        );
+        assert!(TargetSpec::Static.targets(MAX_TEAM_NUMBER + 1).is_err());
+        assert!(TargetSpec::Mdns.targets(MAX_TEAM_NUMBER + 1).is_err());
+    }
+
+    #[test]
+    fn axes_reach_both_ends() {
+        assert_eq!(axis_to_i8(-1.0), -128);
+        assert_eq!(axis_to_i8(1.0), 127);
+        assert_eq!(axis_to_i8(0.0), 0);
+        assert_eq!(axis_to_i8(0.5), 64);
+        assert_eq!(axis_to_i8(2.0), 127);
+        assert_eq!(axis_to_i8(f32::NAN), 0);
+
+        for raw in [i8::MIN, -64, 0, 64, i8::MAX] {
+            assert_eq!(axis_to_i8(axis_from_i8(raw)), raw);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_teams() {
+        assert!(matches!(
+            team_ip(MAX_TEAM_NUMBER + 1),
+            Err(DsError::InvalidTeamNumber(25600))
+        ));
+        assert_eq!(team_from_ip(Ipv4Addr::new(10, 45, 100, 2)), None);
+        assert_eq!(team_from_ip(Ipv4Addr::new(192, 168, 1, 2)), None);
+    }
+}